@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use crate::{execute::Stats, statistics::statistic::Statistics};
+
+/// Stopping rule of the adaptive mode: a benchmark is iterated until the
+/// relative 95% confidence interval of `metric` is narrower than `target`.
+///
+/// At least `min_iterations` are always run. No more iterations are started
+/// once `max_iterations` is reached or the time budget of the whole suite is
+/// spent.
+#[derive(Debug, Clone)]
+pub struct AdaptiveIterations {
+    pub metric: String,
+    /// Target half width of the confidence interval relative to the mean,
+    /// e.g. `0.01` for ±1%.
+    pub target: f64,
+    pub min_iterations: usize,
+    pub max_iterations: usize,
+    deadline: Option<Instant>,
+}
+
+impl AdaptiveIterations {
+    pub fn new(
+        metric: String,
+        target: f64,
+        min_iterations: usize,
+        max_iterations: usize,
+        budget: Option<Duration>,
+    ) -> Self {
+        let min_iterations = min_iterations.max(1);
+        AdaptiveIterations {
+            metric,
+            target,
+            min_iterations,
+            max_iterations: max_iterations.max(min_iterations),
+            deadline: budget.map(|budget| Instant::now() + budget),
+        }
+    }
+
+    /// Whether another iteration is needed, given the samples measured so far.
+    pub fn needs_more(&self, samples: &[&Stats]) -> bool {
+        let n = samples.len();
+        if n < self.min_iterations {
+            return true;
+        }
+        if n >= self.max_iterations || self.is_out_of_budget() {
+            return false;
+        }
+
+        let values = samples
+            .iter()
+            .filter_map(|s| s.stats.get(&self.metric).copied())
+            .collect::<Vec<_>>();
+        if values.len() < n {
+            eprintln!(
+                "Metric `{}` is missing in the results, stop iterating.",
+                self.metric
+            );
+            return false;
+        }
+
+        Statistics::from(values).relative_ci95(n) > self.target
+    }
+
+    pub fn is_out_of_budget(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod test_adaptive {
+    use std::time::Duration;
+
+    use crate::execute::Stats;
+
+    use super::AdaptiveIterations;
+
+    fn samples(values: &[f64]) -> Vec<Stats> {
+        values
+            .iter()
+            .map(|v| {
+                let mut stats = Stats::new();
+                stats.insert(String::from("wall-time"), *v);
+                stats
+            })
+            .collect()
+    }
+
+    /// Test for AdaptiveIterations
+    ///
+    /// Step1: Verify min/max iterations bound the stopping rule.
+    ///
+    /// Step2: Verify stable samples stop early and noisy ones do not.
+    ///
+    /// Step3: Verify a spent time budget stops iterating.
+    #[test]
+    fn test_needs_more() {
+        let adaptive = AdaptiveIterations::new(String::from("wall-time"), 0.01, 3, 6, None);
+
+        let stable = samples(&[1.0, 1.001, 0.999, 1.0]);
+        let noisy = samples(&[1.0, 1.5, 0.7, 1.2, 0.9, 1.3]);
+
+        assert!(adaptive.needs_more(&stable.iter().take(2).collect::<Vec<_>>()));
+        assert!(!adaptive.needs_more(&stable.iter().take(3).collect::<Vec<_>>()));
+        assert!(adaptive.needs_more(&noisy.iter().take(5).collect::<Vec<_>>()));
+        assert!(!adaptive.needs_more(&noisy.iter().collect::<Vec<_>>()));
+
+        let other = AdaptiveIterations::new(String::from("instructions:u"), 0.01, 3, 6, None);
+        assert!(!other.needs_more(&noisy.iter().take(3).collect::<Vec<_>>()));
+
+        let out_of_budget =
+            AdaptiveIterations::new(String::from("wall-time"), 0.01, 3, 6, Some(Duration::ZERO));
+        assert!(out_of_budget.is_out_of_budget());
+        assert!(out_of_budget.needs_more(&noisy.iter().take(2).collect::<Vec<_>>()));
+        assert!(!out_of_budget.needs_more(&noisy.iter().take(3).collect::<Vec<_>>()));
+    }
+}
@@ -1,8 +1,9 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{create_dir_all, read_dir, File},
     mem::ManuallyDrop,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{bail, Context, Ok};
@@ -10,10 +11,16 @@ use serde::Deserialize;
 use tempfile::TempDir;
 
 use crate::{
+    benchmark::{
+        failure::{BenchmarkFailure, FailureKind},
+        patch::Patch,
+        run_options::RunOptions,
+    },
     compile_time::{
         cargo_package_process::CargoPackageProcess, cargo_single_process::CargoSingleProcess,
         CompileTimeProcessor,
     },
+    execute::Stats,
     runtime::{
         cargo_bench_process::CargoBenchProcess, cargo_example_process::CargoExampleProcess,
         cargo_package_process::CargoPackageTestProcess, cargo_test_process::CargoTestProcess,
@@ -21,9 +28,16 @@ use crate::{
     },
     statistics::compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
     toolchain::{Compiler, PerfTool},
+    utils::{checkpoint::Checkpoint, command::TimeoutGuard},
 };
 
-use super::{profile::Profile, scenario::Scenario};
+use super::{
+    adaptive::AdaptiveIterations,
+    feature_set::FeatureSet,
+    profile::{CustomProfile, Profile},
+    scaling::Scaling,
+    scenario::Scenario,
+};
 
 fn default_runs() -> usize {
     3
@@ -52,22 +66,31 @@ impl Benchamrk {
 
     pub fn measure_compile_time(
         &self,
-        perf_tool: &PerfTool,
-        event_filter_file: &PathBuf,
+        options: &RunOptions,
         profiles: &[Profile],
         scenarios: &[Scenario],
         compiler: Compiler<'_>,
-        iterations: Option<usize>,
-        flame_graph_result_path: &Path,
-        flamegraph_flag: i32,
-        out_dir: &Path,
+        checkpoint: &mut Checkpoint<CompileTimeResult>,
+        failures: &mut Vec<BenchmarkFailure>,
     ) -> anyhow::Result<CompileTimeBenchResult> {
+        let RunOptions {
+            perf_tool,
+            event_filter_file,
+            iterations,
+            adaptive,
+            flame_graph_path: flame_graph_result_path,
+            flamegraph_flag,
+            out_dir,
+        } = *options;
         if self.config.disabled {
             println!("Skipping {}: disabled", self.name);
             bail!("disabled benchmark");
         }
 
-        let iterations = iterations.unwrap_or(self.config.runs);
+        let iterations = match adaptive {
+            Some(adaptive) => adaptive.max_iterations,
+            None => iterations.unwrap_or(self.config.runs),
+        };
 
         let mut bench_result = CompileTimeBenchResult::new(self.name.clone(), iterations);
 
@@ -78,22 +101,99 @@ impl Benchamrk {
 
         println!("Preparing {}...", self.name);
 
-        let profile_dirs = profiles
-            .iter()
-            .map(|profile| Ok((*profile, self.make_temp_dir(&self.path)?)))
+        let _timeout = TimeoutGuard::new(self.timeout());
+
+        let patches = self.patches();
+        if scenarios.contains(&Scenario::IncrPatched) && patches.is_empty() {
+            println!(
+                "No patches found for {}, scenario {:?} will be skipped.",
+                self.name,
+                Scenario::IncrPatched
+            );
+        }
+
+        // Every profile is measured once per feature set.
+        let profile_dirs = self
+            .feature_sets()
+            .into_iter()
+            .flat_map(|feature_set| {
+                profiles
+                    .iter()
+                    .map(move |profile| (feature_set.clone(), profile.clone()))
+            })
+            .map(|(feature_set, profile)| {
+                Ok((feature_set, profile, self.make_temp_dir(&self.path)?))
+            })
             .collect::<anyhow::Result<Vec<_>>>()?;
 
-        for (profile, dir) in profile_dirs {
-            let mut result_map = HashMap::new();
-            scenarios.iter().for_each(|scenario| {
-                result_map.insert(scenario.clone(), vec![]);
-            });
+        for (feature_set, profile, dir) in profile_dirs {
+            // Runs recorded by a previous run are not recorded again, and
+            // iterations with all of them recorded are skipped.
+            let recorded_runs = checkpoint
+                .records()
+                .iter()
+                .filter(|r| {
+                    r.benchmark == self.name
+                        && r.profile == profile
+                        && r.feature_set == feature_set.name
+                })
+                .map(|r| (r.iteration, r.scenario, r.patch.clone()))
+                .collect::<HashSet<_>>();
+            let mut runs = vec![];
+            if scenarios.contains(&Scenario::Full) {
+                runs.push((Scenario::Full, None));
+            }
+            // Rustdoc does not support incremental compilation
+            if profile != Profile::Doc {
+                for scenario in [Scenario::IncrFull, Scenario::IncrUnchanged] {
+                    if scenarios.contains(&scenario) {
+                        runs.push((scenario, None));
+                    }
+                }
+                if scenarios.contains(&Scenario::IncrPatched) {
+                    for patch in &patches {
+                        runs.push((Scenario::IncrPatched, Some(patch.name.clone())));
+                    }
+                }
+            }
 
             for i in 0..iterations {
+                if let Some(adaptive) = adaptive {
+                    if !self.needs_more_iterations(
+                        adaptive,
+                        checkpoint.records(),
+                        &profile,
+                        &feature_set.name,
+                    ) {
+                        println!(
+                            "'{}' {:?} [{}] is stable after {} iterations",
+                            self.name, profile, feature_set.name, i
+                        );
+                        break;
+                    }
+                }
+                let recorded = |scenario: Scenario, patch: Option<&String>| {
+                    recorded_runs.contains(&(i + 1, scenario, patch.cloned()))
+                };
+                if runs
+                    .iter()
+                    .all(|(scenario, patch)| recorded(*scenario, patch.as_ref()))
+                {
+                    println!(
+                        "skipping '{}' {:?} [{}] iteration {}/{}: already recorded",
+                        self.name,
+                        profile,
+                        feature_set.name,
+                        i + 1,
+                        iterations
+                    );
+                    continue;
+                }
                 println!(
-                    "running '{}' {:?} + {:?} iteration {}/{}",
+                    "running '{}' {:?} [{}] + {:?} iteration {}/{}",
                     self.name,
                     profile,
+                    feature_set.name,
                     scenarios,
                     i + 1,
                     iterations
@@ -102,8 +202,13 @@ impl Benchamrk {
 
                 let cwd = timing_dir.path();
 
-                if scenarios.contains(&Scenario::Full) {
-                    let mut cargo_process = self.make_cargo_process(compiler, cwd, profile);
+                // Full builds do not depend on each other, so a recorded one
+                // is not measured again.
+                let measure_full =
+                    scenarios.contains(&Scenario::Full) && !recorded(Scenario::Full, None);
+                if measure_full {
+                    let mut cargo_process =
+                        self.make_cargo_process(compiler, cwd, &profile, &feature_set);
                     cargo_process.gen_pkg()?;
                     let stats = cargo_process
                         .run_rustc(perf_tool, event_filter_file, true)
@@ -112,14 +217,16 @@ impl Benchamrk {
                     match stats {
                         Result::Ok(stats) => {
                             if let Some(stats) = stats {
-                                let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
-                                result_vec.push(CompileTimeResult::new(
-                                    self.name.clone(),
-                                    i + 1,
-                                    profile,
-                                    Scenario::Full,
-                                    stats,
-                                ));
+                                checkpoint.record(
+                                    CompileTimeResult::new(
+                                        self.name.clone(),
+                                        i + 1,
+                                        profile.clone(),
+                                        Scenario::Full,
+                                        stats,
+                                    )
+                                    .with_feature_set(feature_set.name.clone()),
+                                )?;
                             }
                         }
                         Err(s) => {
@@ -127,6 +234,12 @@ impl Benchamrk {
                                 "Fail to bench {} with Profile {:?}: {:?}. Skip.",
                                 self.name, profile, s
                             );
+                            failures.push(BenchmarkFailure::new(self.name.clone(), &s).with_run(
+                                profile.clone(),
+                                feature_set.name.clone(),
+                                Scenario::Full,
+                                i + 1,
+                            ));
                             drop(cargo_process);
                             drop(ManuallyDrop::into_inner(timing_dir));
                             continue;
@@ -138,31 +251,54 @@ impl Benchamrk {
                     // An incremental  from scratch (slowest incremental case).
                     // This is required for any subsequent incremental builds.
                     if scenarios.iter().any(|s| s.is_increment()) {
-                        let mut process = self.make_cargo_process(compiler, cwd, profile);
-                        process.increment(true);
-                        let stats = process
-                            .run_rustc(perf_tool, event_filter_file, true)
-                            .with_context(|| format!("Fail to run rust_c for {}", self.name));
+                        if !measure_full {
+                            self.make_cargo_process(compiler, cwd, &profile, &feature_set)
+                                .gen_pkg()?;
+                        }
+                        let stats = self.measure_incremental(
+                            compiler,
+                            cwd,
+                            &profile,
+                            &feature_set,
+                            perf_tool,
+                            event_filter_file,
+                        );
 
                         match stats {
                             Result::Ok(stats) => {
                                 if let Some(stats) = stats {
-                                    let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
-                                    result_vec.push(CompileTimeResult::new(
-                                        self.name.clone(),
-                                        i + 1,
-                                        profile,
-                                        Scenario::Full,
-                                        stats,
-                                    ));
+                                    if scenarios.contains(&Scenario::IncrFull)
+                                        && !recorded(Scenario::IncrFull, None)
+                                    {
+                                        checkpoint.record(
+                                            CompileTimeResult::new(
+                                                self.name.clone(),
+                                                i + 1,
+                                                profile.clone(),
+                                                Scenario::IncrFull,
+                                                stats,
+                                            )
+                                            .with_feature_set(feature_set.name.clone()),
+                                        )?;
+                                    }
                                 }
                             }
                             Err(s) => {
                                 eprintln!(
-                                    "Fail to bench {} with Profile {:?}: {:?}. Skip.",
-                                    self.name, profile, s
+                                    "Fail to bench {} with Profile {:?} + {:?}: {:?}. Skip.",
+                                    self.name,
+                                    profile,
+                                    Scenario::IncrFull,
+                                    s
+                                );
+                                failures.push(
+                                    BenchmarkFailure::new(self.name.clone(), &s).with_run(
+                                        profile.clone(),
+                                        feature_set.name.clone(),
+                                        Scenario::IncrFull,
+                                        i + 1,
+                                    ),
                                 );
-                                drop(process);
                                 drop(ManuallyDrop::into_inner(timing_dir));
                                 continue;
                             }
@@ -170,28 +306,139 @@ impl Benchamrk {
                     }
 
                     // An incremental build with no changes (fastest incremental case).
-                    if scenarios.contains(&Scenario::IncrUnchanged) {
-                        let mut process = self.make_cargo_process(compiler, cwd, profile);
-                        process.increment(true);
-                        let stats = process.run_rustc(perf_tool, event_filter_file, true)?;
-                        let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
-
-                        if let Some(stats) = stats {
-                            result_vec.push(CompileTimeResult::new(
-                                self.name.clone(),
-                                i + 1,
-                                profile,
-                                Scenario::Full,
-                                stats,
-                            ));
+                    // It leaves the build state as it is, so a recorded one is skipped.
+                    if scenarios.contains(&Scenario::IncrUnchanged)
+                        && !recorded(Scenario::IncrUnchanged, None)
+                    {
+                        let stats = self.measure_incremental(
+                            compiler,
+                            cwd,
+                            &profile,
+                            &feature_set,
+                            perf_tool,
+                            event_filter_file,
+                        );
+
+                        match stats {
+                            Result::Ok(stats) => {
+                                if let Some(stats) = stats {
+                                    checkpoint.record(
+                                        CompileTimeResult::new(
+                                            self.name.clone(),
+                                            i + 1,
+                                            profile.clone(),
+                                            Scenario::IncrUnchanged,
+                                            stats,
+                                        )
+                                        .with_feature_set(feature_set.name.clone()),
+                                    )?;
+                                }
+                            }
+                            Err(s) => {
+                                eprintln!(
+                                    "Fail to bench {} with Profile {:?} + {:?}: {:?}. Skip.",
+                                    self.name,
+                                    profile,
+                                    Scenario::IncrUnchanged,
+                                    s
+                                );
+                                failures.push(
+                                    BenchmarkFailure::new(self.name.clone(), &s).with_run(
+                                        profile.clone(),
+                                        feature_set.name.clone(),
+                                        Scenario::IncrUnchanged,
+                                        i + 1,
+                                    ),
+                                );
+                                drop(ManuallyDrop::into_inner(timing_dir));
+                                continue;
+                            }
                         }
                     }
 
-                    // If BenchTool is PerfRecord, we need to move perf.data
-                    // out of the temp dir.
-                    match perf_tool.get_bencher() {
-                        crate::toolchain::Bencher::PerfStat => (),
-                        crate::toolchain::Bencher::PerfRecord => {
+                    // Incremental builds with some changes (realistic incremental case).
+                    // Patches are applied in order on top of each other, and each of
+                    // them produces a result of its own.
+                    if scenarios.contains(&Scenario::IncrPatched) {
+                        for patch in &patches {
+                            if let Err(s) = patch.apply(cwd) {
+                                eprintln!(
+                                    "Fail to apply patch {} for {}: {:?}. Skip the rest patches.",
+                                    patch.name, self.name, s
+                                );
+                                failures.push(BenchmarkFailure {
+                                    kind: FailureKind::PatchError,
+                                    ..BenchmarkFailure::new(
+                                        self.name.clone(),
+                                        &s.context(format!("fail to apply patch {}", patch.name)),
+                                    )
+                                    .with_run(
+                                        profile.clone(),
+                                        feature_set.name.clone(),
+                                        Scenario::IncrPatched,
+                                        i + 1,
+                                    )
+                                });
+                                break;
+                            }
+
+                            let stats = self.measure_incremental(
+                                compiler,
+                                cwd,
+                                &profile,
+                                &feature_set,
+                                perf_tool,
+                                event_filter_file,
+                            );
+
+                            // Later patches build on top of this one, so it is
+                            // measured even if it has been recorded.
+                            match stats {
+                                Result::Ok(stats) => {
+                                    if let Some(stats) = stats.filter(|_| {
+                                        !recorded(Scenario::IncrPatched, Some(&patch.name))
+                                    }) {
+                                        checkpoint.record(
+                                            CompileTimeResult::new(
+                                                self.name.clone(),
+                                                i + 1,
+                                                profile.clone(),
+                                                Scenario::IncrPatched,
+                                                stats,
+                                            )
+                                            .with_feature_set(feature_set.name.clone())
+                                            .with_patch(patch.name.clone()),
+                                        )?;
+                                    }
+                                }
+                                Err(s) => {
+                                    eprintln!(
+                                        "Fail to bench {} with Profile {:?} + {:?} ({}): {:?}. Skip the rest patches.",
+                                        self.name,
+                                        profile,
+                                        Scenario::IncrPatched,
+                                        patch.name,
+                                        s
+                                    );
+                                    failures.push(
+                                        BenchmarkFailure::new(self.name.clone(), &s).with_run(
+                                            profile.clone(),
+                                            feature_set.name.clone(),
+                                            Scenario::IncrPatched,
+                                            i + 1,
+                                        ),
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                    }
+
+                    // If BenchTool is PerfRecord or a valgrind tool, we need to
+                    // move its output (e.g. perf.data) out of the temp dir.
+                    match perf_tool.get_bencher().output_file() {
+                        None => (),
+                        Some(output_file) => {
                             let cwd = timing_dir.path();
                             let dst_dir = out_dir.join(self.name.clone());
 
@@ -201,16 +448,17 @@ impl Benchamrk {
                             // and copy it into directory dst_dir.
                             for entry in read_dir(cwd)? {
                                 let entry = entry?;
-                                if entry.file_name().to_str().unwrap().contains("perf.data") {
+                                if entry.file_name().to_str().unwrap().contains(output_file) {
                                     let src_path = PathBuf::from(entry.file_name());
                                     let dst_path = dst_dir.join(format!(
-                                        "{}_{:02}_{}",
+                                        "{}_{}_{:02}_{}",
                                         profile,
+                                        feature_set.name,
                                         i,
                                         src_path.as_os_str().to_str().unwrap()
                                     ));
                                     if let Err(err) = std::fs::copy(cwd.join(src_path), &dst_path) {
-                                        eprintln!("Failed to copy 'perf.data' file: {}", err);
+                                        eprintln!("Failed to copy '{}' file: {}", output_file, err);
                                     }
                                 }
                             }
@@ -220,24 +468,32 @@ impl Benchamrk {
                 drop(ManuallyDrop::into_inner(timing_dir));
             }
 
-            result_map.iter().for_each(|(_scenario, results)| {
-                results.iter().for_each(|result| {
+            // Results of this run and of the resumed one.
+            checkpoint
+                .records()
+                .iter()
+                .filter(|r| {
+                    r.benchmark == self.name
+                        && r.profile == profile
+                        && r.feature_set == feature_set.name
+                })
+                .for_each(|result| {
                     bench_result.add_result(result.clone());
                 });
-            });
 
             //draw flamegraph with Full Scenario
             if flamegraph_flag > 0 {
                 println!(
-                    "drawing flamegraph for '{}' {:?} + {:?}",
+                    "drawing flamegraph for '{}' {:?} [{}] + {:?}",
                     self.name,
                     profile,
+                    feature_set.name,
                     Scenario::Full
                 );
                 let timing_dir = ManuallyDrop::new(self.make_temp_dir(dir.path())?);
                 let cwd = timing_dir.path();
 
-                let cargo_process = self.make_cargo_process(compiler, cwd, profile);
+                let cargo_process = self.make_cargo_process(compiler, cwd, &profile, &feature_set);
                 cargo_process.gen_pkg()?;
                 cargo_process.draw_flame_graph(flame_graph_result_path)?;
 
@@ -246,9 +502,113 @@ impl Benchamrk {
             }
         }
 
+        if adaptive.is_some() {
+            // Benchmarks stop after different numbers of iterations.
+            bench_result.iterations = bench_result
+                .result_vec
+                .iter()
+                .map(|r| r.iteration)
+                .max()
+                .unwrap_or(0);
+        }
+
         Ok(bench_result)
     }
 
+    /// Whether any scenario measured with `profile` and `feature_set` needs
+    /// another iteration in adaptive mode.
+    fn needs_more_iterations(
+        &self,
+        adaptive: &AdaptiveIterations,
+        records: &[CompileTimeResult],
+        profile: &Profile,
+        feature_set: &str,
+    ) -> bool {
+        let mut samples = HashMap::<(Scenario, Option<String>), Vec<&Stats>>::new();
+        records
+            .iter()
+            .filter(|r| {
+                r.benchmark == self.name && r.profile == *profile && r.feature_set == feature_set
+            })
+            .for_each(|r| {
+                samples
+                    .entry((r.scenario, r.patch.clone()))
+                    .or_default()
+                    .push(&r.stats)
+            });
+        samples.is_empty() || samples.values().any(|s| adaptive.needs_more(s))
+    }
+
+    /// Run an incremental build of the leaf crate in `cwd`. Incremental
+    /// state is kept in `cwd`, so subsequent calls reuse it.
+    fn measure_incremental(
+        &self,
+        compiler: Compiler<'_>,
+        cwd: &Path,
+        profile: &Profile,
+        feature_set: &FeatureSet,
+        perf_tool: &PerfTool,
+        event_filter_file: &PathBuf,
+    ) -> anyhow::Result<Option<Stats>> {
+        let mut process = self.make_cargo_process(compiler, cwd, profile, feature_set);
+        process.increment(true);
+        process
+            .run_rustc(perf_tool, event_filter_file, true)
+            .with_context(|| format!("Fail to run rust_c for {}", self.name))
+    }
+
+    /// Cargo arguments selecting a `Profile::Custom`. Profiles not defined in
+    /// the config are expected to be defined in the Cargo.toml of the benchmark.
+    pub fn profile_cargo_args(&self, profile: &Profile) -> Vec<String> {
+        match profile {
+            Profile::Custom(name) => match self.config.profiles.get(name) {
+                Some(custom) => custom.cargo_args(name),
+                None => vec![String::from("--profile"), name.clone()],
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Arguments passed after `--` to the final crate. `cargo_rustc_opts` are
+    /// rustc flags, which rustdoc does not accept, so `Profile::Doc` gets none.
+    pub fn profile_rustc_args(&self, profile: &Profile) -> Vec<String> {
+        match profile {
+            Profile::Doc => vec![],
+            _ => self
+                .config
+                .cargo_rustc_opts
+                .clone()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    /// Feature sets listed in perf-config, or the default features only.
+    pub fn feature_sets(&self) -> Vec<FeatureSet> {
+        if self.config.feature_sets.is_empty() {
+            vec![FeatureSet::default()]
+        } else {
+            self.config.feature_sets.clone()
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_secs.map(Duration::from_secs)
+    }
+
+    /// Patches listed in perf-config, in the order they should be applied.
+    pub fn patches(&self) -> Vec<Patch> {
+        self.config
+            .patches
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| Patch::new(PathBuf::from(p)))
+            .collect()
+    }
+
     pub fn make_temp_dir(&self, base: &Path) -> anyhow::Result<TempDir> {
         let mut base_dot = base.to_path_buf();
         base_dot.push(".");
@@ -282,7 +642,8 @@ impl Benchamrk {
         &'a self,
         compiler: Compiler<'a>,
         cwd: &'a Path,
-        profile: Profile,
+        profile: &Profile,
+        feature_set: &FeatureSet,
     ) -> Box<dyn CompileTimeProcessor + 'a> {
         let mut cargo_args = self
             .config
@@ -298,6 +659,8 @@ impl Benchamrk {
         {
             cargo_args.push(format!("-j{}", count));
         }
+        cargo_args.extend(self.profile_cargo_args(profile));
+        cargo_args.extend(feature_set.cargo_args());
 
         match &self.config.compile_time_type {
             Some(ctt) => match ctt {
@@ -306,7 +669,7 @@ impl Benchamrk {
                         compiler,
                         processor_name: self.name.clone(),
                         cwd,
-                        profile,
+                        profile: profile.clone(),
                         incremental: false,
                         manifest_path: self
                             .config
@@ -314,14 +677,7 @@ impl Benchamrk {
                             .clone()
                             .unwrap_or_else(|| String::from("Cargo.toml")),
                         cargo_args,
-                        rustc_args: self
-                            .config
-                            .cargo_rustc_opts
-                            .clone()
-                            .unwrap_or_default()
-                            .split_whitespace()
-                            .map(String::from)
-                            .collect(),
+                        rustc_args: self.profile_rustc_args(profile),
                         touch_file: self.config.touch_file.clone(),
                     };
                     Box::new(process)
@@ -332,7 +688,7 @@ impl Benchamrk {
                             compiler,
                             processor_name: self.name.clone(),
                             cwd,
-                            profile,
+                            profile: profile.clone(),
                             incremental: false,
                             manifest_path: self
                                 .config
@@ -340,14 +696,7 @@ impl Benchamrk {
                                 .clone()
                                 .unwrap_or_else(|| String::from("Cargo.toml")),
                             cargo_args,
-                            rustc_args: self
-                                .config
-                                .cargo_rustc_opts
-                                .clone()
-                                .unwrap_or_default()
-                                .split_whitespace()
-                                .map(String::from)
-                                .collect(),
+                            rustc_args: self.profile_rustc_args(profile),
                             touch_file: self.config.touch_file.clone(),
                             packages: packages.clone(),
                         };
@@ -359,7 +708,7 @@ impl Benchamrk {
                             compiler,
                             processor_name: self.name.clone(),
                             cwd,
-                            profile,
+                            profile: profile.clone(),
                             incremental: false,
                             manifest_path: self
                                 .config
@@ -367,14 +716,7 @@ impl Benchamrk {
                                 .clone()
                                 .unwrap_or_else(|| String::from("Cargo.toml")),
                             cargo_args,
-                            rustc_args: self
-                                .config
-                                .cargo_rustc_opts
-                                .clone()
-                                .unwrap_or_default()
-                                .split_whitespace()
-                                .map(String::from)
-                                .collect(),
+                            rustc_args: self.profile_rustc_args(profile),
                             touch_file: self.config.touch_file.clone(),
                         };
                         Box::new(process)
@@ -386,7 +728,7 @@ impl Benchamrk {
                     compiler,
                     processor_name: self.name.clone(),
                     cwd,
-                    profile,
+                    profile: profile.clone(),
                     incremental: false,
                     manifest_path: self
                         .config
@@ -394,14 +736,7 @@ impl Benchamrk {
                         .clone()
                         .unwrap_or_else(|| String::from("Cargo.toml")),
                     cargo_args,
-                    rustc_args: self
-                        .config
-                        .cargo_rustc_opts
-                        .clone()
-                        .unwrap_or_default()
-                        .split_whitespace()
-                        .map(String::from)
-                        .collect(),
+                    rustc_args: self.profile_rustc_args(profile),
                     touch_file: self.config.touch_file.clone(),
                 };
                 Box::new(process)
@@ -409,16 +744,84 @@ impl Benchamrk {
         }
     }
 
+    /// Make the process of a `Binary` runtime benchmark with the input size
+    /// `n` of its `scaling` block.
+    pub fn make_scaled_runtime_process<'a>(
+        &'a self,
+        compiler: Compiler<'a>,
+        cwd: &'a Path,
+        iterations: u32,
+        feature_set: &FeatureSet,
+        n: u64,
+    ) -> Result<Box<dyn Runtime + 'a>, String> {
+        match &self.config.runtime_test_type {
+            Some(RuntimeTestType::Binary) => {
+                self.make_binary_process(compiler, cwd, iterations, feature_set, Some(n))
+            }
+            _ => core::result::Result::Err(format!(
+                "scaling is only supported for the Binary runtime type, benchmark {}",
+                self.name
+            )),
+        }
+    }
+
+    fn make_binary_process<'a>(
+        &'a self,
+        compiler: Compiler<'a>,
+        cwd: &'a Path,
+        iterations: u32,
+        feature_set: &FeatureSet,
+        size: Option<u64>,
+    ) -> Result<Box<dyn Runtime + 'a>, String> {
+        if self.config.inputs.iter().filter(|i| i.stdin).count() > 1 {
+            return core::result::Result::Err(format!(
+                "more than one input piped to stdin in json-profile for benchmark {}",
+                self.name
+            ));
+        }
+        let scaling_args = match (&self.config.scaling, size) {
+            (Some(scaling), Some(n)) => scaling.args(n),
+            _ => vec![],
+        };
+        let process = RuntimeProcess::new(
+            compiler,
+            cwd,
+            self.name.clone(),
+            &self.config,
+            feature_set,
+            scaling_args,
+            iterations,
+        );
+        core::result::Result::Ok(Box::new(process))
+    }
+
+    /// `inputs` and `expected_output_sha256` only apply to the `Binary`
+    /// runtime type, so they are rejected for the others instead of ignored.
+    pub fn check_binary_options(&self) -> Result<(), String> {
+        let is_binary = matches!(self.config.runtime_test_type, Some(RuntimeTestType::Binary));
+        if !is_binary
+            && (!self.config.inputs.is_empty() || self.config.expected_output_sha256.is_some())
+        {
+            return core::result::Result::Err(String::from(
+                "`inputs` and `expected_output_sha256` are only supported by the `Binary` runtime type",
+            ));
+        }
+        core::result::Result::Ok(())
+    }
+
     pub fn make_runtime_process<'a>(
         &'a self,
         compiler: Compiler<'a>,
         cwd: &'a Path,
         iterations: u32,
+        feature_set: &FeatureSet,
     ) -> Result<Box<dyn Runtime + 'a>, String> {
         log::debug!(
             "make_runtime_process: get runtime args: {:?}",
             self.config.runtime_args
         );
+        self.check_binary_options()
+            .map_err(|e| format!("{} in json-profile for benchmark {}", e, self.name))?;
         match &self.config.runtime_test_type {
             Some(test_type) => match test_type {
                 RuntimeTestType::Test => {
@@ -439,7 +842,10 @@ impl Benchamrk {
                             .unwrap_or_default()
                             .split_whitespace()
                             .map(String::from)
+                            .chain(feature_set.cargo_args())
                             .collect(),
+                        run_executables: self.config.run_executables,
+                        test_filter: self.config.test_filter.clone(),
                     };
                     core::result::Result::Ok(Box::new(process))
                 }
@@ -466,29 +872,15 @@ impl Benchamrk {
                             .unwrap_or_default()
                             .split_whitespace()
                             .map(String::from)
+                            .chain(feature_set.cargo_args())
                             .collect(),
                     );
                     core::result::Result::Ok(Box::new(process))
                 }
                 RuntimeTestType::Binary => {
-                    let process = RuntimeProcess::new(
-                        compiler,
-                        cwd,
-                        self.name.clone(),
-                        self.config
-                            .runtime_args
-                            .clone()
-                            .unwrap_or_default()
-                            .split_whitespace()
-                            .map(String::from)
-                            .collect(),
-                        self.config
-                            .runtime_cargo_toml
-                            .clone()
-                            .unwrap_or_else(|| String::from("Cargo.toml")),
-                        iterations,
-                    );
-                    core::result::Result::Ok(Box::new(process))
+                    // The regular run of a scaling benchmark is the largest size.
+                    let size = self.config.scaling.as_ref().and_then(|s| s.max_size());
+                    self.make_binary_process(compiler, cwd, iterations, feature_set, size)
                 }
                 RuntimeTestType::Bench => {
                     let process = CargoBenchProcess::new(
@@ -506,11 +898,22 @@ impl Benchamrk {
                             .unwrap_or_default()
                             .split_whitespace()
                             .map(String::from)
+                            .chain(feature_set.cargo_args())
                             .collect(),
+                        self.config.run_executables,
                     );
                     core::result::Result::Ok(Box::new(process))
                 }
                 RuntimeTestType::Packages => {
+                    let packages = match &self.config.runtime_test_packages {
+                        Some(packages) => packages.clone(),
+                        None => {
+                            return core::result::Result::Err(format!(
+                                "missing runtime test packages in json-profile for benchmark {}",
+                                self.name
+                            ))
+                        }
+                    };
                     core::result::Result::Ok(Box::new(CargoPackageTestProcess {
                         compiler,
                         processor_name: self.name.clone(),
@@ -528,8 +931,9 @@ impl Benchamrk {
                             .unwrap_or_default()
                             .split_whitespace()
                             .map(String::from)
+                            .chain(feature_set.cargo_args())
                             .collect(),
-                        packages: self.config.runtime_test_packages.clone().unwrap(),
+                        packages,
                     }))
                 }
             },
@@ -551,7 +955,10 @@ impl Benchamrk {
                         .unwrap_or_default()
                         .split_whitespace()
                         .map(String::from)
+                        .chain(feature_set.cargo_args())
                         .collect(),
+                    run_executables: self.config.run_executables,
+                    test_filter: self.config.test_filter.clone(),
                 };
                 core::result::Result::Ok(Box::new(process))
             }
@@ -571,6 +978,9 @@ pub struct BenchmarkConfig {
     pub runtime_test_type: Option<RuntimeTestType>,
     pub example_lst: Option<Vec<String>>,
     pub runtime_args: Option<String>,
+    /// Patch files, relative to the benchmark directory, that are applied
+    /// one after another for the `IncrPatched` scenario.
+    pub patches: Option<Vec<String>>,
     pub target_path: Option<PathBuf>,
     /// The file that should be touched to ensure cargo re-checks the leaf crate
     /// we're interested in. Likely, something similar to `src/lib.rs`. The
@@ -580,8 +990,57 @@ pub struct BenchmarkConfig {
     pub touch_file: Option<String>,
     #[serde(default)]
     pub disabled: bool,
+    /// Free-form labels such as `io` or `generic-heavy`, used to select a
+    /// subset of the suit with `--tags`/`--exclude-tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
     #[serde(default = "default_runs")]
     pub runs: usize,
+    /// Runtime iterations executed before the measured ones, to warm up the
+    /// page cache and the like. They are excluded from the stats.
+    #[serde(default)]
+    pub warmup_runs: u32,
+    /// Custom profiles of this benchmark, which take precedence over those in
+    /// `profiles.json` of the benchmark dir.
+    #[serde(default)]
+    pub profiles: HashMap<String, CustomProfile>,
+    /// Sets of cargo features to build the benchmark with. Compile-time,
+    /// runtime and binary-size benchmarks run once per feature set.
+    #[serde(default)]
+    pub feature_sets: Vec<FeatureSet>,
+    /// Kill cargo, rustc-fake or runtime-fake together with all their
+    /// children if they run longer than this.
+    pub timeout_secs: Option<u64>,
+    /// For the `Test` and `Bench` runtime types, build with `--no-run` and
+    /// measure each test or bench executable directly rather than the whole
+    /// `cargo test` or `cargo bench`, whose startup and fingerprint checks
+    /// would otherwise be measured too.
+    #[serde(default)]
+    pub run_executables: bool,
+    /// Only the tests whose names contain one of these are measured by the
+    /// `Test` runtime type. All tests are measured if it is empty.
+    #[serde(default)]
+    pub test_filter: Vec<String>,
+    /// Input files of the `Binary` runtime type, relative to the benchmark
+    /// directory.
+    #[serde(default)]
+    pub inputs: Vec<RuntimeInput>,
+    /// SHA-256 of what the `Binary` runtime type prints to stdout. It is
+    /// checked on every iteration, so that a miscompiled benchmark fails
+    /// with `WrongOutput` instead of being measured.
+    pub expected_output_sha256: Option<String>,
+    /// Input sizes the `Binary` runtime type is run with, to fit how its
+    /// stats scale with the size.
+    pub scaling: Option<Scaling>,
+}
+
+/// An input file of a runtime benchmark, passed as an argument after
+/// `runtime_args` or piped to stdin.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeInput {
+    pub path: String,
+    #[serde(default)]
+    pub stdin: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -616,3 +1075,37 @@ impl BenchmarkSuit {
         names
     }
 }
+
+#[cfg(test)]
+mod test_benchmark {
+    use std::fs::write;
+
+    use crate::benchmark::{benchmark::Benchamrk, profile::Profile};
+
+    /// Test for Benchamrk::profile_rustc_args
+    ///
+    /// Step1: Load a benchmark whose perf-config sets `cargo_rustc_opts`.
+    ///
+    /// Step2: Verify the rustc options are passed to rustc profiles only, and
+    /// not to rustdoc for `Profile::Doc`.
+    #[test]
+    fn test_profile_rustc_args() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path().join("perf-config.json"),
+            r#"{ "cargo_rustc_opts": "-Cdebuginfo=2 --emit=llvm-ir" }"#,
+        )
+        .unwrap();
+        let benchmark = Benchamrk::new("doc".to_string(), dir.path().to_path_buf()).unwrap();
+
+        assert_eq!(
+            vec!["-Cdebuginfo=2", "--emit=llvm-ir"],
+            benchmark.profile_rustc_args(&Profile::Release)
+        );
+        assert_eq!(
+            vec!["-Cdebuginfo=2", "--emit=llvm-ir"],
+            benchmark.profile_rustc_args(&Profile::Check)
+        );
+        assert!(benchmark.profile_rustc_args(&Profile::Doc).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{execute::DeserializeStatError, utils::command::CommandError};
+
+use super::{profile::Profile, scenario::Scenario};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FailureKind {
+    BuildError,
+    /// Internal compiler error of the rustc under test.
+    Ice,
+    Timeout,
+    PerfUnavailable,
+    NonZeroExit,
+    ParseError,
+    /// The output of a runtime benchmark did not match
+    /// `expected_output_sha256`, e.g. because it was miscompiled.
+    WrongOutput,
+    /// The benchmark's config is invalid for the run, e.g. the runtime type
+    /// does not match the package.
+    ConfigError,
+    /// A patch of the `IncrPatched` scenario did not apply.
+    PatchError,
+    /// No process was run, e.g. cargo or runtime-fake could not be spawned
+    /// or the temp dir of the benchmark could not be created.
+    IoError,
+    /// Any other error of the collector, not of a process it ran.
+    Other,
+}
+
+/// A failed measurement, written to `failures.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkFailure {
+    pub benchmark: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_set: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
+    pub kind: FailureKind,
+    pub message: String,
+    /// Crash output of rustc, only set for `Ice`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ice: Option<String>,
+}
+
+impl BenchmarkFailure {
+    pub fn new(benchmark: String, err: &anyhow::Error) -> Self {
+        let (kind, ice) = classify(err);
+        BenchmarkFailure {
+            benchmark,
+            profile: None,
+            feature_set: None,
+            scenario: None,
+            iteration: None,
+            kind,
+            message: format!("{:?}", err),
+            ice,
+        }
+    }
+
+    pub fn with_run(
+        mut self,
+        profile: Profile,
+        feature_set: String,
+        scenario: Scenario,
+        iteration: usize,
+    ) -> Self {
+        self.profile = Some(profile);
+        self.feature_set = Some(feature_set);
+        self.scenario = Some(scenario);
+        self.iteration = Some(iteration);
+        self
+    }
+}
+
+/// Classify an error by the first error in its chain that we know about.
+/// Failed commands are classified by their stderr, and io errors, which no
+/// exit status is known for, as `IoError`. Anything else is `Other`.
+pub fn classify(err: &anyhow::Error) -> (FailureKind, Option<String>) {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<CommandError>() {
+            return match err {
+                CommandError::Timeout(_) => (FailureKind::Timeout, None),
+                CommandError::Failed { stderr, .. } => classify_stderr(stderr),
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<DeserializeStatError>() {
+            return match err {
+                DeserializeStatError::NoOutput(output) => {
+                    match classify_stderr(&String::from_utf8_lossy(&output.stderr)) {
+                        (FailureKind::PerfUnavailable, _) => (FailureKind::PerfUnavailable, None),
+                        _ => (FailureKind::ParseError, None),
+                    }
+                }
+                _ => (FailureKind::ParseError, None),
+            };
+        }
+        if cause.downcast_ref::<std::io::Error>().is_some() {
+            return (FailureKind::IoError, None);
+        }
+    }
+    (FailureKind::Other, None)
+}
+
+fn classify_stderr(stderr: &str) -> (FailureKind, Option<String>) {
+    const ICE_PATTERNS: [&str; 3] = [
+        "error: internal compiler error",
+        "error: the compiler unexpectedly panicked",
+        "thread 'rustc' panicked",
+    ];
+    const PERF_PATTERNS: [&str; 4] = [
+        "assertion failed: has_perf",
+        "perf_event_paranoid",
+        "perf: command not found",
+        "perf not found",
+    ];
+
+    if let Some(start) = ICE_PATTERNS.iter().filter_map(|p| stderr.find(p)).min() {
+        // Keep the whole crash output, starting at the line of the first ICE message.
+        let start = stderr[..start].rfind('\n').map_or(0, |i| i + 1);
+        return (FailureKind::Ice, Some(stderr[start..].to_string()));
+    }
+    if PERF_PATTERNS.iter().any(|p| stderr.contains(p)) {
+        return (FailureKind::PerfUnavailable, None);
+    }
+    if stderr.contains("output sha256 mismatch") {
+        return (FailureKind::WrongOutput, None);
+    }
+    if stderr.contains("error: could not compile") || stderr.contains("error[E") {
+        return (FailureKind::BuildError, None);
+    }
+    (FailureKind::NonZeroExit, None)
+}
+
+#[cfg(test)]
+mod test_failure {
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+
+    use anyhow::Context;
+
+    use crate::utils::command::CommandError;
+
+    use super::{classify, FailureKind};
+
+    fn failed(stderr: &str) -> anyhow::Error {
+        anyhow::Error::from(CommandError::Failed {
+            status: ExitStatus::from_raw(1 << 8),
+            stderr: stderr.to_string(),
+            stdout: String::new(),
+        })
+        .context("fail to start benchmark process.")
+    }
+
+    /// Test for classify
+    ///
+    /// Step1: Classify errors of failed commands by their stderr.
+    ///
+    /// Step2: Verify the crash output is kept for ICEs.
+    ///
+    /// Step3: Classify errors of the collector itself, where no process exited.
+    #[test]
+    fn test_classify() {
+        let err = anyhow::Error::from(CommandError::Timeout(Duration::from_secs(1)));
+        assert_eq!((FailureKind::Timeout, None), classify(&err));
+
+        let err = failed(
+            "error[E0425]: cannot find value `x` in this scope\nerror: could not compile `foo`",
+        );
+        assert_eq!(FailureKind::BuildError, classify(&err).0);
+
+        let err = failed("thread 'main' panicked at 'assertion failed: has_perf'");
+        assert_eq!(FailureKind::PerfUnavailable, classify(&err).0);
+
+        let err = failed(
+            "   Compiling foo v0.1.0\nerror: internal compiler error: boom\nnote: backtrace",
+        );
+        let (kind, ice) = classify(&err);
+        assert_eq!(FailureKind::Ice, kind);
+        assert_eq!(
+            "error: internal compiler error: boom\nnote: backtrace",
+            ice.unwrap()
+        );
+
+        let err = failed("error: output sha256 mismatch: expected 00, got 01");
+        assert_eq!(FailureKind::WrongOutput, classify(&err).0);
+
+        let err = failed("");
+        assert_eq!(FailureKind::NonZeroExit, classify(&err).0);
+
+        let err = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::NotFound))
+            .context("failed to spawn")
+            .unwrap_err();
+        assert_eq!(FailureKind::IoError, classify(&err).0);
+
+        let err = anyhow::anyhow!("no `summary:` line");
+        assert_eq!(FailureKind::Other, classify(&err).0);
+    }
+}
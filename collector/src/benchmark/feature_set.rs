@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// Name of the feature set used if a benchmark defines none.
+pub const DEFAULT_FEATURE_SET: &str = "default";
+
+pub fn default_feature_set_name() -> String {
+    String::from(DEFAULT_FEATURE_SET)
+}
+
+/// Cargo features a benchmark is built with, given in `feature_sets` of
+/// perf-config.json. Every benchmark is measured once per feature set.
+///
+/// ```json
+/// [
+///     { "name": "default" },
+///     { "name": "minimal", "no_default_features": true },
+///     { "name": "full", "all_features": true },
+///     { "name": "serde", "features": ["serde", "serde_json"] }
+/// ]
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureSet {
+    pub name: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub no_default_features: bool,
+    #[serde(default)]
+    pub all_features: bool,
+}
+
+impl Default for FeatureSet {
+    fn default() -> Self {
+        FeatureSet {
+            name: default_feature_set_name(),
+            features: vec![],
+            no_default_features: false,
+            all_features: false,
+        }
+    }
+}
+
+impl FeatureSet {
+    /// Cargo arguments to build with this feature set.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.no_default_features {
+            args.push(String::from("--no-default-features"));
+        }
+        if self.all_features {
+            args.push(String::from("--all-features"));
+        }
+        if !self.features.is_empty() {
+            args.push(String::from("--features"));
+            args.push(self.features.join(","));
+        }
+        args
+    }
+}
+
+/// Name of a benchmark in csv headers. The feature set is appended unless it
+/// is the default one, so that suites without feature sets are unchanged.
+pub fn labeled_benchmark_name(benchmark: &str, feature_set: &str) -> String {
+    if feature_set == DEFAULT_FEATURE_SET {
+        benchmark.to_string()
+    } else {
+        format!("{}[{}]", benchmark, feature_set)
+    }
+}
+
+#[cfg(test)]
+mod test_feature_set {
+    use super::{labeled_benchmark_name, FeatureSet};
+
+    /// Test for FeatureSet
+    ///
+    /// Step1: Parse feature sets from json.
+    ///
+    /// Step2: Verify the cargo arguments and labels generated for them.
+    #[test]
+    fn test_feature_set() {
+        let feature_sets: Vec<FeatureSet> = serde_json::from_str(
+            r#"[
+                {"name": "default"},
+                {"name": "minimal", "no_default_features": true, "features": ["std", "alloc"]},
+                {"name": "full", "all_features": true}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(FeatureSet::default(), feature_sets[0]);
+        assert!(feature_sets[0].cargo_args().is_empty());
+        assert_eq!(
+            vec!["--no-default-features", "--features", "std,alloc"],
+            feature_sets[1].cargo_args()
+        );
+        assert_eq!(vec!["--all-features"], feature_sets[2].cargo_args());
+
+        assert_eq!("regex", labeled_benchmark_name("regex", "default"));
+        assert_eq!("regex[full]", labeled_benchmark_name("regex", "full"));
+    }
+}
@@ -0,0 +1,137 @@
+use regex::Regex;
+
+use super::benchmark::Benchamrk;
+
+/// Select benchmarks of a suit by name and by the `tags` in perf-config.
+///
+/// Names are matched against each comma-separated pattern either by prefix,
+/// or as a glob if the pattern contains `*` or `?`.
+#[derive(Debug, Default)]
+pub struct BenchmarkFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+}
+
+impl BenchmarkFilter {
+    pub fn new(
+        include: Option<&str>,
+        exclude: Option<&str>,
+        tags: Option<&str>,
+        exclude_tags: Option<&str>,
+    ) -> Self {
+        fn split(s: Option<&str>) -> Vec<String> {
+            s.unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        }
+
+        BenchmarkFilter {
+            include: split(include),
+            exclude: split(exclude),
+            tags: split(tags),
+            exclude_tags: split(exclude_tags),
+        }
+    }
+
+    /// Whether the benchmark is selected by this filter.
+    pub fn accepts(&self, benchmark: &Benchamrk) -> bool {
+        self.accepts_name(&benchmark.name) && self.accepts_tags(&benchmark.config.tags)
+    }
+
+    /// Whether a benchmark named `name` passes the include and exclude
+    /// patterns. Unlike tags, this can be checked before perf-config is read.
+    pub fn accepts_name(&self, name: &str) -> bool {
+        if !self.include.is_empty() && !self.include.iter().any(|p| match_name(p, name)) {
+            return false;
+        }
+        !self.exclude.iter().any(|p| match_name(p, name))
+    }
+
+    /// Whether a benchmark with `tags` passes the tag filters.
+    pub fn accepts_tags(&self, tags: &[String]) -> bool {
+        if !self.tags.is_empty() && !self.tags.iter().any(|t| tags.contains(t)) {
+            return false;
+        }
+        if self.exclude_tags.iter().any(|t| tags.contains(t)) {
+            return false;
+        }
+        true
+    }
+}
+
+fn match_name(pattern: &str, name: &str) -> bool {
+    if pattern.contains(['*', '?']) {
+        let re = format!(
+            "^{}$",
+            regex::escape(pattern)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".")
+        );
+        match Regex::new(&re) {
+            Ok(re) => re.is_match(name),
+            Err(_) => false,
+        }
+    } else {
+        name.starts_with(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test_filter {
+    use std::path::PathBuf;
+
+    use crate::benchmark::benchmark::{Benchamrk, BenchmarkConfig};
+
+    use super::BenchmarkFilter;
+
+    fn benchmark(name: &str, tags: &[&str]) -> Benchamrk {
+        Benchamrk {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            config: BenchmarkConfig {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Test for BenchmarkFilter::accepts
+    ///
+    /// Step1: Filter benchmarks by name prefix and glob.
+    ///
+    /// Step2: Filter benchmarks by tags.
+    ///
+    /// Step3: Filter names alone, which tag filters do not affect.
+    #[test]
+    fn test_accepts() {
+        let serde = benchmark("serde-1.0", &["generic-heavy"]);
+        let regex = benchmark("regex-1.9", &["small", "io"]);
+
+        let filter = BenchmarkFilter::new(Some("serde"), None, None, None);
+        assert!(filter.accepts(&serde));
+        assert!(!filter.accepts(&regex));
+
+        let filter = BenchmarkFilter::new(None, Some("*-1.?"), None, None);
+        assert!(!filter.accepts(&serde));
+        assert!(!filter.accepts(&regex));
+
+        let filter = BenchmarkFilter::new(None, None, Some("io,generic-heavy"), None);
+        assert!(filter.accepts(&serde));
+        assert!(filter.accepts(&regex));
+
+        let filter = BenchmarkFilter::new(None, None, None, Some("small"));
+        assert!(filter.accepts(&serde));
+        assert!(!filter.accepts(&regex));
+
+        let filter = BenchmarkFilter::new(Some("serde"), Some("serde-2*"), Some("io"), None);
+        assert!(filter.accepts_name("serde-1.0"));
+        assert!(!filter.accepts_name("serde-2.0"));
+        assert!(!filter.accepts_name("regex-1.9"));
+        assert!(!filter.accepts(&serde));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// One of the two toolchains compared by `bench_compare`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    A,
+    B,
+}
+
+impl Side {
+    /// Name of the sub directory of `out-dir` the results are written to.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            Side::A => "a",
+            Side::B => "b",
+        }
+    }
+}
+
+/// In which order the two toolchains are run within each iteration.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InterleaveOrder {
+    /// `A` first in every iteration, i.e. `ABAB...`.
+    #[serde(rename = "abab")]
+    Abab,
+    /// The toolchain run first is picked at random in every iteration, so
+    /// that a drift of the machine does not always favor the same one.
+    #[serde(rename = "random")]
+    Random,
+}
+
+impl InterleaveOrder {
+    /// The order of the two toolchains in each of `iterations`. `seed` is
+    /// only used by `Random`, so that a schedule can be reproduced.
+    pub fn schedule(&self, iterations: usize, seed: u64) -> Vec<[Side; 2]> {
+        let mut rng = XorShift::new(seed);
+        (0..iterations)
+            .map(|_| match self {
+                InterleaveOrder::Abab => [Side::A, Side::B],
+                InterleaveOrder::Random => {
+                    if rng.next() & 1 == 0 {
+                        [Side::A, Side::B]
+                    } else {
+                        [Side::B, Side::A]
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl Display for InterleaveOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterleaveOrder::Abab => f.write_str("abab"),
+            InterleaveOrder::Random => f.write_str("random"),
+        }
+    }
+}
+
+impl FromStr for InterleaveOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abab" => Ok(InterleaveOrder::Abab),
+            "random" => Ok(InterleaveOrder::Random),
+            _ => Err(format!(
+                "Unrecognized order {}. Supported orders are `abab` and `random`",
+                s
+            )),
+        }
+    }
+}
+
+/// Seed of a random schedule if none is given.
+pub fn seed_from_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// xorshift64, good enough to shuffle the order of runs.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        XorShift(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod test_interleave {
+    use super::{InterleaveOrder, Side};
+
+    /// Test for InterleaveOrder::schedule
+    ///
+    /// Step1: Schedule iterations in `abab` order and verify `A` is always first.
+    ///
+    /// Step2: Schedule iterations in `random` order, verify both toolchains
+    /// are run in every iteration, both come first sometimes, and the same
+    /// seed gives the same schedule.
+    #[test]
+    fn test_schedule() {
+        let abab = InterleaveOrder::Abab.schedule(4, 42);
+        assert_eq!(vec![[Side::A, Side::B]; 4], abab);
+
+        let random = InterleaveOrder::Random.schedule(64, 42);
+        assert_eq!(64, random.len());
+        assert!(random.iter().all(|[x, y]| x != y));
+        assert!(random.iter().any(|[x, _]| *x == Side::A));
+        assert!(random.iter().any(|[x, _]| *x == Side::B));
+        assert_eq!(random, InterleaveOrder::Random.schedule(64, 42));
+    }
+}
@@ -1,3 +1,13 @@
+pub mod adaptive;
 pub(crate) mod benchmark;
+pub mod failure;
+pub mod feature_set;
+pub mod filter;
+pub mod interleave;
+pub mod patch;
 pub mod profile;
+pub mod run_options;
+pub mod scaling;
 pub mod scenario;
+pub mod sweep;
+pub mod validate;
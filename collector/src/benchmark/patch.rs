@@ -0,0 +1,76 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+
+use crate::utils::command::command_output;
+
+/// A patch file applied on top of a benchmark for the `IncrPatched` scenario.
+#[derive(Clone, Debug)]
+pub struct Patch {
+    /// Name recorded with the results, which is the file stem of the patch.
+    pub name: String,
+    /// Path of the patch file, relative to the benchmark directory.
+    pub path: PathBuf,
+}
+
+impl Patch {
+    pub fn new(path: PathBuf) -> Self {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        Patch { name, path }
+    }
+
+    /// Apply the patch to the copy of benchmark in `dir`.
+    pub fn apply(&self, dir: &Path) -> anyhow::Result<()> {
+        log::debug!("applying patch {} in {:?}", self.name, dir);
+        let mut cmd = Command::new("patch");
+        cmd.current_dir(dir)
+            .arg("-Np1")
+            .arg("-i")
+            .arg(dir.join(&self.path));
+        command_output(&mut cmd)
+            .with_context(|| format!("failed to apply patch {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_patch {
+    use std::{fs::read_to_string, path::PathBuf};
+
+    use crate::benchmark::benchmark::Benchamrk;
+
+    /// Test for Patch::apply
+    ///
+    /// Step1: Load benchmark `test/patch/helloworld` and copy it to a temp dir.
+    ///
+    /// Step2: Apply the patches listed in its perf-config.
+    ///
+    /// Step3: Verify the patched source file.
+    #[test]
+    fn test_apply_patch() {
+        let benchmark = Benchamrk::new(
+            "helloworld".to_string(),
+            PathBuf::from("test/patch/helloworld"),
+        )
+        .unwrap();
+        let tmp_dir = benchmark.make_temp_dir(&benchmark.path).unwrap();
+
+        let patches = benchmark.patches();
+        assert_eq!(1, patches.len());
+        assert_eq!("0-println", patches[0].name);
+
+        patches
+            .iter()
+            .for_each(|p| p.apply(tmp_dir.path()).unwrap());
+
+        let src = read_to_string(tmp_dir.path().join("src/main.rs")).unwrap();
+        assert!(src.contains("Hello, patch!"));
+    }
+}
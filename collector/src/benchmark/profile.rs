@@ -1,14 +1,15 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
-#[derive(
-    Clone, Copy, Debug, Eq, Hash, PartialEq, clap::ArgEnum, serde::Deserialize, serde::Serialize,
-)]
-#[clap(rename_all = "PascalCase")]
+/// Serialized as its name, e.g. `"release"` or `"size"`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Profile {
     Check,
     Debug,
     Doc,
     Release,
+    /// A named cargo profile, either defined by a `CustomProfile` or in the
+    /// Cargo.toml of the benchmark.
+    Custom(String),
 }
 
 impl FromStr for Profile {
@@ -19,6 +20,12 @@ impl FromStr for Profile {
             "debug" => Ok(Self::Debug),
             "doc" => Ok(Self::Doc),
             "release" => Ok(Self::Release),
+            _ if !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Ok(Self::Custom(s.to_string()))
+            }
             _ => Err(format!("Unknown Profile {}", s)),
         }
     }
@@ -31,6 +38,27 @@ impl Display for Profile {
             Profile::Debug => f.write_str("debug"),
             Profile::Doc => f.write_str("doc"),
             Profile::Release => f.write_str("release"),
+            Profile::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+impl serde::Serialize for Profile {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Profile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            // Variant names written by earlier versions.
+            "Check" => Ok(Self::Check),
+            "Debug" => Ok(Self::Debug),
+            "Doc" => Ok(Self::Doc),
+            "Release" => Ok(Self::Release),
+            _ => Profile::from_str(&s).map_err(serde::de::Error::custom),
         }
     }
 }
@@ -51,3 +79,110 @@ impl FromStr for Profiles {
         Ok(Self { profiles })
     }
 }
+
+fn default_inherits() -> String {
+    String::from("release")
+}
+
+/// Definition of a `Profile::Custom`, given in `profiles` of perf-config.json
+/// or of `profiles.json` in the benchmark dir. Built-in profiles can not be
+/// redefined.
+///
+/// ```json
+/// { "size": { "opt-level": "s", "panic": "abort", "rustflags": "-Ctarget-cpu=native" } }
+/// ```
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+pub struct CustomProfile {
+    /// The cargo profile to inherit settings from.
+    #[serde(default = "default_inherits")]
+    pub inherits: String,
+    /// Extra flags for rustc, added to `build.rustflags`. Note that cargo
+    /// ignores them if `RUSTFLAGS` is set in the environment.
+    pub rustflags: Option<String>,
+    /// Cargo profile settings such as `opt-level`, `lto`, `codegen-units`.
+    #[serde(flatten)]
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+impl CustomProfile {
+    /// Cargo arguments to build with this profile under `name`.
+    pub fn cargo_args(&self, name: &str) -> Vec<String> {
+        let mut configs = vec![format!("profile.{}.inherits={:?}", name, self.inherits)];
+        // Json scalars are valid toml values as well.
+        self.settings.iter().for_each(|(key, value)| {
+            configs.push(format!("profile.{}.{}={}", name, key, value));
+        });
+        if let Some(rustflags) = &self.rustflags {
+            configs.push(format!(
+                "build.rustflags={}",
+                serde_json::Value::from(rustflags.split_whitespace().collect::<Vec<_>>())
+            ));
+        }
+
+        let mut args = vec![];
+        configs.into_iter().for_each(|config| {
+            args.push(String::from("--config"));
+            args.push(config);
+        });
+        args.push(String::from("--profile"));
+        args.push(name.to_string());
+        args
+    }
+}
+
+#[cfg(test)]
+mod test_profile {
+    use std::str::FromStr;
+
+    use super::{CustomProfile, Profile};
+
+    /// Test for CustomProfile
+    ///
+    /// Step1: Parse built-in and custom profile names.
+    ///
+    /// Step2: Verify profiles are serialized as their names, and profiles
+    /// serialized by earlier versions are still read.
+    ///
+    /// Step3: Verify cargo arguments generated for a custom profile.
+    #[test]
+    fn test_custom_profile() {
+        assert_eq!(Ok(Profile::Release), Profile::from_str("release"));
+        assert_eq!(
+            Ok(Profile::Custom(String::from("size"))),
+            Profile::from_str("size")
+        );
+        assert!(Profile::from_str("opt level").is_err());
+
+        let profiles = vec![Profile::Release, Profile::Custom(String::from("size"))];
+        let json = serde_json::to_string(&profiles).unwrap();
+        assert_eq!(r#"["release","size"]"#, json);
+        assert_eq!(
+            profiles,
+            serde_json::from_str::<Vec<Profile>>(&json).unwrap()
+        );
+        assert_eq!(
+            Profile::Release,
+            serde_json::from_str::<Profile>(r#""Release""#).unwrap()
+        );
+
+        let profile: CustomProfile = serde_json::from_str(
+            r#"{"opt-level": "s", "codegen-units": 1, "rustflags": "-Ctarget-cpu=native"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "--config",
+                "profile.size.inherits=\"release\"",
+                "--config",
+                "profile.size.codegen-units=1",
+                "--config",
+                "profile.size.opt-level=\"s\"",
+                "--config",
+                "build.rustflags=[\"-Ctarget-cpu=native\"]",
+                "--profile",
+                "size",
+            ],
+            profile.cargo_args("size")
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use std::path::{Path, PathBuf};
+
+use crate::{benchmark::adaptive::AdaptiveIterations, toolchain::PerfTool};
+
+/// How every benchmark of a run is measured and where its outputs go.
+#[derive(Clone, Copy)]
+pub struct RunOptions<'a> {
+    pub perf_tool: &'a PerfTool,
+    pub event_filter_file: &'a PathBuf,
+    /// Iterations of each benchmark, `runs` in its perf-config if not set.
+    /// Ignored in adaptive mode.
+    pub iterations: Option<usize>,
+    pub adaptive: Option<&'a AdaptiveIterations>,
+    pub flame_graph_path: &'a Path,
+    /// Flamegraphs are drawn if it is above 0.
+    pub flamegraph_flag: i32,
+    pub out_dir: &'a Path,
+}
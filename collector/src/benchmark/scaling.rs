@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+/// Input sizes a runtime benchmark is run with to see how it scales, e.g.
+///
+/// ```json
+/// "scaling": { "args": "--len {n}", "sizes": [1000, 10000, 100000] }
+/// ```
+///
+/// `{n}` in `args` is replaced by each size, and the result appended to
+/// `runtime_args`. Only the `Binary` runtime type is supported.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scaling {
+    pub args: String,
+    pub sizes: Vec<u64>,
+}
+
+impl Scaling {
+    pub fn args(&self, n: u64) -> Vec<String> {
+        self.args
+            .replace("{n}", &n.to_string())
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    }
+
+    /// The size of the regular run of the benchmark.
+    pub fn max_size(&self) -> Option<u64> {
+        self.sizes.iter().max().copied()
+    }
+
+    /// Name of the sub result of size `n`.
+    pub fn sub_result_name(n: u64) -> String {
+        format!("n={}", n)
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Cargo profiles whose settings are overridden by a sweep point, so that
+/// the settings apply whichever profile a benchmark is built with.
+const SWEPT_PROFILES: [&str; 4] = ["DEV", "RELEASE", "TEST", "BENCH"];
+
+/// Codegen settings to sweep, each mapped to the values to try, e.g.
+///
+/// ```json
+/// {
+///     "opt-level": [2, 3, "s"],
+///     "codegen-units": [1, 16],
+///     "lto": [false, "thin"],
+///     "panic": ["unwind", "abort"],
+///     "debug-assertions": [false, true],
+///     "rustflags": ["", "-Ctarget-cpu=native"]
+/// }
+/// ```
+///
+/// Keys are cargo profile settings, except `rustflags`, which are extra
+/// flags for rustc. Every combination of the values is a sweep point.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct SweepMatrix {
+    pub axes: BTreeMap<String, Vec<serde_json::Value>>,
+}
+
+/// One combination of the settings of a `SweepMatrix`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepPoint {
+    /// `key=value` of every setting, separated by ','.
+    pub name: String,
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+impl SweepMatrix {
+    /// Every combination of the values of all axes.
+    pub fn points(&self) -> Vec<SweepPoint> {
+        self.axes
+            .iter()
+            .fold(vec![BTreeMap::new()], |points, (key, values)| {
+                points
+                    .iter()
+                    .flat_map(|settings| {
+                        values.iter().map(move |value| {
+                            let mut settings = settings.clone();
+                            settings.insert(key.clone(), value.clone());
+                            settings
+                        })
+                    })
+                    .collect()
+            })
+            .into_iter()
+            .map(|settings| SweepPoint {
+                name: settings
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, env_value(value)))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                settings,
+            })
+            .collect()
+    }
+}
+
+impl SweepPoint {
+    /// Environment variables applying the settings to every cargo invoked
+    /// by the collector. Note that cargo ignores `CARGO_BUILD_RUSTFLAGS` if
+    /// `RUSTFLAGS` is set.
+    pub fn cargo_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![];
+        self.settings.iter().for_each(|(key, value)| {
+            if key == "rustflags" {
+                env.push((String::from("CARGO_BUILD_RUSTFLAGS"), env_value(value)));
+            } else {
+                SWEPT_PROFILES.iter().for_each(|profile| {
+                    env.push((
+                        format!(
+                            "CARGO_PROFILE_{}_{}",
+                            profile,
+                            key.to_uppercase().replace('-', "_")
+                        ),
+                        env_value(value),
+                    ))
+                });
+            }
+        });
+        env
+    }
+}
+
+/// Json strings are passed without quotes, other values as they are.
+fn env_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test_sweep {
+    use super::SweepMatrix;
+
+    /// Test for SweepMatrix
+    ///
+    /// Step1: Expand a matrix of 3 axes into its points.
+    ///
+    /// Step2: Verify the names of the points and the env of one of them.
+    #[test]
+    fn test_sweep_points() {
+        let matrix: SweepMatrix = serde_json::from_str(
+            r#"{"opt-level": [2, "s"], "lto": [false, "thin"], "rustflags": ["-Ctarget-cpu=native"]}"#,
+        )
+        .unwrap();
+        let points = matrix.points();
+        assert_eq!(
+            vec![
+                "lto=false,opt-level=2,rustflags=-Ctarget-cpu=native",
+                "lto=false,opt-level=s,rustflags=-Ctarget-cpu=native",
+                "lto=thin,opt-level=2,rustflags=-Ctarget-cpu=native",
+                "lto=thin,opt-level=s,rustflags=-Ctarget-cpu=native",
+            ],
+            points.iter().map(|p| p.name.as_str()).collect::<Vec<_>>()
+        );
+
+        let env = points[2].cargo_env();
+        assert_eq!(9, env.len());
+        assert!(env.contains(&(
+            String::from("CARGO_PROFILE_RELEASE_LTO"),
+            String::from("thin")
+        )));
+        assert!(env.contains(&(
+            String::from("CARGO_PROFILE_BENCH_OPT_LEVEL"),
+            String::from("2")
+        )));
+        assert!(env.contains(&(
+            String::from("CARGO_BUILD_RUSTFLAGS"),
+            String::from("-Ctarget-cpu=native")
+        )));
+    }
+}
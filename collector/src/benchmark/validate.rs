@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, File},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{toolchain::LocalToolchain, utils::command::command_output};
+
+use super::{
+    benchmark::{Benchamrk, BenchmarkConfig, CompileTimeType, RuntimeTestType},
+    filter::BenchmarkFilter,
+};
+
+/// A perf-config with the keys unknown to `BenchmarkConfig` collected aside.
+#[derive(Deserialize)]
+struct CheckedConfig {
+    #[serde(flatten)]
+    config: BenchmarkConfig,
+    #[serde(flatten)]
+    unknown: HashMap<String, serde_json::Value>,
+}
+
+/// Problems found in a single benchmark of the suit.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BenchmarkHealth {
+    pub name: String,
+    pub path: PathBuf,
+    pub problems: Vec<String>,
+    /// Whether the benchmark builds with the given toolchain. `None` if the
+    /// build was not checked.
+    pub builds: Option<bool>,
+}
+
+impl BenchmarkHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.problems.is_empty() && self.builds != Some(false)
+    }
+}
+
+/// Health report of a benchmark suit, written to `suite_health.json`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuiteHealth {
+    pub bench_dir: PathBuf,
+    pub rustc: Option<PathBuf>,
+    pub healthy: bool,
+    pub benchmarks: Vec<BenchmarkHealth>,
+}
+
+/// Check every benchmark under `bench_dir` without measuring anything.
+/// Builds are only checked if a toolchain is given.
+pub fn validate_suite(
+    bench_dir: &Path,
+    filter: &BenchmarkFilter,
+    ltc: Option<&LocalToolchain>,
+) -> anyhow::Result<SuiteHealth> {
+    let mut benchmarks = vec![];
+
+    for entry in read_dir(bench_dir)
+        .with_context(|| format!("failed to list benchmark dir '{}'", bench_dir.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        let name = match entry.file_name().into_string() {
+            Ok(s) => s,
+            Err(e) => bail!("non-utf8 benchmark name: {:?}", e),
+        };
+        if !entry.file_type()?.is_dir() {
+            println!("benchmark '{}' ignored", name);
+            continue;
+        }
+
+        let mut health = BenchmarkHealth {
+            name: name.clone(),
+            path: path.clone(),
+            problems: vec![],
+            builds: None,
+        };
+
+        let config = match load_config(&path) {
+            Ok((config, unknown)) => {
+                unknown.iter().for_each(|key| {
+                    health
+                        .problems
+                        .push(format!("unknown key `{}` in perf-config.json", key))
+                });
+                Some(config)
+            }
+            Err(e) => {
+                health.problems.push(format!("{:#}", e));
+                None
+            }
+        };
+
+        let benchmark = Benchamrk {
+            name,
+            path,
+            config: config.clone().unwrap_or_default(),
+        };
+        if !filter.accepts(&benchmark) {
+            println!("benchmark '{}' filtered out", benchmark.name);
+            continue;
+        }
+
+        if config.is_some() {
+            health.problems.append(&mut check_config(&benchmark));
+
+            if let Some(ltc) = ltc {
+                if health.problems.is_empty() && !benchmark.config.disabled {
+                    println!("Building {}...", benchmark.name);
+                    match check_build(&benchmark, ltc) {
+                        Ok(_) => health.builds = Some(true),
+                        Err(e) => {
+                            health.builds = Some(false);
+                            health.problems.push(format!("{:?}", e));
+                        }
+                    }
+                }
+            }
+        }
+
+        benchmarks.push(health);
+    }
+
+    benchmarks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(SuiteHealth {
+        bench_dir: bench_dir.to_path_buf(),
+        rustc: ltc.map(|ltc| ltc.rustc.clone()),
+        healthy: benchmarks.iter().all(|b| b.is_healthy()),
+        benchmarks,
+    })
+}
+
+fn load_config(path: &Path) -> anyhow::Result<(BenchmarkConfig, Vec<String>)> {
+    let config_path = path.join("perf-config.json");
+    if !config_path.exists() {
+        bail!("missing a perf-config.json file");
+    }
+    let checked: CheckedConfig = serde_json::from_reader(
+        File::open(&config_path).with_context(|| format!("failed to open {:?}", config_path))?,
+    )
+    .with_context(|| format!("failed to parse {:?}", config_path))?;
+
+    let mut unknown = checked.unknown.into_keys().collect::<Vec<_>>();
+    unknown.sort();
+    Ok((checked.config, unknown))
+}
+
+/// Check the options in perf-config that are only used when a benchmark
+/// is actually run.
+fn check_config(benchmark: &Benchamrk) -> Vec<String> {
+    let config = &benchmark.config;
+    let mut problems = vec![];
+
+    if let Some(CompileTimeType::Packages) = config.compile_time_type {
+        if is_missing(&config.packages) {
+            problems.push(String::from(
+                "`compile_time_type` is `Packages` but `packages` is missing",
+            ));
+        }
+    }
+
+    match config.runtime_test_type {
+        Some(RuntimeTestType::Example) if is_missing(&config.example_lst) => {
+            problems.push(String::from(
+                "`runtime_test_type` is `Example` but `example_lst` is missing",
+            ));
+        }
+        Some(RuntimeTestType::Packages) if is_missing(&config.runtime_test_packages) => {
+            problems.push(String::from(
+                "`runtime_test_type` is `Packages` but `runtime_test_packages` is missing",
+            ));
+        }
+        _ => (),
+    }
+    if let Err(e) = benchmark.check_binary_options() {
+        problems.push(e);
+    }
+
+    let mut files = vec![
+        config
+            .cargo_toml
+            .clone()
+            .unwrap_or_else(|| String::from("Cargo.toml")),
+        config
+            .runtime_cargo_toml
+            .clone()
+            .unwrap_or_else(|| String::from("Cargo.toml")),
+    ];
+    files.extend(config.touch_file.clone());
+    files.extend(config.patches.clone().unwrap_or_default());
+    files.extend(config.inputs.iter().map(|i| i.path.clone()));
+    files.dedup();
+    files.iter().for_each(|f| {
+        if !benchmark.path.join(f).exists() {
+            problems.push(format!("file `{}` not found in benchmark dir", f));
+        }
+    });
+
+    problems
+}
+
+fn is_missing(list: &Option<Vec<String>>) -> bool {
+    list.as_ref().is_none_or(|l| l.is_empty())
+}
+
+/// Build the benchmark in a temp dir with the given toolchain.
+fn check_build(benchmark: &Benchamrk, ltc: &LocalToolchain) -> anyhow::Result<()> {
+    let tmp_dir = benchmark.make_temp_dir(&benchmark.path)?;
+
+    let mut manifests = vec![
+        benchmark
+            .config
+            .cargo_toml
+            .clone()
+            .unwrap_or_else(|| String::from("Cargo.toml")),
+        benchmark
+            .config
+            .runtime_cargo_toml
+            .clone()
+            .unwrap_or_else(|| String::from("Cargo.toml")),
+    ];
+    manifests.dedup();
+
+    for manifest in manifests {
+        let mut cmd = Command::new(&ltc.cargo);
+        cmd.current_dir(tmp_dir.path())
+            .env("RUSTC", &ltc.rustc)
+            .env("RUSTC_BOOTSTRAP", "1")
+            .arg("build")
+            .arg("--manifest-path")
+            .arg(&manifest)
+            .args(
+                benchmark
+                    .config
+                    .cargo_opts
+                    .clone()
+                    .unwrap_or_default()
+                    .split_whitespace(),
+            );
+        command_output(&mut cmd).with_context(|| {
+            format!(
+                "fail to build {} with manifest {}",
+                benchmark.name, manifest
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_validate {
+    use std::path::PathBuf;
+
+    use crate::benchmark::filter::BenchmarkFilter;
+
+    use super::validate_suite;
+
+    /// Test for validate_suite
+    ///
+    /// Step1: Validate configs in `test/validate_suite/benchmarks` without building.
+    ///
+    /// Step2: Verify the problems reported for each benchmark.
+    #[test]
+    fn test_validate_suite() {
+        let health = validate_suite(
+            &PathBuf::from("test/validate_suite/benchmarks"),
+            &BenchmarkFilter::default(),
+            None,
+        )
+        .unwrap();
+
+        assert!(!health.healthy);
+        assert_eq!(4, health.benchmarks.len());
+
+        let bad_example = &health.benchmarks[0];
+        assert_eq!("bad_example", bad_example.name);
+        assert_eq!(2, bad_example.problems.len());
+        assert!(bad_example.problems[0].contains("unknown key `example_list`"));
+        assert!(bad_example.problems[1].contains("example_lst"));
+
+        let bad_inputs = &health.benchmarks[1];
+        assert_eq!("bad_inputs", bad_inputs.name);
+        assert_eq!(2, bad_inputs.problems.len());
+        assert!(bad_inputs.problems[0].contains("`Binary` runtime type"));
+        assert!(bad_inputs.problems[1].contains("`input.txt` not found"));
+
+        let bad_packages = &health.benchmarks[2];
+        assert_eq!("bad_packages", bad_packages.name);
+        assert_eq!(2, bad_packages.problems.len());
+
+        let helloworld = &health.benchmarks[3];
+        assert!(helloworld.is_healthy());
+        assert_eq!(None, helloworld.builds);
+    }
+}
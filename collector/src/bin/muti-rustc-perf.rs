@@ -1,12 +1,20 @@
 use std::{
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, File, OpenOptions},
     io::{BufReader, BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Output},
 };
 
+use anyhow::{bail, Context};
 use clap::Parser;
-use serde::Deserialize;
+use collector::{
+    benchmark::{profile::Profile, scenario::Scenario},
+    statistics::{
+        compile_time_stat::CompileTimeResultSet, metadata::read_result_file,
+        runtime_stat::RuntimeResultVec, welch::WelchTest,
+    },
+};
+use serde::{Deserialize, Serialize};
 
 fn main() {
     let cli = Cli::parse();
@@ -49,6 +57,7 @@ fn main() {
                         flamegraph,
                         &cargo,
                         &path,
+                        &[],
                     ) {
                         Ok(output) => {
                             println!(
@@ -103,6 +112,7 @@ fn main() {
                         flamegraph,
                         &cargo,
                         &path,
+                        &[],
                     ) {
                         Ok(output) => {
                             println!(
@@ -119,7 +129,328 @@ fn main() {
                 })
             });
         }
+        Commands::Bisect {
+            compilers_config,
+            benchmark_dir,
+            benchmark,
+            metric,
+            threshold,
+            runtime,
+            profile,
+            scenario,
+            feature_set,
+            perf_tool,
+            out,
+            collector,
+            iterations,
+            cargo,
+        } => {
+            let toolchains = match parse_compiler_json(&compilers_config) {
+                Ok(compilers) => compilers
+                    .into_iter()
+                    .flat_map(|compiler| compiler.paths)
+                    .collect::<Vec<_>>(),
+                Err(err) => {
+                    eprintln!("Fail to parse {:?}\n{}", compilers_config, err);
+                    return;
+                }
+            };
+
+            let mut bisector = Bisector {
+                toolchains,
+                benchmark_dir,
+                benchmark,
+                metric,
+                threshold,
+                runtime,
+                profile,
+                scenario,
+                feature_set,
+                perf_tool,
+                out,
+                collector,
+                iterations,
+                cargo,
+                probes: vec![],
+            };
+            match bisector.bisect() {
+                Ok(result) => match write_bisect_result(&bisector.out, &result) {
+                    Ok(_) => (),
+                    Err(err) => eprintln!("Fail to write result to {:?}\n{}", bisector.out, err),
+                },
+                Err(err) => eprintln!("Fail to bisect.\n{:?}", err),
+            }
+        }
+    }
+}
+
+/// Binary search of an ordered list of toolchains for the first one where a
+/// metric of a benchmark regresses, compared to the first toolchain.
+struct Bisector {
+    toolchains: Vec<PathBuf>,
+    benchmark_dir: PathBuf,
+    benchmark: String,
+    metric: String,
+    threshold: f64,
+    runtime: bool,
+    profile: Profile,
+    scenario: Scenario,
+    feature_set: String,
+    perf_tool: String,
+    out: PathBuf,
+    collector: PathBuf,
+    iterations: u32,
+    cargo: PathBuf,
+    /// Every toolchain measured so far, in the order they were measured.
+    probes: Vec<Probe>,
+}
+
+/// A toolchain measured during bisection.
+#[derive(Serialize, Debug, Clone)]
+struct Probe {
+    index: usize,
+    rustc: PathBuf,
+    samples: Vec<f64>,
+    /// Test against the samples of the first toolchain, which is not set
+    /// for the first toolchain itself.
+    test: Option<WelchTest>,
+    regressed: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct BisectResult {
+    benchmark: String,
+    metric: String,
+    threshold: f64,
+    /// The last toolchain without the regression.
+    last_good: Option<PathBuf>,
+    /// The first toolchain with the regression, if any regresses.
+    first_bad: Option<PathBuf>,
+    probes: Vec<Probe>,
+}
+
+impl Bisector {
+    fn bisect(&mut self) -> anyhow::Result<BisectResult> {
+        if self.toolchains.len() < 2 {
+            bail!("At least 2 toolchains are needed to bisect.");
+        }
+        if self.iterations < 2 {
+            bail!("At least 2 iterations are needed for the statistical test.");
+        }
+        create_dir_all(&self.out)?;
+        File::create(self.out.join("bisect_log.jsonl"))?;
+
+        let baseline = self.probe(0, None)?.samples;
+        let good_bad = first_regression(self.toolchains.len(), |index| {
+            Ok(self.probe(index, Some(&baseline))?.regressed)
+        })?;
+
+        match good_bad {
+            Some((good, bad)) => println!(
+                "{} of '{}' regresses first with {:?}, the last good toolchain is {:?}.",
+                self.metric, self.benchmark, self.toolchains[bad], self.toolchains[good]
+            ),
+            None => println!(
+                "{} of '{}' does not regress up to {:?}.",
+                self.metric,
+                self.benchmark,
+                self.toolchains.last().unwrap()
+            ),
+        }
+        Ok(self.result(good_bad))
+    }
+
+    fn result(&self, good_bad: Option<(usize, usize)>) -> BisectResult {
+        BisectResult {
+            benchmark: self.benchmark.clone(),
+            metric: self.metric.clone(),
+            threshold: self.threshold,
+            last_good: good_bad.map(|(good, _)| self.toolchains[good].clone()),
+            first_bad: good_bad.map(|(_, bad)| self.toolchains[bad].clone()),
+            probes: self.probes.clone(),
+        }
+    }
+
+    /// Measure toolchain `index` and test it against the `baseline` samples.
+    /// Every probe is logged to `bisect_log.jsonl` in `out`.
+    fn probe(&mut self, index: usize, baseline: Option<&[f64]>) -> anyhow::Result<Probe> {
+        let rustc = self.toolchains[index].clone();
+        println!(
+            "Probing toolchain {}/{}: {:?}",
+            index + 1,
+            self.toolchains.len(),
+            rustc
+        );
+
+        let out = self.out.join(format!("{:03}-{}", index, commit_id(&rustc)));
+        let output = run_collector(
+            &self.collector,
+            &String::from(if self.runtime {
+                "bench_runtime_local"
+            } else {
+                "bench_local"
+            }),
+            &self.benchmark_dir,
+            &out,
+            self.iterations,
+            0,
+            &self.cargo,
+            &rustc,
+            &self.collector_args(),
+        )?;
+        write_output(&out, &output)?;
+        if !output.status.success() {
+            bail!(
+                "Collector failed with {:?}, see {:?}.",
+                rustc,
+                out.join("log.txt")
+            );
+        }
+
+        let samples = self.read_samples(&out)?;
+        let test = match baseline {
+            Some(baseline) => Some(WelchTest::new(baseline, &samples).with_context(|| {
+                format!("Too few samples of {} to test {:?}", self.metric, rustc)
+            })?),
+            None => None,
+        };
+        let regressed = test
+            .as_ref()
+            .is_some_and(|t| t.significant && t.relative_change() > self.threshold);
+        match &test {
+            Some(test) => println!(
+                "    {}: {:.4} -> {:.4} ({:+.2}%, t = {:.2}), {}",
+                self.metric,
+                test.mean_a,
+                test.mean_b,
+                test.relative_change() * 100.,
+                test.t_value,
+                if regressed { "regressed" } else { "good" }
+            ),
+            None => println!("    {}: {:?} (baseline)", self.metric, samples),
+        }
+
+        let probe = Probe {
+            index,
+            rustc,
+            samples,
+            test,
+            regressed,
+        };
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(self.out.join("bisect_log.jsonl"))?;
+        writeln!(log, "{}", serde_json::to_string(&probe)?)?;
+        self.probes.push(probe.clone());
+        Ok(probe)
+    }
+
+    /// Arguments selecting the benchmark and how it is measured.
+    ///
+    /// `--include` matches names by prefix, so the benchmarks the name is a
+    /// proper prefix of, e.g. `regex-1.9` of `regex-1`, are excluded with a
+    /// glob to select the benchmark alone.
+    fn collector_args(&self) -> Vec<String> {
+        let mut args = vec![
+            String::from("--include"),
+            self.benchmark.clone(),
+            String::from("--exclude"),
+            format!("{}?*", self.benchmark),
+            String::from("--perf-tool"),
+            self.perf_tool.clone(),
+        ];
+        if !self.runtime {
+            args.extend([
+                String::from("--profile"),
+                self.profile.to_string(),
+                String::from("--scenarios"),
+                self.scenario.to_string(),
+            ]);
+        }
+        args
+    }
+
+    /// Values of the metric in each iteration of the benchmark, from the
+    /// results the collector wrote to `out`.
+    fn read_samples(&self, out: &Path) -> anyhow::Result<Vec<f64>> {
+        let samples = if self.runtime {
+            read_result_file::<RuntimeResultVec>(&out.join("runtime_results.json"))?
+                .data
+                .0
+                .into_iter()
+                .filter(|r| r.name == self.benchmark && r.feature_set == self.feature_set)
+                .flat_map(|r| r.stats)
+                .filter_map(|s| s.stats.get(&self.metric).copied())
+                .collect::<Vec<_>>()
+        } else {
+            read_result_file::<CompileTimeResultSet>(&out.join("compile_time_results.json"))?
+                .data
+                .results
+                .into_iter()
+                .filter(|r| r.benchmark == self.benchmark)
+                .flat_map(|r| r.result_vec)
+                .filter(|r| {
+                    r.profile == self.profile
+                        && r.scenario == self.scenario
+                        && r.feature_set == self.feature_set
+                        && r.patch.is_none()
+                })
+                .filter_map(|r| r.stats.stats.get(&self.metric).copied())
+                .collect::<Vec<_>>()
+        };
+        if samples.is_empty() {
+            bail!(
+                "No {} of '{}' recorded in {:?}.",
+                self.metric,
+                self.benchmark,
+                out
+            );
+        }
+        Ok(samples)
+    }
+}
+
+/// Binary search of `len` toolchains for the first one that `regressed`,
+/// given the first toolchain is good. The last toolchain is tested first, and
+/// `None` is returned if it does not regress. Otherwise returns the indices of
+/// the last good and the first bad toolchain.
+fn first_regression(
+    len: usize,
+    mut regressed: impl FnMut(usize) -> anyhow::Result<bool>,
+) -> anyhow::Result<Option<(usize, usize)>> {
+    let (mut good, mut bad) = (0, len - 1);
+    if !regressed(bad)? {
+        return Ok(None);
+    }
+
+    while bad - good > 1 {
+        let mid = (good + bad) / 2;
+        if regressed(mid)? {
+            bad = mid;
+        } else {
+            good = mid;
+        }
     }
+    Ok(Some((good, bad)))
+}
+
+fn write_bisect_result(dir: &PathBuf, result: &BisectResult) -> anyhow::Result<()> {
+    let fptr = File::create(dir.join("bisect_result.json"))?;
+    println!("bisect result {:?}", dir.join("bisect_result.json"));
+    serde_json::to_writer(BufWriter::new(fptr), result)?;
+    Ok(())
+}
+
+/// Name of the directory a toolchain is installed in, e.g. the commit of
+/// `<commit>/bin/rustc`.
+fn commit_id(rustc: &Path) -> String {
+    rustc
+        .parent()
+        .filter(|bin| bin.ends_with("bin"))
+        .and_then(|bin| bin.parent())
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("rustc"))
 }
 
 fn parse_compiler_json(compilers_config: &PathBuf) -> anyhow::Result<Vec<RustCompiler>> {
@@ -158,6 +489,7 @@ fn run_collector(
     flamegraph: u32,
     cargo: &PathBuf,
     rustc: &PathBuf,
+    extra_args: &[String],
 ) -> anyhow::Result<Output> {
     let mut cmd = Command::new(collector);
     cmd.arg(subcommand)
@@ -171,6 +503,7 @@ fn run_collector(
         .arg(flamegraph.to_string())
         .arg("--cargo")
         .arg(cargo)
+        .args(extra_args)
         .arg(rustc);
 
     match cmd.output() {
@@ -230,6 +563,63 @@ pub enum Commands {
         #[clap(long = "flamegraph")]
         flamegraph: u32,
 
+        #[clap(long = "cargo")]
+        cargo: PathBuf,
+    },
+    /// Binary-search the toolchains for the first one where a metric of a
+    /// benchmark regresses compared to the first toolchain.
+    Bisect {
+        /// The compilers config. The paths of all compilers, in the order
+        /// they are listed, are the toolchains to bisect, oldest first.
+        #[clap(long = "compilers-config")]
+        compilers_config: PathBuf,
+
+        #[clap(long = "benchmark-dir")]
+        benchmark_dir: PathBuf,
+
+        /// Name of the benchmark to measure.
+        #[clap(long = "benchmark")]
+        benchmark: String,
+
+        /// The metric to compare, e.g. `instructions:u` or `wall-time`. An
+        /// increase of it is a regression.
+        #[clap(long = "metric")]
+        metric: String,
+
+        /// The least significant increase of the metric relative to the first
+        /// toolchain counted as a regression, e.g. 0.02 for 2%.
+        #[clap(long = "threshold", default_value = "0.02")]
+        threshold: f64,
+
+        /// Bisect a runtime benchmark instead of a compile-time one.
+        #[clap(long = "runtime")]
+        runtime: bool,
+
+        /// The profile of a compile-time benchmark to compare.
+        #[clap(long = "profile", default_value = "debug")]
+        profile: Profile,
+
+        /// The scenario of a compile-time benchmark to compare, one of `full`,
+        /// `incr-full` and `incr-unchanged`.
+        #[clap(long = "scenario", default_value = "full")]
+        scenario: Scenario,
+
+        #[clap(long = "feature-set", default_value = "default")]
+        feature_set: String,
+
+        #[clap(long = "perf-tool", default_value = "perf-stat")]
+        perf_tool: String,
+
+        #[clap(long = "out")]
+        out: PathBuf,
+
+        #[clap(long = "collector")]
+        collector: PathBuf,
+
+        /// Iterations measured with each toolchain, at least 2.
+        #[clap(long = "iterations", default_value = "5")]
+        iterations: u32,
+
         #[clap(long = "cargo")]
         cargo: PathBuf,
     },
@@ -240,3 +630,52 @@ struct RustCompiler {
     name: String,
     paths: Vec<PathBuf>,
 }
+
+#[cfg(test)]
+mod test_bisect {
+    use collector::benchmark::filter::BenchmarkFilter;
+
+    use super::first_regression;
+
+    /// Test for first_regression
+    ///
+    /// Step1: Bisect a fake metric sequence that regresses from some toolchain
+    /// on, and check the toolchains probed.
+    ///
+    /// Step2: Bisect sequences that never regress or regress with the last
+    /// toolchain alone.
+    #[test]
+    fn test_first_regression() {
+        fn bisect(metrics: &[f64]) -> (Option<(usize, usize)>, Vec<usize>) {
+            let mut probed = vec![];
+            let good_bad = first_regression(metrics.len(), |index| {
+                probed.push(index);
+                Ok(metrics[index] > metrics[0] * 1.02)
+            })
+            .unwrap();
+            (good_bad, probed)
+        }
+
+        let metrics = [100., 101., 99., 100., 105., 104., 106., 105.];
+        let (good_bad, probed) = bisect(&metrics);
+        assert_eq!(good_bad, Some((3, 4)));
+        assert_eq!(probed, vec![7, 3, 5, 4]);
+
+        assert_eq!(bisect(&[100., 101., 100., 99.]).0, None);
+        assert_eq!(bisect(&[100., 101., 100., 110.]).0, Some((2, 3)));
+        assert_eq!(bisect(&[100., 110.]).0, Some((0, 1)));
+    }
+
+    /// Test for the benchmark selection of Bisector::collector_args
+    ///
+    /// Step1: Select benchmarks with the include and exclude patterns passed
+    /// to the collector, and check only the benchmark itself is selected.
+    #[test]
+    fn test_select_benchmark() {
+        let filter = BenchmarkFilter::new(Some("regex-1"), Some("regex-1?*"), None, None);
+        assert!(filter.accepts_name("regex-1"));
+        assert!(!filter.accepts_name("regex-1.9"));
+        assert!(!filter.accepts_name("regex-10"));
+        assert!(!filter.accepts_name("serde-1"));
+    }
+}
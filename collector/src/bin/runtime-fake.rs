@@ -1,9 +1,18 @@
 use std::{
     env, fs,
-    process::{Command, Stdio},
+    io::Read,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 
+use collector::execute::{perf_events, rusage};
+use sha2::{Digest, Sha256};
+
 fn main() {
     let mut arg_os = env::args_os();
     let _name = arg_os.next().unwrap().into_string().unwrap();
@@ -24,6 +33,12 @@ fn main() {
 
         raise_process_priority();
 
+        // The stdout of the benchmark is captured when its hash is checked, so
+        // perf stat writes to a file instead.
+        let perf_log = expected_output_sha256().map(|_| {
+            env::temp_dir().join(format!("runtime-fake-{}.perf-stat", std::process::id()))
+        });
+
         // These strings come from `PerfTool::name()`.
         match wrapper {
             "PerfStat" | "PerfStatSelfProfile" => {
@@ -35,11 +50,16 @@ fn main() {
                     // the output, but we want standard output on all systems.
                     .env("LC_NUMERIC", "C")
                     .arg("-x;")
+                    // Counts are scaled by the collector, which also records
+                    // how long multiplexed counters were active.
+                    .arg("--no-scale")
                     .arg("-e")
-                    .arg("instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses")
-                    .arg("--log-fd")
-                    .arg("1")
-                    .arg("setarch")
+                    .arg(perf_events());
+                match &perf_log {
+                    Some(perf_log) => cmd.arg("-o").arg(perf_log),
+                    None => cmd.arg("--log-fd").arg("1"),
+                };
+                cmd.arg("setarch")
                     .arg(std::env::consts::ARCH)
                     .arg("-R")
                     .arg(&elf)
@@ -78,14 +98,34 @@ fn main() {
                     .arg(&elf)
                     .args(&args);
             }
+
+            "Rusage" => {
+                cmd = Command::new(&elf);
+                cmd.args(&args).stderr(Stdio::null());
+                verify_status(run_with_rusage(&mut cmd));
+                return;
+            }
             _ => panic!(),
         }
 
         let start = Instant::now();
 
-        let _status = cmd.status().expect("failed to spawn");
+        configure_io(&mut cmd);
+        let mut child = cmd.spawn().expect("failed to spawn");
+        let output_hash = hash_output(&mut child);
+        let tracker = WorkloadMemoryTracker::start(child.id());
+        let status = child.wait().expect("failed to wait");
         let dur = start.elapsed();
+        verify_status(status);
+        verify_output(output_hash);
+        if let Some(perf_log) = &perf_log {
+            if let Ok(log) = fs::read_to_string(perf_log) {
+                print!("{}", log);
+                let _ = fs::remove_file(perf_log);
+            }
+        }
         print_memory();
+        print_workload_memory(tracker.finish());
         print_time(dur);
     };
 }
@@ -104,6 +144,90 @@ fn raise_process_priority() {
     }
 }
 
+/// Run `cmd` and print its resource usage in the format of `print_time`.
+/// Unlike `print_memory`, the usage only covers `cmd` and its descendants.
+#[cfg(unix)]
+fn run_with_rusage(cmd: &mut Command) -> std::process::ExitStatus {
+    let start = Instant::now();
+    configure_io(cmd);
+    let mut child = cmd.spawn().expect("failed to spawn");
+    let output_hash = hash_output(&mut child);
+    let tracker = WorkloadMemoryTracker::start(child.id());
+    let (status, usage) = rusage::wait_with_rusage(&child);
+    let dur = start.elapsed();
+    verify_output(output_hash);
+
+    rusage::print_rusage(&usage);
+    print_workload_memory(tracker.finish());
+    print_time(dur);
+    status
+}
+
+/// Pipe the input file named by `RUNTIME_STDIN` to the benchmark, and
+/// capture its stdout if `RUNTIME_EXPECTED_SHA256` is set.
+fn configure_io(cmd: &mut Command) {
+    if let Some(stdin) = env::var_os("RUNTIME_STDIN") {
+        let file = fs::File::open(&stdin)
+            .unwrap_or_else(|e| panic!("failed to open input {:?}: {}", stdin, e));
+        cmd.stdin(file);
+    }
+    if expected_output_sha256().is_some() {
+        cmd.stdout(Stdio::piped());
+    }
+}
+
+fn expected_output_sha256() -> Option<String> {
+    env::var("RUNTIME_EXPECTED_SHA256")
+        .ok()
+        .map(|s| s.trim().to_lowercase())
+}
+
+/// Hash the captured stdout of `child` while it runs.
+fn hash_output(child: &mut Child) -> Option<JoinHandle<String>> {
+    let mut stdout = child.stdout.take()?;
+    Some(thread::spawn(move || {
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match stdout.read(&mut buf).expect("failed to read stdout") {
+                0 => break,
+                n => hasher.update(&buf[..n]),
+            }
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }))
+}
+
+/// Exit with an error if the benchmark failed, e.g. because it was
+/// miscompiled and crashed, so that the collector reports the iteration as a
+/// failure rather than measuring it.
+fn verify_status(status: ExitStatus) {
+    if !status.success() {
+        eprintln!("error: benchmark exited with {}", status);
+        std::process::exit(status.code().filter(|c| *c != 0).unwrap_or(1));
+    }
+}
+
+/// Exit with an error if the output of the benchmark does not match
+/// `RUNTIME_EXPECTED_SHA256`, so that the collector reports the iteration
+/// as a correctness failure rather than measuring it.
+fn verify_output(output_hash: Option<JoinHandle<String>>) {
+    if let (Some(output_hash), Some(expected)) = (output_hash, expected_output_sha256()) {
+        let actual = output_hash.join().unwrap();
+        if actual != expected {
+            eprintln!(
+                "error: output sha256 mismatch: expected {}, got {}",
+                expected, actual
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
 fn print_memory() {
     use std::mem;
 
@@ -117,6 +241,148 @@ fn print_memory() {
     }
 }
 
+fn print_workload_memory(max_rss: Option<u64>) {
+    if let Some(max_rss) = max_rss {
+        println!("{};;workload-max-rss;3;100.00", max_rss);
+    }
+}
+
+/// Samples the peak RSS of the benchmark workload while `cmd` runs.
+///
+/// `max-rss` is the largest of all children ever waited for, so for
+/// `cargo test` or `cargo bench` it is often cargo's own memory. The tracker
+/// walks the process tree below the child instead: `perf`, `setarch` and
+/// `cargo` are only looked through, `rustc` and build scripts are skipped,
+/// and every other process is part of the workload, e.g. a test binary.
+/// The result is the largest `VmHWM` (in KB, like `max-rss`) among those.
+///
+/// A process is only seen while it is alive, so one living shorter than the
+/// 5 ms sampling interval may be missed.
+struct WorkloadMemoryTracker {
+    done: Arc<AtomicBool>,
+    handle: JoinHandle<Option<u64>>,
+}
+
+impl WorkloadMemoryTracker {
+    const INTERVAL: Duration = Duration::from_millis(5);
+
+    fn start(pid: u32) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut peak = None;
+                while !done.load(Ordering::Relaxed) {
+                    peak = peak.max(workload_max_rss(pid));
+                    thread::sleep(Self::INTERVAL);
+                }
+                peak
+            })
+        };
+        WorkloadMemoryTracker { done, handle }
+    }
+
+    fn finish(self) -> Option<u64> {
+        self.done.store(true, Ordering::Relaxed);
+        self.handle.join().unwrap()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ProcessKind {
+    /// Runs the workload, e.g. `perf`, `setarch` or `cargo`.
+    Launcher,
+    /// Builds the workload, e.g. `rustc` or a build script.
+    Compiler,
+    Workload,
+}
+
+impl ProcessKind {
+    /// Classify a process by its `comm`, which is truncated to 15 bytes.
+    fn of(comm: &str) -> Self {
+        if comm.starts_with("perf") || comm == "setarch" || comm == "cargo" {
+            ProcessKind::Launcher
+        } else if comm == "rustc"
+            || comm.starts_with("build-script")
+            || comm.starts_with("build_script")
+        {
+            ProcessKind::Compiler
+        } else {
+            ProcessKind::Workload
+        }
+    }
+}
+
+/// Largest current `VmHWM` of the workload processes below `pid`.
+#[cfg(target_os = "linux")]
+fn workload_max_rss(pid: u32) -> Option<u64> {
+    let children = children_of_all();
+    let mut peak = None;
+    let mut stack = vec![(pid, false)];
+    while let Some((pid, in_workload)) = stack.pop() {
+        let comm = match fs::read_to_string(format!("/proc/{}/comm", pid)) {
+            Ok(comm) => comm.trim().to_string(),
+            Err(_) => continue,
+        };
+        let in_workload = in_workload
+            || match ProcessKind::of(&comm) {
+                ProcessKind::Launcher => false,
+                ProcessKind::Compiler => continue,
+                ProcessKind::Workload => true,
+            };
+        if in_workload {
+            let status = fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+            peak = peak.max(parse_vm_hwm(&status));
+        }
+        if let Some(children) = children.get(&pid) {
+            stack.extend(children.iter().map(|c| (*c, in_workload)));
+        }
+    }
+    peak
+}
+
+#[cfg(not(target_os = "linux"))]
+fn workload_max_rss(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Map from every process to its children, read from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn children_of_all() -> std::collections::HashMap<u32, Vec<u32>> {
+    let mut children = std::collections::HashMap::<u32, Vec<u32>>::new();
+    for entry in fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let stat = match fs::read_to_string(entry.path().join("stat")) {
+            Ok(stat) => stat,
+            Err(_) => continue,
+        };
+        // `comm` may contain spaces or parentheses, so skip past the last `)`.
+        let ppid = stat
+            .rfind(')')
+            .and_then(|i| stat[i + 1..].split_whitespace().nth(1))
+            .and_then(|s| s.parse::<u32>().ok());
+        if let Some(ppid) = ppid {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    children
+}
+
+/// Peak resident set size in KB from the content of `/proc/<pid>/status`.
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
 fn print_time(dur: Duration) {
     // Format output the same as `perf stat` in CSV mode, explained at
     // http://man7.org/linux/man-pages/man1/perf-stat.1.html#CSV_FORMAT
@@ -130,3 +396,43 @@ fn print_time(dur: Duration) {
         dur.subsec_nanos()
     );
 }
+
+#[cfg(test)]
+mod test_workload_memory {
+    use super::{parse_vm_hwm, ProcessKind};
+
+    /// Test for parse_vm_hwm
+    ///
+    /// Step1: Parse `VmHWM` from the content of `/proc/<pid>/status`.
+    ///
+    /// Step2: Get `None` if it is missing or malformed.
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status =
+            "Name:\tbench\nVmPeak:\t  20480 kB\nVmHWM:\t    4096 kB\nVmRSS:\t    2048 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(4096));
+
+        assert_eq!(
+            parse_vm_hwm("Name:\tkthreadd\nState:\tS (sleeping)\n"),
+            None
+        );
+        assert_eq!(parse_vm_hwm("VmHWM:\t  n/a kB\n"), None);
+        assert_eq!(parse_vm_hwm(""), None);
+    }
+
+    /// Test for ProcessKind::of
+    ///
+    /// Step1: Classify launchers, compilers and workloads by `comm`.
+    #[test]
+    fn test_process_kind() {
+        for comm in ["perf", "perf-exec", "setarch", "cargo"] {
+            assert_eq!(ProcessKind::of(comm), ProcessKind::Launcher, "{}", comm);
+        }
+        for comm in ["rustc", "build-script-bu", "build_script_bu"] {
+            assert_eq!(ProcessKind::of(comm), ProcessKind::Compiler, "{}", comm);
+        }
+        for comm in ["bench-1a2b3c4d5", "rustdoc", "sh"] {
+            assert_eq!(ProcessKind::of(comm), ProcessKind::Workload, "{}", comm);
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use anyhow::Context;
+use collector::execute::perf_events;
+#[cfg(unix)]
+use collector::execute::rusage;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
@@ -29,7 +32,7 @@ fn run_with_determinism_env(mut cmd: Command) {
     );
 }
 
-fn main() {
+pub fn main() {
     let mut args_os = env::args_os();
     let name = args_os.next().unwrap().into_string().unwrap();
 
@@ -82,8 +85,11 @@ fn main() {
                     // the output, but we want standard output on all systems.
                     .env("LC_NUMERIC", "C")
                     .arg("-x;")
+                    // Counts are scaled by the collector, which also records
+                    // how long multiplexed counters were active.
+                    .arg("--no-scale")
                     .arg("-e")
-                    .arg("instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses")
+                    .arg(perf_events())
                     .arg("--log-fd")
                     .arg("1")
                     .arg("setarch")
@@ -206,14 +212,7 @@ fn main() {
             }
 
             "PerfRecord" => {
-                let out_file = if let Some(pos) = args.iter().position(|arg| arg == "--output") {
-                    args.remove(pos);
-                    let out_file = args.remove(pos);
-                    let out_file = out_file.to_str().unwrap().to_string();
-                    out_file
-                } else {
-                    "perf.data".to_string()
-                };
+                let out_file = take_output_arg(&mut args, "perf.data");
                 let mut cmd = Command::new("perf");
                 let has_perf = cmd.output().is_ok();
                 assert!(has_perf);
@@ -227,6 +226,18 @@ fn main() {
                 run_with_determinism_env(cmd);
             }
 
+            "Rusage" => {
+                let mut cmd = Command::new(&tool);
+                cmd.args(&args);
+                determinism_env(&mut cmd);
+                let status = run_with_rusage(&mut cmd);
+                assert!(
+                    status.success(),
+                    "command did not complete successfully: {:?}",
+                    cmd
+                );
+            }
+
             "Oprofile" => {
                 let mut cmd = Command::new("operf");
                 let has_oprofile = cmd.output().is_ok();
@@ -238,12 +249,13 @@ fn main() {
             }
 
             "Cachegrind" => {
+                let out_file = take_output_arg(&mut args, "cgout");
                 let mut cmd = Command::new("valgrind");
                 let has_valgrind = cmd.output().is_ok();
                 assert!(has_valgrind);
 
-                // With --cache-sim=no and --branch-sim=no, Cachegrind just
-                // collects instruction counts.
+                // With --branch-sim=no, Cachegrind collects instruction counts
+                // and simulated D1/LL cache misses.
                 cmd
                     // We disable jemalloc's delayed purging to eliminate noise
                     // when benchmarks are around the 10 second mark.
@@ -252,9 +264,9 @@ fn main() {
                     // further details.
                     .env("MALLOC_CONF", "dirty_decay_ms:0,muzzy_decay_ms:0")
                     .arg("--tool=cachegrind")
-                    .arg("--cache-sim=no")
+                    .arg("--cache-sim=yes")
                     .arg("--branch-sim=no")
-                    .arg("--cachegrind-out-file=cgout")
+                    .arg(format!("--cachegrind-out-file={}", out_file))
                     .arg(&tool)
                     .args(&args);
 
@@ -262,6 +274,7 @@ fn main() {
             }
 
             "Callgrind" => {
+                let out_file = take_output_arg(&mut args, "clgout");
                 let mut cmd = Command::new("valgrind");
                 let has_valgrind = cmd.output().is_ok();
                 assert!(has_valgrind);
@@ -271,7 +284,7 @@ fn main() {
                 cmd.arg("--tool=callgrind")
                     .arg("--cache-sim=no")
                     .arg("--branch-sim=no")
-                    .arg("--callgrind-out-file=clgout")
+                    .arg(format!("--callgrind-out-file={}", out_file))
                     .arg(&tool)
                     .args(&args);
 
@@ -279,12 +292,13 @@ fn main() {
             }
 
             "Dhat" => {
+                let out_file = take_output_arg(&mut args, "dhout");
                 let mut cmd = Command::new("valgrind");
                 let has_valgrind = cmd.output().is_ok();
                 assert!(has_valgrind);
                 cmd.arg("--tool=dhat")
                     .arg("--num-callers=8")
-                    .arg("--dhat-out-file=dhout")
+                    .arg(format!("--dhat-out-file={}", out_file))
                     .arg(&tool)
                     .args(&args);
 
@@ -306,6 +320,7 @@ fn main() {
             }
 
             "Massif" => {
+                let out_file = take_output_arg(&mut args, "msout");
                 let mut cmd = Command::new("valgrind");
                 let has_valgrind = cmd.output().is_ok();
                 assert!(has_valgrind);
@@ -313,7 +328,7 @@ fn main() {
                     .arg("--heap-admin=0")
                     .arg("--depth=15")
                     .arg("--threshold=0.2")
-                    .arg("--massif-out-file=msout")
+                    .arg(format!("--massif-out-file={}", out_file))
                     .arg("--alloc-fn=__rdl_alloc")
                     .arg(&tool)
                     .args(&args);
@@ -469,6 +484,17 @@ fn process_self_profile_output(prof_out_dir: PathBuf, args: &[OsString]) {
     }
 }
 
+/// Strip out `--output <file>`, which the collector passes to choose where a
+/// profiler writes its output, returning the file or `default`.
+fn take_output_arg(args: &mut Vec<OsString>, default: &str) -> String {
+    if let Some(pos) = args.iter().position(|arg| arg == "--output") {
+        args.remove(pos);
+        args.remove(pos).to_str().unwrap().to_string()
+    } else {
+        default.to_string()
+    }
+}
+
 #[cfg(windows)]
 fn exec(cmd: &mut Command) -> ! {
     let cmd_d = format!("{:?}", cmd);
@@ -540,6 +566,25 @@ fn run_summarize(name: &str, prof_out_dir: &Path, prefix: &str) -> anyhow::Resul
 #[cfg(windows)]
 fn print_memory() {}
 
+/// Run `cmd` and print its resource usage in the format of `print_time`.
+/// Unlike `print_memory`, the usage only covers `cmd` and its descendants.
+#[cfg(unix)]
+fn run_with_rusage(cmd: &mut Command) -> std::process::ExitStatus {
+    let child = cmd.spawn().expect("failed to spawn");
+    let start = Instant::now();
+    let (status, usage) = rusage::wait_with_rusage(&child);
+    let dur = start.elapsed();
+
+    rusage::print_rusage(&usage);
+    print_time(dur);
+    status
+}
+
+#[cfg(windows)]
+fn run_with_rusage(_cmd: &mut Command) -> std::process::ExitStatus {
+    panic!("the rusage wrapper is not supported on windows");
+}
+
 #[cfg(unix)]
 pub fn raise_process_priority() {
     unsafe {
@@ -0,0 +1,9 @@
+//! `rustc-fake` decides whether to wrap rustc or rustdoc by the name it is
+//! invoked with, so build it a second time under the name `rustdoc-fake`.
+
+#[path = "rustc-fake.rs"]
+mod rustc_fake;
+
+fn main() {
+    rustc_fake::main()
+}
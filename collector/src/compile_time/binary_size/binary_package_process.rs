@@ -45,6 +45,8 @@ impl<'a> BinaryProcess for BinaryPackageProcess<'a> {
                 Profile::Release => {
                     cmd.arg("--release");
                 }
+                // Passed with `cargo_args`.
+                Profile::Custom(_) => (),
             }
 
             cmd.stdout(Stdio::null()).stderr(Stdio::null());
@@ -42,6 +42,8 @@ impl<'a> BinaryProcess for BinarySingleProcess<'a> {
             Profile::Release => {
                 cmd.arg("--release");
             }
+            // Passed with `cargo_args`.
+            Profile::Custom(_) => (),
         }
 
         cmd.stdout(Stdio::null()).stderr(Stdio::null());
@@ -1,6 +1,9 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf};
 
-use crate::{benchmark::profile::Profile, statistics::compile_time_stat::CompileTimeBenchResult};
+use crate::{
+    benchmark::profile::Profile,
+    statistics::{compile_time_stat::CompileTimeBenchResult, metadata::read_result_file},
+};
 
 use super::BINARY_SIZE_LABEL;
 
@@ -13,10 +16,9 @@ pub fn compare_binary_size(
     profile: Profile,
 ) -> HashMap<String, f64> {
     let read_binary_size = |p: &PathBuf| {
-        let data: Vec<CompileTimeBenchResult> =
-            serde_json::from_reader(BufReader::new(File::open(p).unwrap())).unwrap();
+        let data: Vec<CompileTimeBenchResult> = read_result_file(p).unwrap().data;
         data.into_iter()
-            .map(move |i| {
+            .map(|i| {
                 (
                     i.get_benchmark(),
                     *i.get_stats_ref_by_profile(&profile)
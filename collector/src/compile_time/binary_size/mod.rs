@@ -8,6 +8,8 @@ use std::{
 use crate::{
     benchmark::{
         benchmark::{Benchamrk, BenchmarkSuit, CompileTimeType},
+        feature_set::FeatureSet,
+        filter::BenchmarkFilter,
         profile::Profile,
         scenario::Scenario,
     },
@@ -28,7 +30,7 @@ pub mod binary_single_process;
 pub mod compare;
 pub mod plotter;
 
-const BINARY_SIZE_LABEL: &str = "binary_size";
+pub(crate) const BINARY_SIZE_LABEL: &str = "binary_size";
 
 pub trait BinaryProcess {
     fn run_rustc(&self) -> anyhow::Result<Option<Stats>>;
@@ -76,9 +78,10 @@ pub fn bench_binary_size(
     ltc: &LocalToolchain,
     profiles: &[Profile],
     benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
 ) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
     let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
+        benchmarks: discover_benchmark_suit(&benchmark_dir, filter)?,
     };
     println!("{}", benchmark_suit.display_benchmarks());
 
@@ -114,21 +117,27 @@ impl Benchamrk {
 
         let mut bench_result = CompileTimeBenchResult::new(self.name.clone(), 1);
 
-        for profile in profiles {
-            let binary_size_process = self.make_binray_size_process(
-                Compiler::from_toolchain(ltc),
-                tmp_dir.path(),
-                *profile,
-            );
-
-            if let Some(stats) = binary_size_process.run_rustc()? {
-                bench_result.add_result(CompileTimeResult::new(
-                    self.name.clone(),
-                    1,
-                    *profile,
-                    Scenario::Full,
-                    stats,
-                ));
+        for feature_set in self.feature_sets() {
+            for profile in profiles {
+                let binary_size_process = self.make_binray_size_process(
+                    Compiler::from_toolchain(ltc),
+                    tmp_dir.path(),
+                    profile.clone(),
+                    &feature_set,
+                );
+
+                if let Some(stats) = binary_size_process.run_rustc()? {
+                    bench_result.add_result(
+                        CompileTimeResult::new(
+                            self.name.clone(),
+                            1,
+                            profile.clone(),
+                            Scenario::Full,
+                            stats,
+                        )
+                        .with_feature_set(feature_set.name.clone()),
+                    );
+                }
             }
         }
 
@@ -144,6 +153,7 @@ impl Benchamrk {
         compiler: Compiler<'a>,
         cwd: &'a Path,
         profile: Profile,
+        feature_set: &FeatureSet,
     ) -> Box<dyn BinaryProcess + 'a> {
         let compile_type = if let Some(t) = &self.config.compile_time_type {
             t
@@ -156,7 +166,7 @@ impl Benchamrk {
                 compiler,
                 processor_name: self.name.clone(),
                 cwd,
-                profile,
+                profile: profile.clone(),
                 incremental: false,
                 manifest_path: self
                     .config
@@ -171,6 +181,8 @@ impl Benchamrk {
                     .unwrap_or_default()
                     .split_whitespace()
                     .map(String::from)
+                    .chain(self.profile_cargo_args(&profile))
+                    .chain(feature_set.cargo_args())
                     .collect::<Vec<_>>(),
                 rustc_args: self
                     .config
@@ -187,7 +199,7 @@ impl Benchamrk {
                 compiler,
                 processor_name: self.name.clone(),
                 cwd,
-                profile,
+                profile: profile.clone(),
                 incremental: false,
                 manifest_path: self
                     .config
@@ -202,6 +214,8 @@ impl Benchamrk {
                     .unwrap_or_default()
                     .split_whitespace()
                     .map(String::from)
+                    .chain(self.profile_cargo_args(&profile))
+                    .chain(feature_set.cargo_args())
                     .collect::<Vec<_>>(),
                 rustc_args: self
                     .config
@@ -229,7 +243,7 @@ mod test_binary_size {
     use anyhow::Context;
 
     use crate::{
-        benchmark::profile::Profile,
+        benchmark::{filter::BenchmarkFilter, profile::Profile},
         compile_time::binary_size::{binary_single_process::BinarySingleProcess, BinaryProcess},
         toolchain::{Compiler, LocalToolchain},
     };
@@ -271,12 +285,14 @@ mod test_binary_size {
         let results = bench_binary_size(
             &LocalToolchain {
                 rustc: PathBuf::from(get_rustc().unwrap()),
+                rustdoc: None,
                 cargo: PathBuf::from(get_cargo().unwrap()),
                 flame_graph: PathBuf::new(),
                 id: String::new(),
             },
             &[Profile::Release],
             PathBuf::from("test/binary_size/benchmarks"),
+            &BenchmarkFilter::default(),
         )
         .unwrap();
 
@@ -298,6 +314,7 @@ mod test_binary_size {
         let binary_process = BinarySingleProcess {
             compiler: Compiler {
                 rustc: Path::new("null"),
+                rustdoc: None,
                 cargo: Path::new("null"),
                 is_nightly: false,
             },
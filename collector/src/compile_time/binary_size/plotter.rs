@@ -1,14 +1,13 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::BufReader,
     path::PathBuf,
     process::{Command, Stdio},
 };
 
 use crate::{
-    benchmark::profile::Profile, compile_time::binary_size::BINARY_SIZE_LABEL,
-    statistics::compile_time_stat::CompileTimeBenchResult,
+    benchmark::profile::Profile,
+    compile_time::binary_size::BINARY_SIZE_LABEL,
+    statistics::{compile_time_stat::CompileTimeBenchResult, metadata::read_result_file},
 };
 
 pub fn plot(
@@ -19,11 +18,9 @@ pub fn plot(
     out_path: PathBuf,
     profile: Profile,
 ) -> anyhow::Result<()> {
-    let data_a: Vec<CompileTimeBenchResult> =
-        serde_json::from_reader(BufReader::new(File::open(data_file_a)?))?;
+    let data_a: Vec<CompileTimeBenchResult> = read_result_file(&data_file_a)?.data;
 
-    let data_b: Vec<CompileTimeBenchResult> =
-        serde_json::from_reader(BufReader::new(File::open(data_file_b)?))?;
+    let data_b: Vec<CompileTimeBenchResult> = read_result_file(&data_file_b)?.data;
 
     let get_benchmark_binary_size = |d: &Vec<CompileTimeBenchResult>| {
         d.iter()
@@ -5,7 +5,7 @@ use std::{
 
 use crate::{
     benchmark::profile::Profile,
-    execute::{process_benchmark_output, Stats},
+    execute::{process_benchmark_output, valgrind::process_valgrind_output, Stats},
     toolchain::{Compiler, PerfTool},
     utils::{
         self,
@@ -13,7 +13,7 @@ use crate::{
     },
 };
 
-use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC};
+use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC, FAKE_RUSTDOC};
 
 pub struct CargoPackageProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -52,6 +52,11 @@ impl<'a> CargoPackageProcess<'a> {
             .arg("--manifest-path")
             .arg(&self.manifest_path);
 
+        if let Some(rustdoc) = self.compiler.rustdoc {
+            cmd.env("RUSTDOC", &*FAKE_RUSTDOC)
+                .env("RUSTDOC_REAL", rustdoc);
+        }
+
         cmd
     }
 
@@ -69,12 +74,18 @@ impl<'a> CargoPackageProcess<'a> {
             .arg(out.join(flame_graph_file_name))
             .arg("--")
             .arg(&self.compiler.cargo)
-            .arg("rustc")
+            .arg(match self.profile {
+                Profile::Doc => "rustdoc",
+                _ => "rustc",
+            })
             .env("RUSTC", self.compiler.rustc)
             .arg("--package")
             .arg(package)
             .arg("--manifest-path")
             .arg(&self.manifest_path);
+        if let Some(rustdoc) = self.compiler.rustdoc {
+            cmd.env("RUSTDOC", rustdoc);
+        }
         cmd
     }
 }
@@ -86,7 +97,10 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
         _event_filter_file: &PathBuf,
         needs_final: bool,
     ) -> anyhow::Result<Option<Stats>> {
-        let cargo_subcommand = "rustc";
+        let cargo_subcommand = match self.profile {
+            Profile::Doc => "rustdoc",
+            _ => "rustc",
+        };
 
         let mut stats_sum = Stats::default();
 
@@ -112,6 +126,8 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
                 Profile::Release => {
                     cmd.arg("--release");
                 }
+                // Passed with `cargo_args`.
+                Profile::Custom(_) => {}
             }
             cmd.args(&self.cargo_args);
 
@@ -129,11 +145,20 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
             if needs_final {
                 let perf_tool_name = perf_tool.name();
                 match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => (),
+                    crate::toolchain::Bencher::PerfStat
+                    | crate::toolchain::Bencher::Rusage
+                    | crate::toolchain::Bencher::PerfStatSelfProfile => (),
                     crate::toolchain::Bencher::PerfRecord => {
                         cmd.arg("--output")
                             .arg(self.cwd.join(format!("{}_perf.data", package)));
                     }
+                    crate::toolchain::Bencher::Valgrind(tool) => {
+                        cmd.arg("--output").arg(self.cwd.join(format!(
+                            "{}_{}",
+                            package,
+                            tool.output_file()
+                        )));
+                    }
                 }
                 // If we're using a processor, we expect that only the crate
                 // we're interested in benchmarking will be built, not any
@@ -183,7 +208,9 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
                 Ok(output) => {
                     log::debug!("output: \n{:?}", output);
                     match perf_tool.get_bencher() {
-                        crate::toolchain::Bencher::PerfStat => {
+                        crate::toolchain::Bencher::PerfStat
+                        | crate::toolchain::Bencher::Rusage
+                        | crate::toolchain::Bencher::PerfStatSelfProfile => {
                             let stats = process_benchmark_output(output);
                             match stats {
                                 Ok(stats) => stats_sum += stats,
@@ -198,6 +225,22 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
                             }
                         }
                         crate::toolchain::Bencher::PerfRecord => (),
+                        crate::toolchain::Bencher::Valgrind(tool) => {
+                            match process_valgrind_output(
+                                tool,
+                                &self.cwd.join(format!("{}_{}", package, tool.output_file())),
+                            ) {
+                                Ok(stats) => stats_sum += stats,
+                                Err(err) => {
+                                    eprintln!(
+                                        "Fail to bench package {} [{:?}]. Skip",
+                                        package, self.profile
+                                    );
+                                    eprintln!("error msg: {:?}", err);
+                                    continue;
+                                }
+                            }
+                        }
                     }
                 }
                 Err(err) => {
@@ -212,7 +255,10 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
         }
 
         match perf_tool.get_bencher() {
-            crate::toolchain::Bencher::PerfStat => return Ok(Some(stats_sum)),
+            crate::toolchain::Bencher::PerfStat
+            | crate::toolchain::Bencher::Rusage
+            | crate::toolchain::Bencher::PerfStatSelfProfile
+            | crate::toolchain::Bencher::Valgrind(_) => return Ok(Some(stats_sum)),
             crate::toolchain::Bencher::PerfRecord => return Ok(None),
         }
     }
@@ -239,6 +285,8 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
                 Profile::Release => {
                     cmd.arg("--profile").arg("release");
                 }
+                // Passed with `cargo_args`.
+                Profile::Custom(_) => {}
             };
 
             cmd.args(&self.cargo_args);
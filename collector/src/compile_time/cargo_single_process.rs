@@ -7,15 +7,15 @@ use anyhow::Context;
 
 use crate::{
     benchmark::profile::Profile,
-    execute::{process_benchmark_output, Stats},
-    toolchain::{Compiler, PerfTool},
+    execute::{process_benchmark_output, valgrind::process_valgrind_output, Stats},
+    toolchain::{Bencher, Compiler, PerfTool},
     utils::{
         self,
         command::{command_discard_output, command_output},
     },
 };
 
-use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC};
+use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC, FAKE_RUSTDOC};
 
 pub struct CargoSingleProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -51,6 +51,11 @@ impl<'a> CargoSingleProcess<'a> {
             .arg("--manifest-path")
             .arg(&self.manifest_path);
 
+        if let Some(rustdoc) = self.compiler.rustdoc {
+            cmd.env("RUSTDOC", &*FAKE_RUSTDOC)
+                .env("RUSTDOC_REAL", rustdoc);
+        }
+
         cmd
     }
 
@@ -68,10 +73,16 @@ impl<'a> CargoSingleProcess<'a> {
             .arg(out.join(flame_graph_file_name))
             .arg("--")
             .arg(&self.compiler.cargo)
-            .arg("rustc")
+            .arg(match self.profile {
+                Profile::Doc => "rustdoc",
+                _ => "rustc",
+            })
             .env("RUSTC", self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "0")
             .env("RUSTC_BOOTSTRAP", "1");
+        if let Some(rustdoc) = self.compiler.rustdoc {
+            cmd.env("RUSTDOC", rustdoc);
+        }
         cmd
     }
 
@@ -83,6 +94,14 @@ impl<'a> CargoSingleProcess<'a> {
         let package_id = std::str::from_utf8(&out).unwrap();
         Ok(package_id.trim().to_string())
     }
+
+    /// Where rustc-fake writes the output of a valgrind tool.
+    fn valgrind_output_file(&self, perf_tool: &PerfTool) -> Option<PathBuf> {
+        match perf_tool.get_bencher() {
+            Bencher::Valgrind(tool) => Some(self.cwd.join(tool.output_file())),
+            _ => None,
+        }
+    }
 }
 
 impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
@@ -92,7 +111,10 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
         _event_filter_file: &PathBuf,
         needs_final: bool,
     ) -> anyhow::Result<Option<Stats>> {
-        let cargo_subcommand = "rustc";
+        let cargo_subcommand = match self.profile {
+            Profile::Doc => "rustdoc",
+            _ => "rustc",
+        };
 
         let mut cmd = self.base_command(self.cwd, cargo_subcommand);
 
@@ -106,6 +128,8 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
             Profile::Release => {
                 cmd.arg("--release");
             }
+            // Passed with `cargo_args`.
+            Profile::Custom(_) => {}
         }
         cmd.args(&self.cargo_args);
 
@@ -126,6 +150,9 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
             // we're interested in benchmarking will be built, not any
             // dependencies.
             // cmd.env("EXPECT_ONLY_WRAPPED_RUSTC", "1");
+            if let Some(file) = self.valgrind_output_file(perf_tool) {
+                cmd.arg("--output").arg(file);
+            }
             cmd.arg("--wrap-rustc-with");
             cmd.arg(perf_tool_name);
             cmd.args(&self.rustc_args);
@@ -170,8 +197,16 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
         log::debug!("output: \n{:?}", output);
 
         match perf_tool.get_bencher() {
-            crate::toolchain::Bencher::PerfStat => Ok(Some(process_benchmark_output(output)?)),
+            crate::toolchain::Bencher::PerfStat
+            | crate::toolchain::Bencher::Rusage
+            | crate::toolchain::Bencher::PerfStatSelfProfile => {
+                Ok(Some(process_benchmark_output(output)?))
+            }
             crate::toolchain::Bencher::PerfRecord => Ok(None),
+            crate::toolchain::Bencher::Valgrind(tool) => Ok(Some(process_valgrind_output(
+                tool,
+                &self.valgrind_output_file(perf_tool).unwrap(),
+            )?)),
         }
     }
 
@@ -186,6 +221,8 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
             Profile::Release => {
                 cmd.arg("--profile").arg("release");
             }
+            // Passed with `cargo_args`.
+            Profile::Custom(_) => {}
         };
 
         cmd.arg("--manifest-path").arg(&self.manifest_path);
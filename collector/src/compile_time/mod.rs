@@ -1,19 +1,30 @@
 use std::{
-    fs::read_dir,
+    collections::HashMap,
+    fs::{read_dir, File},
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 use anyhow::{bail, Context, Ok};
+use tempfile::TempDir;
 
 use crate::{
     benchmark::{
         benchmark::{Benchamrk, BenchmarkSuit},
-        profile::Profile,
+        failure::BenchmarkFailure,
+        filter::BenchmarkFilter,
+        interleave::Side,
+        profile::{CustomProfile, Profile},
+        run_options::RunOptions,
         scenario::Scenario,
     },
     execute::Stats,
-    statistics::compile_time_stat::CompileTimeBenchResult,
-    toolchain::{Compiler, LocalToolchain, PerfTool},
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
+        metadata::{ResultFile, RunMetadata},
+    },
+    toolchain::{Bencher, Compiler, LocalToolchain, PerfTool, ResultWriter},
+    utils::checkpoint::Checkpoint,
 };
 
 pub(crate) mod binary_size;
@@ -22,47 +33,147 @@ pub(crate) mod cargo_single_process;
 
 pub(crate) fn bench_compile_time(
     ltc: &LocalToolchain,
-    perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
+    options: &RunOptions,
     profiles: &[Profile],
     scenarios: &[Scenario],
     benchmark_dir: PathBuf,
-    iterations: usize,
-    flame_graph_path: &Path,
-    flamegraph_flag: i32,
-    out_dir: &Path,
+    filter: &BenchmarkFilter,
+    resume: bool,
 ) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
+    let started_at = SystemTime::now();
+    let out_dir = options.out_dir;
+    let mut checkpoint = Checkpoint::new(out_dir, "compile_time_checkpoint.jsonl", resume)?;
+
     let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
+        benchmarks: discover_benchmark_suit(&benchmark_dir, filter)?,
     };
     println!("{}", benchmark_suit.display_benchmarks());
 
+    let mut failures = vec![];
     let result = bench(
-        perf_tool,
-        event_filter_file,
-        &profiles,
-        &scenarios,
+        options,
+        profiles,
+        scenarios,
         Compiler::from_toolchain(ltc),
         &benchmark_suit.benchmarks,
-        Some(iterations),
-        flame_graph_path,
-        flamegraph_flag,
-        out_dir,
+        &mut checkpoint,
+        &mut failures,
     )?;
+
+    let metadata = RunMetadata::collect(&ltc.rustc, &ltc.cargo, &benchmark_dir, started_at);
+    write_failures(out_dir, metadata, &failures)?;
     Ok(result)
 }
 
-pub(crate) fn bench<'a>(
-    perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
+/// Measure every benchmark with the toolchains `ltcs` of both sides,
+/// alternating them iteration by iteration in the order of `schedule`. Each
+/// run is set up in a fresh temp dir, exactly like in `bench_compile_time`.
+/// The iterations, adaptive mode and flamegraphs of `options` are not used.
+pub(crate) fn bench_compile_time_interleaved(
+    ltcs: [&LocalToolchain; 2],
+    options: &RunOptions,
+    profiles: &[Profile],
+    scenarios: &[Scenario],
+    benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
+    schedule: &[[Side; 2]],
+) -> anyhow::Result<[Vec<CompileTimeBenchResult>; 2]> {
+    let started_at = SystemTime::now();
+    match options.perf_tool.get_bencher() {
+        Bencher::PerfStat | Bencher::Rusage | Bencher::PerfStatSelfProfile => (),
+        bencher => bail!("{:?} is not supported to compare toolchains.", bencher),
+    }
+
+    let benchmark_suit = BenchmarkSuit {
+        benchmarks: discover_benchmark_suit(&benchmark_dir, filter)?,
+    };
+    println!("{}", benchmark_suit.display_benchmarks());
+
+    // Every run is measured as the only iteration of a checkpoint of its own.
+    let checkpoint_dir = TempDir::new()?;
+    let mut results = [vec![], vec![]];
+    let mut failures = [vec![], vec![]];
+    for b in &benchmark_suit.benchmarks {
+        if b.config.disabled {
+            println!("Skipping {}: disabled", b.name);
+            continue;
+        }
+        let mut bench_results = [
+            CompileTimeBenchResult::new(b.name.clone(), schedule.len()),
+            CompileTimeBenchResult::new(b.name.clone(), schedule.len()),
+        ];
+        for (i, order) in schedule.iter().enumerate() {
+            for side in order {
+                let idx = *side as usize;
+                println!(
+                    "Compile-time benchmarking for '{}' with toolchain {:?}, iteration {}/{}",
+                    b.name,
+                    side,
+                    i + 1,
+                    schedule.len()
+                );
+                let mut checkpoint = Checkpoint::new(
+                    checkpoint_dir.path(),
+                    "compile_time_checkpoint.jsonl",
+                    false,
+                )?;
+                let mut run_failures = vec![];
+                let result = b.measure_compile_time(
+                    &RunOptions {
+                        iterations: Some(1),
+                        adaptive: None,
+                        flame_graph_path: checkpoint_dir.path(),
+                        flamegraph_flag: 0,
+                        out_dir: &options.out_dir.join(side.dir_name()),
+                        ..*options
+                    },
+                    profiles,
+                    scenarios,
+                    Compiler::from_toolchain(ltcs[idx]),
+                    &mut checkpoint,
+                    &mut run_failures,
+                );
+                match result {
+                    core::result::Result::Ok(r) => r.result_vec.into_iter().for_each(|mut r| {
+                        r.iteration = i + 1;
+                        bench_results[idx].add_result(r);
+                    }),
+                    Err(s) => {
+                        eprintln!("Fail to bench '{}': {:?}", b.name, s);
+                        run_failures.push(BenchmarkFailure::new(b.name.clone(), &s));
+                    }
+                }
+                failures[idx].extend(run_failures.into_iter().map(|mut f| {
+                    f.iteration = f.iteration.map(|_| i + 1);
+                    f
+                }));
+            }
+        }
+        let [a, b] = bench_results;
+        results[0].push(a);
+        results[1].push(b);
+    }
+
+    for side in [Side::A, Side::B] {
+        let ltc = ltcs[side as usize];
+        let metadata = RunMetadata::collect(&ltc.rustc, &ltc.cargo, &benchmark_dir, started_at);
+        write_failures(
+            &options.out_dir.join(side.dir_name()),
+            metadata,
+            &failures[side as usize],
+        )?;
+    }
+    Ok(results)
+}
+
+pub(crate) fn bench(
+    options: &RunOptions,
     profiles: &[Profile],
     scenarios: &[Scenario],
     compiler: Compiler<'_>,
     benchmarks: &[Benchamrk],
-    iterations: Option<usize>,
-    flame_graph_path: &Path,
-    flamegraph_flag: i32,
-    out_dir: &Path,
+    checkpoint: &mut Checkpoint<CompileTimeResult>,
+    failures: &mut Vec<BenchmarkFailure>,
 ) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
     let mut result_vec = vec![];
     let mut num_benchmark_left = benchmarks.len();
@@ -72,29 +183,41 @@ pub(crate) fn bench<'a>(
         num_benchmark_left -= 1;
 
         let result = b
-            .measure_compile_time(
-                perf_tool,
-                event_filter_file,
-                profiles,
-                scenarios,
-                compiler,
-                iterations,
-                flame_graph_path,
-                flamegraph_flag,
-                out_dir,
-            )
+            .measure_compile_time(options, profiles, scenarios, compiler, checkpoint, failures)
             .with_context(|| format!("Fail to bench '{}'!", b.name));
         match result {
             core::result::Result::Ok(r) => result_vec.push(r),
             Err(s) => {
                 eprintln!("{}", s);
                 eprintln!("Please check your perf tool, rust compiler or the benchmark program.\n");
+                if !b.config.disabled {
+                    failures.push(BenchmarkFailure::new(b.name.clone(), &s));
+                }
             }
         }
     });
     Ok(result_vec)
 }
 
+/// Write failed measurements to `failures.json` in `out_dir`.
+pub(crate) fn write_failures(
+    out_dir: &Path,
+    metadata: RunMetadata,
+    failures: &[BenchmarkFailure],
+) -> anyhow::Result<()> {
+    if !failures.is_empty() {
+        eprintln!(
+            "{} failures recorded in {:?}.",
+            failures.len(),
+            out_dir.join("failures.json")
+        );
+    }
+    ResultWriter::new(out_dir.to_path_buf(), PathBuf::from("failures.json"))?.write(
+        serde_json::to_string(&ResultFile::new(Some(metadata), failures))?,
+    )?;
+    Ok(())
+}
+
 pub(crate) trait CompileTimeProcessor {
     fn run_rustc(
         &mut self,
@@ -115,6 +238,13 @@ lazy_static::lazy_static! {
         fake_rustc
     };
 
+    static ref FAKE_RUSTDOC: PathBuf = {
+        let mut fake_rustdoc = std::env::current_exe().unwrap();
+        fake_rustdoc.pop();
+        fake_rustdoc.push("rustdoc-fake");
+        fake_rustdoc
+    };
+
     static ref FAKE_FLAMEGRAPH: PathBuf = {
         let mut fake_runtime = std::env::current_exe().unwrap();
         fake_runtime.pop();
@@ -123,8 +253,12 @@ lazy_static::lazy_static! {
     };
 }
 
-pub(crate) fn discover_benchmark_suit(dir: &Path) -> anyhow::Result<Vec<Benchamrk>> {
+pub(crate) fn discover_benchmark_suit(
+    dir: &Path,
+    filter: &BenchmarkFilter,
+) -> anyhow::Result<Vec<Benchamrk>> {
     let mut benchmarks = vec![];
+    let suite_profiles = read_suite_profiles(dir)?;
 
     for entry in read_dir(dir)
         .with_context(|| format!("failed to list benchmark dir '{}'", dir.display()))?
@@ -139,10 +273,40 @@ pub(crate) fn discover_benchmark_suit(dir: &Path) -> anyhow::Result<Vec<Benchamr
             println!("benchmark '{}' ignored", name);
             continue;
         }
-        benchmarks.push(Benchamrk::new(name, path)?);
+        if !filter.accepts_name(&name) {
+            println!("benchmark '{}' filtered out", name);
+            continue;
+        }
+        let mut benchmark = Benchamrk::new(name, path)?;
+        suite_profiles.iter().for_each(|(name, profile)| {
+            benchmark
+                .config
+                .profiles
+                .entry(name.clone())
+                .or_insert_with(|| profile.clone());
+        });
+        if !filter.accepts_tags(&benchmark.config.tags) {
+            println!("benchmark '{}' filtered out", benchmark.name);
+            continue;
+        }
+        benchmarks.push(benchmark);
     }
     if benchmarks.is_empty() {
         eprintln!("Error: no benchmark found in '{}'", dir.display());
     }
     Ok(benchmarks)
 }
+
+/// Read custom profiles shared by all benchmarks from `profiles.json` in the
+/// benchmark dir, if there is one.
+fn read_suite_profiles(dir: &Path) -> anyhow::Result<HashMap<String, CustomProfile>> {
+    let path = dir.join("profiles.json");
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let profiles = serde_json::from_reader(
+        File::open(&path).with_context(|| format!("failed to open {:?}", path))?,
+    )
+    .with_context(|| format!("failed to parse {:?}", path))?;
+    Ok(profiles)
+}
@@ -1,17 +1,14 @@
 use std::{
     fs::{create_dir_all, File},
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    io::BufWriter,
+    path::{Path, PathBuf},
 };
 
-use crate::benchmark::profile::Profiles;
-use crate::statistics::compile_time_stat::CompileTimeResultSet;
+use crate::benchmark::{feature_set::labeled_benchmark_name, profile::Profiles};
+use crate::statistics::{compile_time_stat::CompileTimeResultSet, metadata::read_result_file};
 
-pub fn read_compile_time_json(path: &PathBuf) -> anyhow::Result<CompileTimeResultSet> {
-    let file = File::open(path)?;
-    let file = BufReader::new(file);
-    let res = serde_json::from_reader(file)?;
-    Ok(res)
+pub fn read_compile_time_json(path: &Path) -> anyhow::Result<CompileTimeResultSet> {
+    Ok(read_result_file(path)?.data)
 }
 
 pub fn create_compile_time_csv(
@@ -28,12 +25,24 @@ pub fn create_compile_time_csv(
 
     let results = data.get_ref_results();
 
-    let header: Vec<String> = results.iter().map(|r| r.get_benchmark()).collect();
+    // One column per benchmark and feature set.
+    let columns: Vec<_> = results
+        .iter()
+        .flat_map(|r| r.get_feature_sets().into_iter().map(move |f| (r, f)))
+        .collect();
+    if columns.is_empty() {
+        eprintln!("{} has empty data set, will be skipped.", out.display());
+        return Ok(());
+    }
 
-    let metrics: Vec<&String> = results
-        .first()
-        .unwrap()
-        .get_stats_ref_by_profile(profiles.profiles.first().unwrap())
+    let header: Vec<String> = columns
+        .iter()
+        .map(|(r, f)| labeled_benchmark_name(&r.get_benchmark(), f))
+        .collect();
+
+    let (first, first_feature_set) = columns.first().unwrap();
+    let metrics: Vec<&String> = first
+        .get_stats_ref_by_profile_feature_set(profiles.profiles.first().unwrap(), first_feature_set)
         .first()
         .unwrap()
         .stats
@@ -51,14 +60,15 @@ pub fn create_compile_time_csv(
             csv_writer.write_record(&header)?;
 
             for i in 0..results.first().unwrap().get_iterations() {
-                let datas: Vec<String> = results
+                let datas: Vec<String> = columns
                     .iter()
-                    .map(|r| {
+                    .map(|(r, f)| {
                         // eprintln!("{}[{}] {} {}", r.get_benchmark(), i, rustc_id, metric);
-                        if i >= r.get_stats_ref_by_profile(profile).len() {
+                        let stats = r.get_stats_ref_by_profile_feature_set(profile, f);
+                        if i >= stats.len() {
                             return String::from("0");
                         }
-                        match r.get_stats_ref_by_profile(profile)[i].stats.get(metric) {
+                        match stats[i].stats.get(metric) {
                             Some(f) => f.to_string(),
                             None => {
                                 eprintln!("Corrupted output data to write {}!", out.display());
@@ -1,16 +1,16 @@
 use std::{
     fs::{create_dir_all, File},
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    io::BufWriter,
+    path::{Path, PathBuf},
 };
 
-use crate::statistics::runtime_stat::RuntimeResultVec;
+use crate::{
+    benchmark::feature_set::labeled_benchmark_name,
+    statistics::{metadata::read_result_file, runtime_stat::RuntimeResultVec},
+};
 
-pub fn read_runtime_json(path: &PathBuf) -> anyhow::Result<RuntimeResultVec> {
-    let file = File::open(path)?;
-    let file = BufReader::new(file);
-    let res = serde_json::from_reader(file)?;
-    Ok(res)
+pub fn read_runtime_json(path: &Path) -> anyhow::Result<RuntimeResultVec> {
+    Ok(read_result_file(path)?.data)
 }
 
 pub fn create_runtime_csv(
@@ -26,7 +26,10 @@ pub fn create_runtime_csv(
 
     let results = &data.0;
 
-    let header: Vec<String> = results.iter().map(|r| r.name.clone()).collect();
+    let header: Vec<String> = results
+        .iter()
+        .map(|r| labeled_benchmark_name(&r.name, &r.feature_set))
+        .collect();
 
     let metrics: Vec<String> = results
         .first()
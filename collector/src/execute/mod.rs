@@ -1,3 +1,6 @@
+pub mod rusage;
+pub mod valgrind;
+
 use std::{
     collections::HashMap,
     ops::{Add, AddAssign},
@@ -6,20 +9,30 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::statistics::self_profile::SelfProfile;
+
+/// Environment variable passing the perf events to count to `rustc-fake` and
+/// `runtime-fake`.
+pub const PERF_EVENTS_ENV: &str = "PERF_EVENTS";
+
+/// Events counted with `perf stat` if the collector chose none, the same as
+/// `--event-profile basic`.
+pub const DEFAULT_PERF_EVENTS: &str = "instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses";
+
+/// Events to count with `perf stat`, chosen by the collector.
+pub fn perf_events() -> String {
+    std::env::var(PERF_EVENTS_ENV).unwrap_or_else(|_| String::from(DEFAULT_PERF_EVENTS))
+}
+
 pub fn process_benchmark_output(output: Output) -> Result<Stats, DeserializeStatError> {
     let stdout = String::from_utf8(output.stdout.clone()).expect("utf8 output");
     let mut stats = Stats::new();
-    let mut perf_output = false;
     for line in stdout.lines() {
-        // filter out cargo test output msg.
-        if perf_output == false {
-            if line.contains("instructions") {
-                perf_output = true;
-            } else {
-                continue;
+        if let Some(json) = line.strip_prefix("!self-profile-output:") {
+            match SelfProfile::from_summarize_json(json) {
+                Ok(profile) => stats.self_profile = Some(profile),
+                Err(e) => log::warn!("fail to parse self profile output: {:?}", e),
             }
-        }
-        if line.contains("ignored") {
             continue;
         }
         // github.com/torvalds/linux/blob/bc78d646e708/tools/perf/Documentation/perf-stat.txt#L281
@@ -40,20 +53,37 @@ pub fn process_benchmark_output(output: Output) -> Result<Stats, DeserializeStat
         let name = get!(parts.next());
         let _time = get!(parts.next());
         let pct = get!(parts.next());
-        if cnt == "<not supported>" || cnt.len() == 0 {
+        if cnt == "<not supported>" || cnt == "<not counted>" || cnt.len() == 0 {
+            continue;
+        }
+        // Lines of perf stat always start with a number, so this filters out
+        // other output, e.g. of cargo test, whatever events are counted.
+        if !cnt.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+            continue;
+        }
+        let cnt: f64 = cnt
+            .parse()
+            .map_err(|e| DeserializeStatError::ParseError(cnt.to_string(), e))?;
+        let pct: f64 = pct
+            .parse()
+            .map_err(|e| DeserializeStatError::ParseError(pct.to_string(), e))?;
+        if pct <= 0. {
+            log::warn!("measurement of `{}` was never active", name);
             continue;
         }
-        if !pct.starts_with("100.") {
-            panic!(
+        if pct < 100. {
+            // The counter was multiplexed. perf stat runs with `--no-scale`,
+            // so extrapolate the raw count to the whole run the same way
+            // perf stat would, and keep how long it was active.
+            log::warn!(
                 "measurement of `{}` only active for {}% of the time",
-                name, pct
+                name,
+                pct
             );
+            stats.insert_with_coverage(name.to_owned(), cnt * 100. / pct, pct);
+        } else {
+            stats.insert(name.to_owned(), cnt);
         }
-        stats.insert(
-            name.to_owned(),
-            cnt.parse()
-                .map_err(|e| DeserializeStatError::ParseError(cnt.to_string(), e))?,
-        );
     }
 
     log::info!("{:?}", stdout.lines());
@@ -67,6 +97,14 @@ pub fn process_benchmark_output(output: Output) -> Result<Stats, DeserializeStat
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Stats {
     pub stats: HashMap<String, f64>,
+    /// Percentage of the time multiplexed counters were active. Metrics
+    /// measured all the time are not recorded here.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub coverage: HashMap<String, f64>,
+    /// Time rustc spent in each query, only measured with
+    /// `PerfStatSelfProfile`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_profile: Option<SelfProfile>,
 }
 
 impl Default for Stats {
@@ -79,6 +117,8 @@ impl Stats {
     pub fn new() -> Stats {
         Stats {
             stats: HashMap::new(),
+            coverage: HashMap::new(),
+            self_profile: None,
         }
     }
 
@@ -94,6 +134,32 @@ impl Stats {
         self.stats.insert(stat, value);
     }
 
+    pub fn insert_with_coverage(&mut self, stat: String, value: f64, coverage: f64) {
+        self.merge_coverage(&stat, coverage);
+        self.insert(stat, value);
+    }
+
+    /// Percentage of the time `stat` was measured, `100.` if it was not
+    /// multiplexed.
+    pub fn coverage(&self, stat: &str) -> f64 {
+        self.coverage.get(stat).copied().unwrap_or(100.)
+    }
+
+    /// A sum of stats is only as good as its least covered part.
+    fn merge_coverage(&mut self, stat: &str, coverage: f64) {
+        if coverage < self.coverage(stat) {
+            self.coverage.insert(stat.to_string(), coverage);
+        }
+    }
+
+    fn merge_self_profile(&mut self, profile: Option<SelfProfile>) {
+        match (&mut self.self_profile, profile) {
+            (Some(p), Some(profile)) => *p += profile,
+            (None, profile) => self.self_profile = profile,
+            (Some(_), None) => (),
+        }
+    }
+
     pub fn add_or_insert(&mut self, stat: String, value: f64) {
         match self.stats.get_mut(&stat) {
             Some(e) => *e += value,
@@ -113,6 +179,13 @@ impl Add for Stats {
                 None => res.insert(label.clone(), left_val.clone()),
             }
         }
+        for (label, coverage) in self.coverage.iter().chain(rhs.coverage.iter()) {
+            if res.stats.contains_key(label) {
+                res.merge_coverage(label, *coverage);
+            }
+        }
+        res.self_profile = self.self_profile;
+        res.merge_self_profile(rhs.self_profile);
         res
     }
 }
@@ -126,6 +199,10 @@ impl AddAssign for Stats {
                 None => self.insert(label.clone(), right_val.clone()),
             }
         }
+        for (label, coverage) in rhs.coverage.iter() {
+            self.merge_coverage(label, *coverage);
+        }
+        self.merge_self_profile(rhs.self_profile);
     }
 }
 
@@ -138,3 +215,50 @@ pub enum DeserializeStatError {
     #[error("could not process xperf data")]
     XperfError(#[from] anyhow::Error),
 }
+
+#[cfg(test)]
+mod test_execute {
+    use std::{
+        os::unix::process::ExitStatusExt,
+        process::{ExitStatus, Output},
+    };
+
+    use super::process_benchmark_output;
+
+    /// Test for process_benchmark_output
+    ///
+    /// Step1: Process perf stat output of arbitrary events mixed with cargo test output.
+    ///
+    /// Step2: Verify every counted event is recorded and other lines are skipped.
+    ///
+    /// Step3: Verify multiplexed events are scaled and their coverage is kept.
+    #[test]
+    fn test_process_benchmark_output() {
+        let stdout = "running 1 test
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+120043;;L1-dcache-load-misses;1000;100.00
+<not supported>;;stalled-cycles-frontend;0;100.00
+<not counted>;;LLC-loads;0;0.00
+5.25;msec;task-clock;5250000;100.00
+2048;;max-rss;3;100.00
+0.005300000;;wall-time;4;100.00
+1000;;cache-misses;250000;25.00
+";
+        let output = Output {
+            status: ExitStatus::from_raw(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        };
+
+        let stats = process_benchmark_output(output).unwrap();
+        assert_eq!(5, stats.stats.len());
+        assert_eq!(Some(&120043.), stats.stats.get("L1-dcache-load-misses"));
+        assert_eq!(Some(&5.25), stats.stats.get("task-clock"));
+        assert!(!stats.stats.contains_key("stalled-cycles-frontend"));
+        assert!(!stats.stats.contains_key("LLC-loads"));
+
+        assert_eq!(Some(&4000.), stats.stats.get("cache-misses"));
+        assert_eq!(25., stats.coverage("cache-misses"));
+        assert_eq!(100., stats.coverage("task-clock"));
+    }
+}
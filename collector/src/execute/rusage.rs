@@ -0,0 +1,40 @@
+//! Resource usage of a benchmarked process, shared by `rustc-fake` and
+//! `runtime-fake`.
+
+use std::process::{Child, ExitStatus};
+
+/// Wait for `child` and return its exit status and resource usage. Unlike
+/// `getrusage(RUSAGE_CHILDREN)`, the usage is reaped with `wait4`, so it only
+/// covers `child` and its descendants.
+#[cfg(unix)]
+pub fn wait_with_rusage(child: &Child) -> (ExitStatus, libc::rusage) {
+    use std::os::unix::process::ExitStatusExt;
+
+    let (status, usage) = unsafe {
+        let mut status = 0;
+        let mut usage: libc::rusage = std::mem::zeroed();
+        let r = libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage);
+        assert!(r >= 0, "wait4 failed: {}", std::io::Error::last_os_error());
+        (status, usage)
+    };
+    (ExitStatus::from_raw(status), usage)
+}
+
+/// Print `usage` in the csv format of `perf stat`, like the wall time
+/// printed by the fakes.
+#[cfg(unix)]
+pub fn print_rusage(usage: &libc::rusage) {
+    let secs = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1e6;
+    println!("{:.6};;user-time;0;100.00", secs(usage.ru_utime));
+    println!("{:.6};;sys-time;0;100.00", secs(usage.ru_stime));
+    println!("{};;max-rss;0;100.00", usage.ru_maxrss);
+    println!("{};;minor-faults;0;100.00", usage.ru_minflt);
+    println!("{};;major-faults;0;100.00", usage.ru_majflt);
+    println!("{};;faults;0;100.00", usage.ru_minflt + usage.ru_majflt);
+    println!("{};;voluntary-context-switches;0;100.00", usage.ru_nvcsw);
+    println!("{};;involuntary-context-switches;0;100.00", usage.ru_nivcsw);
+    println!(
+        "{};;context-switches;0;100.00",
+        usage.ru_nvcsw + usage.ru_nivcsw
+    );
+}
@@ -0,0 +1,166 @@
+use std::{fs::read_to_string, path::Path};
+
+use anyhow::{bail, Context};
+
+use crate::toolchain::ValgrindTool;
+
+use super::Stats;
+
+/// Parse the output file of a valgrind tool into stats.
+///
+/// * Cachegrind and Callgrind: the total of every event, e.g. `Ir` and
+///   `D1mr`, plus `D1-misses` and `LL-misses` if caches were simulated.
+/// * DHAT: `total-bytes` and `total-blocks` allocated, and `peak-heap` and
+///   `peak-blocks` at the global heap peak.
+/// * Massif: `peak-heap` over all snapshots.
+pub fn process_valgrind_output(tool: ValgrindTool, path: &Path) -> anyhow::Result<Stats> {
+    let content = read_to_string(path)
+        .with_context(|| format!("failed to read {:?} output {:?}", tool, path))?;
+    match tool {
+        ValgrindTool::Cachegrind | ValgrindTool::Callgrind => parse_grind_output(&content),
+        ValgrindTool::Dhat => parse_dhat_output(&content),
+        ValgrindTool::Massif => parse_massif_output(&content),
+    }
+    .with_context(|| format!("failed to parse {:?} output {:?}", tool, path))
+}
+
+fn parse_grind_output(content: &str) -> anyhow::Result<Stats> {
+    let field = |key: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .map(|v| v.split_whitespace().collect::<Vec<_>>())
+    };
+
+    let events = match field("events:") {
+        Some(events) => events,
+        None => bail!("no `events:` line"),
+    };
+    // Callgrind may only write `totals:` at the end.
+    let totals = match field("summary:").or_else(|| field("totals:")) {
+        Some(totals) => totals,
+        None => bail!("no `summary:` line"),
+    };
+
+    let mut stats = Stats::new();
+    for (event, total) in events.iter().zip(totals) {
+        stats.insert(event.to_string(), total.parse()?);
+    }
+
+    let sum = |events: &[&str]| -> Option<f64> { events.iter().map(|e| stats.stats.get(*e)).sum() };
+    let (d1, ll) = (sum(&["D1mr", "D1mw"]), sum(&["ILmr", "DLmr", "DLmw"]));
+    if let Some(d1) = d1 {
+        stats.insert(String::from("D1-misses"), d1);
+    }
+    if let Some(ll) = ll {
+        stats.insert(String::from("LL-misses"), ll);
+    }
+    Ok(stats)
+}
+
+fn parse_dhat_output(content: &str) -> anyhow::Result<Stats> {
+    let dhat: serde_json::Value = serde_json::from_str(content)?;
+    let pps = match dhat["pps"].as_array() {
+        Some(pps) => pps,
+        None => bail!("no program points"),
+    };
+    let total = |key: &str| pps.iter().filter_map(|pp| pp[key].as_f64()).sum::<f64>();
+
+    let mut stats = Stats::new();
+    stats.insert(String::from("total-bytes"), total("tb"));
+    stats.insert(String::from("total-blocks"), total("tbk"));
+    stats.insert(String::from("peak-heap"), total("gb"));
+    stats.insert(String::from("peak-blocks"), total("gbk"));
+    Ok(stats)
+}
+
+fn parse_massif_output(content: &str) -> anyhow::Result<Stats> {
+    let mut peak = None;
+    let mut heap = 0.;
+    for line in content.lines() {
+        if let Some(v) = line.strip_prefix("mem_heap_B=") {
+            heap = v.trim().parse()?;
+        } else if let Some(v) = line.strip_prefix("mem_heap_extra_B=") {
+            let total = heap + v.trim().parse::<f64>()?;
+            peak = Some(peak.map_or(total, |p: f64| p.max(total)));
+        }
+    }
+
+    let mut stats = Stats::new();
+    match peak {
+        Some(peak) => stats.insert(String::from("peak-heap"), peak),
+        None => bail!("no snapshots"),
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test_valgrind {
+    use super::{parse_dhat_output, parse_grind_output, parse_massif_output};
+
+    /// Test for parsing valgrind outputs
+    ///
+    /// Step1: Parse Cachegrind, Callgrind, DHAT and Massif outputs.
+    ///
+    /// Step2: Verify the totals and peaks.
+    #[test]
+    fn test_process_valgrind_output() {
+        let cachegrind = parse_grind_output(
+            "desc: I1 cache: 32768 B, 64 B, 8-way associative
+cmd: rustc --crate-name foo
+events: Ir I1mr ILmr Dr D1mr DLmr Dw D1mw DLmw
+fl=foo.rs
+fn=main
+1 10 1 1 4 1 0 2 1 1
+summary: 1000 10 5 400 40 4 200 20 2
+",
+        )
+        .unwrap();
+        assert_eq!(Some(&1000.), cachegrind.stats.get("Ir"));
+        assert_eq!(Some(&60.), cachegrind.stats.get("D1-misses"));
+        assert_eq!(Some(&11.), cachegrind.stats.get("LL-misses"));
+
+        let callgrind = parse_grind_output("events: Ir\nfn=main\n0 12\ntotals: 12\n").unwrap();
+        assert_eq!(Some(&12.), callgrind.stats.get("Ir"));
+        assert!(!callgrind.stats.contains_key("D1-misses"));
+
+        let dhat = parse_dhat_output(
+            r#"{"dhatFileVersion": 2, "mode": "rust", "pps": [
+                {"tb": 100, "tbk": 2, "mb": 64, "mbk": 1, "gb": 64, "gbk": 1},
+                {"tb": 30, "tbk": 3, "mb": 20, "mbk": 2, "gb": 10, "gbk": 1}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(&130.), dhat.stats.get("total-bytes"));
+        assert_eq!(Some(&5.), dhat.stats.get("total-blocks"));
+        assert_eq!(Some(&74.), dhat.stats.get("peak-heap"));
+
+        let massif = parse_massif_output(
+            "snapshot=0
+#-----------
+time=0
+mem_heap_B=0
+mem_heap_extra_B=0
+mem_stacks_B=0
+heap_tree=empty
+snapshot=1
+#-----------
+time=100
+mem_heap_B=4096
+mem_heap_extra_B=0
+mem_stacks_B=0
+heap_tree=peak
+snapshot=2
+#-----------
+time=200
+mem_heap_B=1024
+mem_heap_extra_B=0
+mem_stacks_B=0
+heap_tree=empty
+",
+        )
+        .unwrap();
+        assert_eq!(Some(&4096.), massif.stats.get("peak-heap"));
+        assert!(parse_massif_output("").is_err());
+    }
+}
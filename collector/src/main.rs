@@ -1,15 +1,16 @@
 use std::{
-    env::current_dir,
-    fs::create_dir_all,
+    env::{self, current_dir},
+    fs::{create_dir_all, File},
     path::PathBuf,
     process::{self, Command},
+    time::SystemTime,
 };
 
 use anyhow::{bail, Context, Ok};
-use benchmark::scenario::Scenario;
+use benchmark::validate::validate_suite;
 use clap::Parser;
 use compile_time::{
-    bench_compile_time,
+    bench_compile_time, bench_compile_time_interleaved,
     binary_size::{
         compare::compare_binary_size,
         plotter::{plot, plot_compare},
@@ -17,14 +18,29 @@ use compile_time::{
     discover_benchmark_suit,
 };
 use mir_analyze::mir_generate::generate_mir;
-use runtime::bench_runtime;
+use runtime::{bench_runtime, bench_runtime_interleaved};
 use src_code_analyze::entry::src_code_analyze;
-use toolchain::{Cli, Commands, ResultWriter};
+use sweep::{bench_sweep, SweepOptions};
+use toolchain::{BenchKind, Cli, Commands, ResultWriter};
 
 use crate::{
-    benchmark::benchmark::BenchmarkSuit, compile_time::binary_size::bench_binary_size,
-    csv_transfer::sheduler, morpheme_miner::run_miners, perf_analyze::perf_analyzer,
-    statistics::compile_time_stat::CompileTimeResultSet, toolchain::get_local_toolchain,
+    benchmark::{
+        benchmark::BenchmarkSuit,
+        interleave::{seed_from_time, Side},
+        run_options::RunOptions,
+        sweep::SweepMatrix,
+    },
+    compile_time::binary_size::bench_binary_size,
+    csv_transfer::sheduler,
+    execute::PERF_EVENTS_ENV,
+    morpheme_miner::run_miners,
+    perf_analyze::perf_analyzer,
+    statistics::{
+        compile_time_stat::CompileTimeResultSet,
+        metadata::{ResultFile, RunMetadata},
+        paired::{compare_compile_time, compare_runtime, PairedComparisonFile},
+    },
+    toolchain::get_local_toolchain,
 };
 
 mod benchmark;
@@ -38,6 +54,7 @@ mod perf_analyze;
 mod runtime;
 mod src_code_analyze;
 mod statistics;
+mod sweep;
 mod toolchain;
 mod utils;
 
@@ -61,22 +78,46 @@ fn main_result() -> anyhow::Result<i32> {
         Commands::BenchLocal {
             local,
             iterations,
+            adaptive,
             perf_tool,
+            events,
             event_filter_file,
             bench_dir,
             profiles,
+            scenarios,
             out_dir,
             flamegraph,
+            resume,
         } => {
-            perf_check();
+            let started_at = SystemTime::now();
+            let flamegraph = if perf_tool.needs_perf() {
+                perf_check();
+                flamegraph
+            } else {
+                // Flamegraphs are recorded with perf as well.
+                if flamegraph > 0 {
+                    eprintln!(
+                        "Warning: flamegraphs are recorded with perf, which `--perf-tool {}` does not use. No flamegraph is drawn.",
+                        perf_tool.name()
+                    );
+                }
+                0
+            };
             if flamegraph > 0 {
                 flamegraph_check();
             }
+            if let toolchain::Bencher::PerfStatSelfProfile = perf_tool.get_bencher() {
+                summarize_check()?;
+            }
+            // Inherited by rustc-fake and runtime-fake through cargo.
+            env::set_var(PERF_EVENTS_ENV, events.perf_events());
 
             println!("profiles: {:?}", profiles.profiles);
-            let default_scenarios = vec![Scenario::Full];
+            println!("scenarios: {:?}", scenarios.scenarios);
             let toolch = get_local_toolchain(
+                &profiles.profiles,
                 &local.rustc,
+                local.rustdoc.as_deref(),
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
@@ -115,25 +156,42 @@ fn main_result() -> anyhow::Result<i32> {
 
             let results = bench_compile_time(
                 &toolch,
-                &perf_tool,
-                &event_filter_file,
+                &RunOptions {
+                    perf_tool: &perf_tool,
+                    event_filter_file: &event_filter_file,
+                    iterations: Some(iterations),
+                    adaptive: adaptive.adaptive_iterations().as_ref(),
+                    flame_graph_path: &flame_graph_path,
+                    flamegraph_flag: flamegraph,
+                    out_dir: &out_dir,
+                },
                 &profiles.profiles,
-                &default_scenarios,
-                bench_dir,
-                iterations,
-                flame_graph_path.as_path().as_ref(),
-                flamegraph,
-                out_dir.as_path(),
+                &scenarios.scenarios,
+                bench_dir.clone(),
+                &local.benchmark_filter(),
+                resume,
             )?;
 
             match perf_tool.get_bencher() {
-                toolchain::Bencher::PerfStat => {
+                toolchain::Bencher::PerfStat
+                | toolchain::Bencher::Rusage
+                | toolchain::Bencher::PerfStatSelfProfile
+                | toolchain::Bencher::Valgrind(_) => {
+                    let metadata =
+                        RunMetadata::collect(&toolch.rustc, &toolch.cargo, &bench_dir, started_at);
+
                     let result_set =
                         CompileTimeResultSet::new(local.id.unwrap_or_default(), results);
-                    result_writer.write(serde_json::to_string(&result_set)?)?;
+                    result_writer.write(serde_json::to_string(&ResultFile::new(
+                        Some(metadata.clone()),
+                        &result_set,
+                    ))?)?;
 
                     let result_statistics = result_set.calculate_statistics();
-                    statistics_writer.write(serde_json::to_string(&result_statistics)?)?;
+                    statistics_writer.write(serde_json::to_string(&ResultFile::new(
+                        Some(metadata),
+                        &result_statistics,
+                    ))?)?;
                 }
                 toolchain::Bencher::PerfRecord => (),
             }
@@ -143,19 +201,40 @@ fn main_result() -> anyhow::Result<i32> {
         Commands::BenchRuntimeLocal {
             local,
             iterations,
+            adaptive,
+            warmup_runs,
             perf_tool,
+            events,
             event_filter_file,
             bench_dir,
             out_dir,
             flamegraph,
+            resume,
         } => {
-            perf_check();
+            let started_at = SystemTime::now();
+            let flamegraph = if perf_tool.needs_perf() {
+                perf_check();
+                flamegraph
+            } else {
+                // Flamegraphs are recorded with perf as well.
+                if flamegraph > 0 {
+                    eprintln!(
+                        "Warning: flamegraphs are recorded with perf, which `--perf-tool {}` does not use. No flamegraph is drawn.",
+                        perf_tool.name()
+                    );
+                }
+                0
+            };
             if flamegraph > 0 {
                 flamegraph_check();
             }
+            // Inherited by rustc-fake and runtime-fake through cargo.
+            env::set_var(PERF_EVENTS_ENV, events.perf_events());
 
             let ltc = get_local_toolchain(
+                &[],
                 &local.rustc,
+                local.rustdoc.as_deref(),
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
@@ -190,19 +269,259 @@ fn main_result() -> anyhow::Result<i32> {
 
             let results = bench_runtime(
                 &ltc,
-                bench_dir,
-                iterations,
-                &perf_tool,
-                &event_filter_file,
-                &flame_graph_path,
-                flamegraph,
-                &out_dir,
+                bench_dir.clone(),
+                &local.benchmark_filter(),
+                &RunOptions {
+                    perf_tool: &perf_tool,
+                    event_filter_file: &event_filter_file,
+                    iterations: Some(iterations as usize),
+                    adaptive: adaptive.adaptive_iterations().as_ref(),
+                    flame_graph_path: &flame_graph_path,
+                    flamegraph_flag: flamegraph,
+                    out_dir: &out_dir,
+                },
+                warmup_runs,
+                resume,
             )?;
 
-            result_writer.write(serde_json::to_string(&results)?)?;
+            let metadata = RunMetadata::collect(&ltc.rustc, &ltc.cargo, &bench_dir, started_at);
+
+            result_writer.write(serde_json::to_string(&ResultFile::new(
+                Some(metadata.clone()),
+                &results,
+            ))?)?;
 
             let statistics = results.calculate_statistics();
-            statistics_writer.write(serde_json::to_string(&statistics)?)?;
+            statistics_writer.write(serde_json::to_string(&ResultFile::new(
+                Some(metadata),
+                &statistics,
+            ))?)?;
+            Ok(0)
+        }
+        Commands::BenchCompare {
+            rustc_a,
+            rustc_b,
+            cargo_a,
+            cargo_b,
+            rustdoc_a,
+            rustdoc_b,
+            kind,
+            iterations,
+            order,
+            seed,
+            warmup_runs,
+            perf_tool,
+            events,
+            event_filter_file,
+            profiles,
+            scenarios,
+            bench_dir,
+            out_dir,
+            filter,
+        } => {
+            let started_at = SystemTime::now();
+            if perf_tool.needs_perf() {
+                perf_check();
+            }
+            if let toolchain::Bencher::PerfStatSelfProfile = perf_tool.get_bencher() {
+                summarize_check()?;
+            }
+            // Inherited by rustc-fake and runtime-fake through cargo.
+            env::set_var(PERF_EVENTS_ENV, events.perf_events());
+
+            let profiles = match kind {
+                BenchKind::CompileTime => profiles.profiles,
+                BenchKind::Runtime => vec![],
+            };
+            let ltc_a = get_local_toolchain(
+                &profiles,
+                &rustc_a,
+                rustdoc_a.as_deref(),
+                cargo_a.as_deref(),
+                Some("a"),
+                "",
+            )?;
+            let ltc_b = get_local_toolchain(
+                &profiles,
+                &rustc_b,
+                rustdoc_b.as_deref(),
+                cargo_b.as_deref(),
+                Some("b"),
+                "",
+            )?;
+            println!("a:\n{}\nb:\n{}", ltc_a, ltc_b);
+
+            let bench_dir = bench_dir.unwrap_or_else(|| kind.default_bench_dir());
+            let filter = filter.benchmark_filter();
+            let seed = seed.unwrap_or_else(seed_from_time);
+            let schedule = order.schedule(iterations, seed);
+            println!("order: {}, seed: {}", order, seed);
+
+            create_output_dir(&out_dir)?;
+            let ltcs = [&ltc_a, &ltc_b];
+            let options = RunOptions {
+                perf_tool: &perf_tool,
+                event_filter_file: &event_filter_file,
+                iterations: Some(iterations),
+                adaptive: None,
+                flame_graph_path: &out_dir,
+                flamegraph_flag: 0,
+                out_dir: &out_dir,
+            };
+            let comparisons = match kind {
+                BenchKind::CompileTime => {
+                    let results = bench_compile_time_interleaved(
+                        ltcs,
+                        &options,
+                        &profiles,
+                        &scenarios.scenarios,
+                        bench_dir.clone(),
+                        &filter,
+                        &schedule,
+                    )?;
+                    let comparisons = compare_compile_time(&results[0], &results[1]);
+                    for (side, results) in [Side::A, Side::B].into_iter().zip(results) {
+                        let ltc = ltcs[side as usize];
+                        let metadata =
+                            RunMetadata::collect(&ltc.rustc, &ltc.cargo, &bench_dir, started_at);
+                        let side_dir = out_dir.join(side.dir_name());
+                        let result_set =
+                            CompileTimeResultSet::new(side.dir_name().to_string(), results);
+                        ResultWriter::new(
+                            side_dir.clone(),
+                            PathBuf::from("compile_time_results.json"),
+                        )?
+                        .write(serde_json::to_string(
+                            &ResultFile::new(Some(metadata.clone()), &result_set),
+                        )?)?;
+                        ResultWriter::new(side_dir, PathBuf::from("compile_time_statistics.json"))?
+                            .write(serde_json::to_string(&ResultFile::new(
+                                Some(metadata),
+                                &result_set.calculate_statistics(),
+                            ))?)?;
+                    }
+                    comparisons
+                }
+                BenchKind::Runtime => {
+                    let results = bench_runtime_interleaved(
+                        ltcs,
+                        bench_dir.clone(),
+                        &filter,
+                        &options,
+                        warmup_runs,
+                        &schedule,
+                    )?;
+                    let comparisons = compare_runtime(&results[0], &results[1]);
+                    for (side, results) in [Side::A, Side::B].into_iter().zip(results) {
+                        let ltc = ltcs[side as usize];
+                        let metadata =
+                            RunMetadata::collect(&ltc.rustc, &ltc.cargo, &bench_dir, started_at);
+                        let side_dir = out_dir.join(side.dir_name());
+                        ResultWriter::new(side_dir.clone(), PathBuf::from("runtime_results.json"))?
+                            .write(serde_json::to_string(&ResultFile::new(
+                                Some(metadata.clone()),
+                                &results,
+                            ))?)?;
+                        ResultWriter::new(side_dir, PathBuf::from("runtime_statistics.json"))?
+                            .write(serde_json::to_string(&ResultFile::new(
+                                Some(metadata),
+                                &results.calculate_statistics(),
+                            ))?)?;
+                    }
+                    comparisons
+                }
+            };
+
+            comparisons.iter().for_each(|c| {
+                c.diffs.iter().filter(|d| d.significant).for_each(|d| {
+                    println!(
+                        "{} [{}] {}: {:+.2}%",
+                        c.name,
+                        c.feature_set,
+                        d.metric,
+                        d.relative_change * 100.
+                    )
+                })
+            });
+            let metadata_a =
+                RunMetadata::collect(&ltc_a.rustc, &ltc_a.cargo, &bench_dir, started_at);
+            let metadata_b =
+                RunMetadata::collect(&ltc_b.rustc, &ltc_b.cargo, &bench_dir, started_at);
+            ResultWriter::new(out_dir.clone(), PathBuf::from("paired_comparison.json"))?.write(
+                serde_json::to_string(&ResultFile::new(
+                    Some(metadata_a),
+                    &PairedComparisonFile {
+                        rustc_a,
+                        rustc_b,
+                        rustc_b_version: metadata_b.rustc_version,
+                        cargo_b_version: metadata_b.cargo_version,
+                        order,
+                        seed,
+                        schedule,
+                        comparisons,
+                    },
+                ))?,
+            )?;
+            Ok(0)
+        }
+        Commands::Sweep {
+            local,
+            sweep_config,
+            iterations,
+            perf_tool,
+            events,
+            event_filter_file,
+            profile,
+            compile_time_metric,
+            runtime_metric,
+            compile_time_bench_dir,
+            runtime_bench_dir,
+            out_dir,
+        } => {
+            if perf_tool.needs_perf() {
+                perf_check();
+            }
+            // Inherited by rustc-fake and runtime-fake through cargo.
+            env::set_var(PERF_EVENTS_ENV, events.perf_events());
+
+            let matrix: SweepMatrix = serde_json::from_reader(
+                File::open(&sweep_config)
+                    .with_context(|| format!("failed to open {:?}", sweep_config))?,
+            )
+            .with_context(|| format!("failed to parse {:?}", sweep_config))?;
+
+            let ltc = get_local_toolchain(
+                &[profile.clone()],
+                &local.rustc,
+                local.rustdoc.as_deref(),
+                local.cargo.as_deref(),
+                local.id.as_deref(),
+                "",
+            )?;
+            println!("{}", ltc);
+
+            create_output_dir(&out_dir)?;
+            let result = bench_sweep(
+                &ltc,
+                &matrix,
+                &SweepOptions {
+                    perf_tool: &perf_tool,
+                    event_filter_file: &event_filter_file,
+                    profile: &profile,
+                    compile_time_dir: &compile_time_bench_dir,
+                    runtime_dir: &runtime_bench_dir,
+                    filter: &local.benchmark_filter(),
+                    iterations,
+                    compile_time_metric: &compile_time_metric,
+                    runtime_metric: &runtime_metric,
+                },
+                &out_dir,
+            )?;
+
+            result.pareto_fronts.iter().for_each(|front| {
+                println!("Pareto front of {}:", front.benchmark);
+                front.points.iter().for_each(|p| println!("    {}", p));
+            });
             Ok(0)
         }
         Commands::TransferCsvOutput {
@@ -245,8 +564,11 @@ fn main_result() -> anyhow::Result<i32> {
             bench_dir,
             out_dir,
         } => {
+            let started_at = SystemTime::now();
             let toolch = get_local_toolchain(
+                &profiles.profiles,
                 &local.rustc,
+                local.rustdoc.as_deref(),
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
@@ -262,12 +584,82 @@ fn main_result() -> anyhow::Result<i32> {
                         )
                     })?;
 
-            let results = bench_binary_size(&toolch, &profiles.profiles, bench_dir)?;
+            let results = bench_binary_size(
+                &toolch,
+                &profiles.profiles,
+                bench_dir.clone(),
+                &local.benchmark_filter(),
+            )?;
 
-            result_writer.write(serde_json::to_string(&results)?)?;
+            let metadata =
+                RunMetadata::collect(&toolch.rustc, &toolch.cargo, &bench_dir, started_at);
+            result_writer.write(serde_json::to_string(&ResultFile::new(
+                Some(metadata),
+                &results,
+            ))?)?;
 
             Ok(0)
         }
+        Commands::ValidateSuite {
+            rustc,
+            rustdoc,
+            id,
+            cargo,
+            filter,
+            bench_dir,
+            out_dir,
+            no_build,
+        } => {
+            let started_at = SystemTime::now();
+            let ltc = match rustc {
+                Some(rustc) if !no_build => Some(get_local_toolchain(
+                    &[],
+                    &rustc,
+                    rustdoc.as_deref(),
+                    cargo.as_deref(),
+                    id.as_deref(),
+                    "",
+                )?),
+                // Required by clap unless `--no-build` is set.
+                _ => None,
+            };
+            create_output_dir(&out_dir)?;
+
+            let mut result_writer =
+                ResultWriter::new(out_dir.clone(), PathBuf::from("suite_health.json"))
+                    .with_context(|| {
+                        format!(
+                            "Fail to open {} to record results!",
+                            out_dir.to_str().unwrap_or("?")
+                        )
+                    })?;
+
+            let health = validate_suite(&bench_dir, &filter.benchmark_filter(), ltc.as_ref())?;
+            let metadata = match &ltc {
+                Some(ltc) => RunMetadata::collect(&ltc.rustc, &ltc.cargo, &bench_dir, started_at),
+                None => RunMetadata::collect_without_toolchain(&bench_dir, started_at),
+            };
+            result_writer.write(serde_json::to_string(&ResultFile::new(
+                Some(metadata),
+                &health,
+            ))?)?;
+
+            health
+                .benchmarks
+                .iter()
+                .filter(|b| !b.is_healthy())
+                .for_each(|b| {
+                    eprintln!("{}:", b.name);
+                    b.problems.iter().for_each(|p| eprintln!("    {}", p));
+                });
+
+            if health.healthy {
+                println!("All benchmarks in {:?} are healthy.", bench_dir);
+                Ok(0)
+            } else {
+                Ok(1)
+            }
+        }
         Commands::BinaryPlot {
             data1,
             data2,
@@ -292,14 +684,16 @@ fn main_result() -> anyhow::Result<i32> {
             out_dir,
         } => {
             let ltc = get_local_toolchain(
+                &[],
                 &local.rustc,
+                local.rustdoc.as_deref(),
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
             )?;
 
             let benchmark_suit = BenchmarkSuit {
-                benchmarks: discover_benchmark_suit(&bench_dir)?,
+                benchmarks: discover_benchmark_suit(&bench_dir, &local.benchmark_filter())?,
             };
             println!("{}", benchmark_suit.display_benchmarks());
 
@@ -342,6 +736,21 @@ fn perf_check() {
     assert!(has_perf);
 }
 
+/// rustc-fake summarizes self-profile data with `summarize` of measureme,
+/// falling back to `summarize-9.0`.
+fn summarize_check() -> anyhow::Result<()> {
+    let has_summarize = ["summarize", "summarize-9.0"]
+        .iter()
+        .any(|name| Command::new(name).arg("--version").output().is_ok());
+    if !has_summarize {
+        bail!(
+            "`--perf-tool perf-stat-self-profile` needs `summarize` in PATH. Install it with \
+            `cargo install --git https://github.com/rust-lang/measureme --branch stable summarize`."
+        );
+    }
+    Ok(())
+}
+
 fn flamegraph_check() {
     let mut cmd = Command::new("flamegraph");
     let has_flamegraph = cmd.output().is_ok();
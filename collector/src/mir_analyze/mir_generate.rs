@@ -155,6 +155,7 @@ fn package_generate_mir(
 #[cfg(test)]
 mod test {
     use std::{
+        collections::HashMap,
         fs::remove_dir_all,
         path::{Path, PathBuf},
     };
@@ -181,9 +182,20 @@ mod test {
                 runtime_test_type: None,
                 example_lst: None,
                 runtime_args: None,
+                patches: None,
                 touch_file: None,
                 disabled: false,
+                tags: vec![],
                 runs: 0,
+                warmup_runs: 0,
+                profiles: HashMap::new(),
+                feature_sets: vec![],
+                timeout_secs: None,
+                run_executables: false,
+                test_filter: vec![],
+                inputs: vec![],
+                expected_output_sha256: None,
+                scaling: None,
                 target_path: None,
                 runtime_cargo_toml: None,
                 runtime_test_packages: None,
@@ -195,6 +207,7 @@ mod test {
             &benchmark,
             &LocalToolchain {
                 rustc: PathBuf::from("rustc"),
+                rustdoc: None,
                 cargo: PathBuf::from("cargo"),
                 flame_graph: PathBuf::from(""),
                 id: 0.to_string(),
@@ -226,6 +239,7 @@ mod test {
             &benchmark,
             &LocalToolchain {
                 rustc: PathBuf::from("rustc"),
+                rustdoc: None,
                 cargo: PathBuf::from("cargo"),
                 flame_graph: PathBuf::from(""),
                 id: 0.to_string(),
@@ -1,4 +1,5 @@
 use std::{
+    fs::remove_dir_all,
     path::{Path, PathBuf},
     process::Command,
 };
@@ -10,7 +11,11 @@ use crate::{
     utils::command::{command_discard_output, command_output},
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
+use super::{
+    criterion::{append_criterion_results, criterion_dir},
+    executables::{build_executables, measure_executables, HarnessResults},
+    Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME,
+};
 
 pub struct CargoBenchProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -19,6 +24,8 @@ pub struct CargoBenchProcess<'a> {
     pub manifest_path: String,
     pub iterations: u32,
     args: Vec<String>,
+    /// Run the bench executables directly instead of `cargo bench`.
+    run_executables: bool,
 }
 
 impl<'a> CargoBenchProcess<'a> {
@@ -29,6 +36,7 @@ impl<'a> CargoBenchProcess<'a> {
         manifest_path: String,
         iterations: u32,
         args: Vec<String>,
+        run_executables: bool,
     ) -> Self {
         let process = Self {
             compiler,
@@ -37,7 +45,12 @@ impl<'a> CargoBenchProcess<'a> {
             manifest_path,
             iterations,
             args,
+            run_executables,
         };
+        // The executables are built when measuring.
+        if run_executables {
+            return process;
+        }
         match process.build() {
             Ok(_) => (),
             Err(_) => eprintln!("Fail to compile examples for {}.", process.processor_name),
@@ -46,9 +59,27 @@ impl<'a> CargoBenchProcess<'a> {
         process
     }
 
+    /// `cargo bench` without the wrapper, to build the bench executables.
+    fn build_command(&self) -> Command {
+        let mut cmd = Command::new(self.compiler.cargo);
+        cmd.env("RUSTC", self.compiler.rustc)
+            .env("CARGO_INCREMENTAL", "0")
+            .env("RUSTC_BOOTSTRAP", "1")
+            .current_dir(self.cwd)
+            .arg("bench")
+            .args(self.args.clone())
+            .arg("--manifest-path")
+            .arg(&self.manifest_path)
+            .arg("--profile")
+            .arg("release")
+            .arg("--no-fail-fast");
+        cmd
+    }
+
     fn base_command(&self) -> Command {
         let mut cmd = Command::new(&*FAKE_RUNTIME);
         cmd.env("RUNTIME_ELF", self.compiler.cargo)
+            .env("CRITERION_HOME", criterion_dir(self.cwd))
             .env("RUSTC", self.compiler.rustc)
             // .env("CARGO_INCREMENTAL", "0")
             .env("RUSTC_BOOTSTRAP", "1")
@@ -63,12 +94,31 @@ impl<'a> CargoBenchProcess<'a> {
         cmd
     }
 
+    /// `cargo bench` run by runtime-fake under `perf_tool`.
+    fn measure_command(&self, perf_tool: &PerfTool, iteration: u32) -> Command {
+        let mut cmd = self.base_command();
+        cmd.arg("--wrap-rustc-with").arg(perf_tool.name());
+
+        match perf_tool.get_bencher() {
+            crate::toolchain::Bencher::PerfStat
+            | crate::toolchain::Bencher::Rusage
+            | crate::toolchain::Bencher::PerfStatSelfProfile
+            | crate::toolchain::Bencher::Valgrind(_) => (),
+            crate::toolchain::Bencher::PerfRecord => {
+                cmd.arg("--output")
+                    .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
+            }
+        }
+        cmd
+    }
+
     fn base_flame_graph_command(&self, out: &Path) -> Command {
         let mut cmd = Command::new(&*FAKE_FLAMEGRAPH);
 
         let mut flame_graph_file_name = self.processor_name.clone();
         flame_graph_file_name += "_runtime.svg";
         cmd.current_dir(self.cwd)
+            .env("CRITERION_HOME", criterion_dir(self.cwd))
             .env("RUSTC", self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "1")
             .env("RUSTC_BOOTSTRAP", "1")
@@ -111,6 +161,28 @@ impl<'a> Runtime for CargoBenchProcess<'a> {
         perf_tool: &PerfTool,
         _event_filter_file: &PathBuf,
     ) -> anyhow::Result<Option<crate::statistics::runtime_stat::RuntimeResult>> {
+        // Results of criterion benchmarks, which replace those of the previous
+        // run, so that ones of benchmarks no longer run are not read again.
+        let criterion_dir = criterion_dir(self.cwd);
+        if criterion_dir.exists() {
+            remove_dir_all(&criterion_dir)?;
+        }
+
+        if self.run_executables {
+            eprintln!("Building bench executables for {}...", self.processor_name);
+            let executables = build_executables(&mut self.build_command())?;
+            // `cargo bench` passes `--bench` to the harness as well.
+            return Ok(Some(measure_executables(
+                &self.processor_name,
+                &executables,
+                &["--bench"],
+                HarnessResults::Criterion(&criterion_dir),
+                perf_tool,
+                self.iterations,
+                self.cwd,
+            )?));
+        }
+
         let mut result = RuntimeResult::new(self.processor_name.clone());
 
         for iteration in 0..self.iterations {
@@ -120,24 +192,18 @@ impl<'a> Runtime for CargoBenchProcess<'a> {
                 iteration + 1,
                 self.iterations
             );
-            let mut cmd = self.base_command();
-
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
-                crate::toolchain::Bencher::PerfRecord => {
-                    cmd.arg("--output")
-                        .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
-                }
-            }
-
+            let mut cmd = self.measure_command(perf_tool, iteration);
             let output = command_output(&mut cmd)?;
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => {
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile => {
                     let output = process_benchmark_output(output)?;
                     result.append(output);
+                    append_criterion_results(&mut result, &criterion_dir)?;
                 }
-                crate::toolchain::Bencher::PerfRecord => (),
+                crate::toolchain::Bencher::PerfRecord | crate::toolchain::Bencher::Valgrind(_) => {}
             }
         }
         Ok(Some(result))
@@ -158,3 +224,54 @@ impl<'a> Runtime for CargoBenchProcess<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test_cargo_bench_process {
+    use std::path::Path;
+
+    use crate::toolchain::{Bencher, Compiler, PerfTool};
+
+    use super::CargoBenchProcess;
+
+    /// Test for CargoBenchProcess::measure_command
+    ///
+    /// Step1: Make the command measuring `cargo bench` with perf-stat and
+    /// perf-record.
+    ///
+    /// Step2: Verify runtime-fake is told to wrap cargo with the perf tool,
+    /// and perf-record and criterion write to the benchmark directory.
+    #[test]
+    fn test_measure_command() {
+        let process = CargoBenchProcess {
+            compiler: Compiler {
+                rustc: Path::new("rustc"),
+                rustdoc: None,
+                cargo: Path::new("cargo"),
+                is_nightly: false,
+            },
+            processor_name: String::from("fib"),
+            cwd: Path::new("/tmp/fib"),
+            manifest_path: String::from("Cargo.toml"),
+            iterations: 1,
+            args: vec![],
+            run_executables: false,
+        };
+
+        let cmd = process.measure_command(&PerfTool::BenchTool(Bencher::PerfStat), 0);
+        let args = cmd.get_args().collect::<Vec<_>>();
+        assert_eq!("bench", args[0]);
+        let pos = args.iter().position(|a| *a == "--wrap-rustc-with").unwrap();
+        assert_eq!("PerfStat", args[pos + 1]);
+        let criterion_home = cmd
+            .get_envs()
+            .find(|(key, _)| *key == "CRITERION_HOME")
+            .and_then(|(_, value)| value)
+            .unwrap();
+        assert!(Path::new(criterion_home).starts_with("/tmp/fib"));
+
+        let cmd = process.measure_command(&PerfTool::BenchTool(Bencher::PerfRecord), 2);
+        let args = cmd.get_args().collect::<Vec<_>>();
+        let pos = args.iter().position(|a| *a == "--output").unwrap();
+        assert_eq!("/tmp/fib/02_perf.data", args[pos + 1]);
+    }
+}
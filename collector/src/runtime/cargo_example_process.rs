@@ -1,5 +1,4 @@
 use std::{
-    io::BufReader,
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
@@ -10,7 +9,7 @@ use crate::{
     execute::{process_benchmark_output, Stats},
     statistics::runtime_stat::RuntimeResult,
     toolchain::{Compiler, PerfTool},
-    utils::command::{command_discard_output, command_output},
+    utils::command::{command_discard_output, command_output, run_command_with_output},
 };
 
 use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
@@ -98,17 +97,12 @@ impl<'a> CargoExampleProcess<'a> {
             );
 
             let mut cmd = self.build_command(example);
+            cmd.stdin(Stdio::null());
 
-            let mut child = cmd
-                .stdin(Stdio::null())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::null())
-                .spawn()
-                .map_err(|error| anyhow::anyhow!("Failed to start cargo: {:?}", error))?;
-
-            let stream = BufReader::new(child.stdout.take().unwrap());
+            // A failed example is skipped below.
+            let output = run_command_with_output(&mut cmd)?;
             let mut beacon = false;
-            for message in Message::parse_stream(stream) {
+            for message in Message::parse_stream(&output.stdout[..]) {
                 let message = message?;
                 match message {
                     Message::CompilerArtifact(artifact) => {
@@ -169,7 +163,10 @@ impl<'a> Runtime for CargoExampleProcess<'a> {
                 let mut cmd = self.base_command(elf);
 
                 match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => (),
+                    crate::toolchain::Bencher::PerfStat
+                    | crate::toolchain::Bencher::Rusage
+                    | crate::toolchain::Bencher::PerfStatSelfProfile
+                    | crate::toolchain::Bencher::Valgrind(_) => (),
                     crate::toolchain::Bencher::PerfRecord => {
                         cmd.arg("--output").arg(
                             self.cwd
@@ -183,8 +180,11 @@ impl<'a> Runtime for CargoExampleProcess<'a> {
                 let output = process_benchmark_output(output)?;
 
                 match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => output_sum += output,
-                    crate::toolchain::Bencher::PerfRecord => (),
+                    crate::toolchain::Bencher::PerfStat
+                    | crate::toolchain::Bencher::Rusage
+                    | crate::toolchain::Bencher::PerfStatSelfProfile => output_sum += output,
+                    crate::toolchain::Bencher::PerfRecord
+                    | crate::toolchain::Bencher::Valgrind(_) => (),
                 }
             }
 
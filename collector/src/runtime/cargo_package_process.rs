@@ -114,7 +114,10 @@ impl<'a> Runtime for CargoPackageTestProcess<'a> {
             cmd.arg(perf_tool_name);
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile
+                | crate::toolchain::Bencher::Valgrind(_) => (),
                 crate::toolchain::Bencher::PerfRecord => {
                     cmd.arg("--output")
                         .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
@@ -128,17 +131,21 @@ impl<'a> Runtime for CargoPackageTestProcess<'a> {
             }
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => match process_benchmark_output(output) {
-                    Ok(output) => {
-                        result.append(output);
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile => {
+                    match process_benchmark_output(output) {
+                        Ok(output) => {
+                            result.append(output);
+                        }
+                        Err(err) => {
+                            eprintln!("Fail to test {}. Err msg:", self.processor_name);
+                            eprintln!("{:?}", err);
+                            return Err(err.into());
+                        }
                     }
-                    Err(err) => {
-                        eprintln!("Fail to test {}. Err msg:", self.processor_name);
-                        eprintln!("{:?}", err);
-                        return Err(err.into());
-                    }
-                },
-                crate::toolchain::Bencher::PerfRecord => (),
+                }
+                crate::toolchain::Bencher::PerfRecord | crate::toolchain::Bencher::Valgrind(_) => {}
             }
         }
 
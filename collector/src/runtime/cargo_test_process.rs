@@ -9,7 +9,11 @@ use crate::{
     utils::command::{command_discard_output, command_output},
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
+use super::{
+    executables::{build_executables, measure_executables, HarnessResults},
+    libtest::{parse_test_times, LIBTEST_JSON_ARGS},
+    Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME,
+};
 use crate::statistics::runtime_stat::RuntimeResult;
 
 pub struct CargoTestProcess<'a> {
@@ -19,6 +23,10 @@ pub struct CargoTestProcess<'a> {
     pub manifest_path: String,
     pub iterations: u32,
     pub args: Vec<String>,
+    /// Run the test executables directly instead of `cargo test`.
+    pub run_executables: bool,
+    /// Only tests whose names contain one of these are run.
+    pub test_filter: Vec<String>,
 }
 
 impl<'a> CargoTestProcess<'a> {
@@ -34,7 +42,7 @@ impl<'a> CargoTestProcess<'a> {
             .args(self.args.clone())
             .arg("--manifest-path")
             .arg(&self.manifest_path)
-            .arg("release")
+            .arg("--release")
             .arg("--no-fail-fast");
         cmd
     }
@@ -57,7 +65,35 @@ impl<'a> CargoTestProcess<'a> {
             .args(self.args.clone())
             .arg("--manifest-path")
             .arg(&self.manifest_path)
-            .arg("release")
+            .arg("--release")
+            .arg("--no-fail-fast");
+        cmd
+    }
+
+    /// Arguments of the test harness, to select the tests and report the
+    /// time of each.
+    fn harness_args(&self) -> Vec<&str> {
+        self.test_filter
+            .iter()
+            .map(String::as_str)
+            .chain(LIBTEST_JSON_ARGS)
+            .collect()
+    }
+
+    /// `cargo test` without the wrapper, to build the test executables. It
+    /// selects the same tests as `base_command`.
+    fn build_command(&self) -> Command {
+        let mut cmd = Command::new(self.compiler.cargo);
+        cmd.env("RUSTC", self.compiler.rustc)
+            .env("CARGO_INCREMENTAL", "0")
+            .env("RUSTC_BOOTSTRAP", "1")
+            .current_dir(self.cwd)
+            .arg("test")
+            .arg("--all")
+            .args(self.args.clone())
+            .arg("--manifest-path")
+            .arg(&self.manifest_path)
+            .arg("--release")
             .arg("--no-fail-fast");
         cmd
     }
@@ -76,6 +112,20 @@ impl<'a> Runtime for CargoTestProcess<'a> {
         perf_tool: &PerfTool,
         _event_filter_file: &PathBuf,
     ) -> anyhow::Result<Option<RuntimeResult>> {
+        if self.run_executables {
+            eprintln!("compiling test executables for {}...", self.processor_name);
+            let executables = build_executables(&mut self.build_command())?;
+            return Ok(Some(measure_executables(
+                &self.processor_name,
+                &executables,
+                &self.harness_args(),
+                HarnessResults::TestTimes,
+                perf_tool,
+                self.iterations,
+                self.cwd,
+            )?));
+        }
+
         eprintln!("compiling test for {}...", self.processor_name);
         match self.compile_test() {
             Ok(_) => (),
@@ -105,13 +155,18 @@ impl<'a> Runtime for CargoTestProcess<'a> {
             cmd.arg(perf_tool_name);
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile
+                | crate::toolchain::Bencher::Valgrind(_) => (),
                 crate::toolchain::Bencher::PerfRecord => {
                     cmd.arg("--output")
                         .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
                 }
             }
 
+            cmd.arg("--").args(self.harness_args());
+
             let output = command_output(&mut cmd)?;
 
             if iteration == 0 {
@@ -119,17 +174,25 @@ impl<'a> Runtime for CargoTestProcess<'a> {
             }
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => match process_benchmark_output(output) {
-                    Ok(output) => {
-                        result.append(output);
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile => {
+                    for (test, stats) in parse_test_times(&String::from_utf8_lossy(&output.stdout))
+                    {
+                        result.append_sub(&test, stats);
                     }
-                    Err(err) => {
-                        eprintln!("Fail to test {}. Err msg:", self.processor_name);
-                        eprintln!("{:?}", err);
-                        return Err(err.into());
+                    match process_benchmark_output(output) {
+                        Ok(output) => {
+                            result.append(output);
+                        }
+                        Err(err) => {
+                            eprintln!("Fail to test {}. Err msg:", self.processor_name);
+                            eprintln!("{:?}", err);
+                            return Err(err.into());
+                        }
                     }
-                },
-                crate::toolchain::Bencher::PerfRecord => (),
+                }
+                crate::toolchain::Bencher::PerfRecord | crate::toolchain::Bencher::Valgrind(_) => {}
             }
         }
 
@@ -199,6 +262,39 @@ fn test_cargo_test_process() {
     }
 }
 
+/// Test for CargoTestProcess::base_command
+///
+/// Step1: Make the command running the tests of a benchmark with a test filter.
+///
+/// Step2: Verify the filter is the only test name passed to the harness.
+#[test]
+fn test_cargo_test_command() {
+    let process = CargoTestProcess {
+        compiler: Compiler {
+            rustc: Path::new("rustc"),
+            rustdoc: None,
+            cargo: Path::new("cargo"),
+            is_nightly: false,
+        },
+        processor_name: String::from("sled"),
+        cwd: Path::new("/tmp/sled"),
+        manifest_path: String::from("Cargo.toml"),
+        iterations: 1,
+        args: vec![],
+        run_executables: false,
+        test_filter: vec![String::from("tree")],
+    };
+
+    let mut cmd = process.base_command();
+    cmd.arg("--").args(process.harness_args());
+    let args = cmd.get_args().collect::<Vec<_>>();
+    let pos = args.iter().position(|a| *a == "--").unwrap();
+    assert!(args[..pos].contains(&std::ffi::OsStr::new("--release")));
+    assert!(!args[..pos].contains(&std::ffi::OsStr::new("release")));
+    assert_eq!("tree", args[pos + 1]);
+    assert_eq!(LIBTEST_JSON_ARGS, args[pos + 2..]);
+}
+
 #[test]
 fn test_cargo_test_process_flamegraph() {
     let mut cmd = Command::new("/home/fxl191220029/study/Rust_Performance_Benchmark/collector/target/release/flamegraph-fake");
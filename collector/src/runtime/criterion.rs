@@ -0,0 +1,189 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize};
+use walkdir::WalkDir;
+
+use crate::{execute::Stats, statistics::runtime_stat::RuntimeResult};
+
+/// Results of a criterion benchmark ID written by the last bench run.
+#[derive(Debug)]
+pub struct CriterionResult {
+    pub id: String,
+    /// `criterion-mean`, `criterion-median` and `criterion-std-dev` of the
+    /// time per iteration in ns.
+    pub stats: Stats,
+    /// Time per iteration of each sample in ns.
+    pub samples: Vec<f64>,
+}
+
+/// `new/estimates.json`.
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+    median: Estimate,
+    std_dev: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+/// `new/sample.json`, each sample is `iters[i]` iterations taking `times[i]`.
+#[derive(Deserialize)]
+struct Sample {
+    iters: Vec<f64>,
+    times: Vec<f64>,
+}
+
+/// `new/benchmark.json`.
+#[derive(Deserialize)]
+struct BenchmarkId {
+    full_id: String,
+}
+
+/// Directory criterion is told to write its results to, as `CRITERION_HOME`,
+/// when benchmarking in `cwd`, the temp dir of the benchmark.
+///
+/// It is cleared before every run, so it must never be the user's own
+/// `CRITERION_HOME` or `criterion` in a shared target dir, which may hold
+/// saved baselines.
+pub fn criterion_dir(cwd: &Path) -> PathBuf {
+    cwd.join("collector-criterion")
+}
+
+/// Read the results of every benchmark ID in `criterion_dir`.
+pub fn read_criterion_results(criterion_dir: &Path) -> anyhow::Result<Vec<CriterionResult>> {
+    let mut results = vec![];
+    if !criterion_dir.exists() {
+        return Ok(results);
+    }
+
+    for entry in WalkDir::new(criterion_dir).sort_by_file_name() {
+        let entry = entry?;
+        let new_dir = entry.path();
+        if entry.file_name() != "new" || !new_dir.join("estimates.json").exists() {
+            continue;
+        }
+
+        let estimates: Estimates = read_json(&new_dir.join("estimates.json"))?;
+        let sample: Sample = read_json(&new_dir.join("sample.json"))?;
+        let id = match read_json::<BenchmarkId>(&new_dir.join("benchmark.json")) {
+            Ok(id) => id.full_id,
+            // The directory names are sanitized IDs, e.g. `group/function`.
+            Err(_) => new_dir
+                .parent()
+                .and_then(|p| p.strip_prefix(criterion_dir).ok())
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        let mut stats = Stats::new();
+        stats.insert(
+            String::from("criterion-mean"),
+            estimates.mean.point_estimate,
+        );
+        stats.insert(
+            String::from("criterion-median"),
+            estimates.median.point_estimate,
+        );
+        stats.insert(
+            String::from("criterion-std-dev"),
+            estimates.std_dev.point_estimate,
+        );
+        results.push(CriterionResult {
+            id,
+            stats,
+            samples: sample
+                .iters
+                .iter()
+                .zip(sample.times.iter())
+                .map(|(iters, time)| time / iters)
+                .collect(),
+        });
+    }
+    Ok(results)
+}
+
+/// Append the results in `criterion_dir` to `result`, one sub result per
+/// benchmark ID. Warns if the bench run left no results, which is expected
+/// only for benchmarks not using criterion.
+pub fn append_criterion_results(
+    result: &mut RuntimeResult,
+    criterion_dir: &Path,
+) -> anyhow::Result<()> {
+    let criterion_results = read_criterion_results(criterion_dir)?;
+    if criterion_results.is_empty() {
+        eprintln!(
+            "Warning: no criterion estimates of {} found in {:?}.",
+            result.name, criterion_dir
+        );
+    }
+    for criterion_result in criterion_results {
+        result.append_sub(&criterion_result.id, criterion_result.stats);
+        result.extend_sub_samples(&criterion_result.id, criterion_result.samples);
+    }
+    Ok(())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    serde_json::from_reader(BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {:?}", path))?,
+    ))
+    .with_context(|| format!("failed to parse {:?}", path))
+}
+
+#[cfg(test)]
+mod test_criterion {
+    use std::fs::{create_dir_all, write};
+
+    use super::read_criterion_results;
+
+    /// Test for read_criterion_results
+    ///
+    /// Step1: Write the results of 2 benchmark IDs the way criterion does.
+    ///
+    /// Step2: Read them and verify the estimates and samples.
+    #[test]
+    fn test_read_criterion_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let estimates = r#"{"mean":{"confidence_interval":{"confidence_level":0.95,"lower_bound":9.0,"upper_bound":11.0},"point_estimate":10.0,"standard_error":0.5},
+            "median":{"point_estimate":9.5,"standard_error":0.5},
+            "median_abs_dev":{"point_estimate":0.2,"standard_error":0.1},
+            "slope":null,
+            "std_dev":{"point_estimate":1.5,"standard_error":0.1}}"#;
+        let sample = r#"{"sampling_mode":"Linear","iters":[1.0,2.0],"times":[10.0,30.0]}"#;
+
+        let fib = dir.path().join("fib").join("20").join("new");
+        create_dir_all(&fib).unwrap();
+        write(fib.join("estimates.json"), estimates).unwrap();
+        write(fib.join("sample.json"), sample).unwrap();
+        write(
+            fib.join("benchmark.json"),
+            r#"{"group_id":"fib","function_id":null,"value_str":"20","throughput":null,"full_id":"fib/20","directory_name":"fib/20","title":"fib/20"}"#,
+        )
+        .unwrap();
+        let sort = dir.path().join("sort").join("new");
+        create_dir_all(&sort).unwrap();
+        write(sort.join("estimates.json"), estimates).unwrap();
+        write(sort.join("sample.json"), sample).unwrap();
+        // Results of the previous run are not read.
+        let base = dir.path().join("sort").join("base");
+        create_dir_all(&base).unwrap();
+        write(base.join("estimates.json"), estimates).unwrap();
+
+        let results = read_criterion_results(dir.path()).unwrap();
+        assert_eq!(2, results.len());
+        assert_eq!("fib/20", results[0].id);
+        assert_eq!("sort", results[1].id);
+        assert_eq!(Some(&10.), results[0].stats.stats.get("criterion-mean"));
+        assert_eq!(Some(&9.5), results[0].stats.stats.get("criterion-median"));
+        assert_eq!(Some(&1.5), results[0].stats.stats.get("criterion-std-dev"));
+        assert_eq!(vec![10., 15.], results[1].samples);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cargo_metadata::Message;
+
+use crate::{
+    execute::{process_benchmark_output, Stats},
+    statistics::runtime_stat::RuntimeResult,
+    toolchain::PerfTool,
+    utils::command::command_output,
+};
+
+use super::{criterion::append_criterion_results, libtest::parse_test_times, FAKE_RUNTIME};
+
+/// A test or bench executable built by cargo, which is run directly instead
+/// of through `cargo test` or `cargo bench`, so that the measurements do
+/// not include cargo startup and fingerprint checks.
+#[derive(Debug)]
+pub struct Executable {
+    /// `<target kind>-<target name>`, e.g. `lib-sled` or `test-tree`.
+    pub name: String,
+    pub path: PathBuf,
+    /// Directory of the package's `Cargo.toml`, in which cargo would run the
+    /// executable.
+    pub cwd: PathBuf,
+}
+
+/// Build with `cmd`, a `cargo test` or `cargo bench` command, and collect
+/// the executables of the test targets from cargo's json messages.
+pub fn build_executables(cmd: &mut Command) -> anyhow::Result<Vec<Executable>> {
+    cmd.arg("--no-run").arg("--message-format").arg("json");
+    let output = command_output(cmd)?;
+
+    let mut executables = vec![];
+    for message in Message::parse_stream(&output.stdout[..]) {
+        match message? {
+            Message::CompilerArtifact(artifact) if artifact.profile.test => {
+                if let Some(executable) = artifact.executable {
+                    let executable = Executable {
+                        name: format!(
+                            "{}-{}",
+                            artifact.target.kind.join("-"),
+                            artifact.target.name
+                        ),
+                        path: executable.into_std_path_buf(),
+                        cwd: artifact
+                            .manifest_path
+                            .parent()
+                            .map(|p| p.as_std_path().to_path_buf())
+                            .unwrap_or_default(),
+                    };
+                    log::info!("Compiled {}", executable.path.display());
+                    executables.push(executable);
+                }
+            }
+            message => log::debug!("Cargo metadata output: {:?}", message),
+        }
+    }
+    Ok(executables)
+}
+
+/// Results reported by the harness of the executables, besides the stats
+/// measured for each executable as a whole.
+pub enum HarnessResults<'a> {
+    /// The time of each test reported by libtest, recorded as sub results
+    /// named `<executable>/<test>`.
+    TestTimes,
+    /// The criterion results written to the given `CRITERION_HOME`, one
+    /// sub result per benchmark ID.
+    Criterion(&'a Path),
+}
+
+/// Measure each of `executables` under `perf_tool`, passing `args` to the
+/// test harness. The stats of each executable are recorded as a sub result,
+/// and their sum as the stats of the benchmark.
+pub fn measure_executables(
+    processor_name: &str,
+    executables: &[Executable],
+    args: &[&str],
+    harness_results: HarnessResults,
+    perf_tool: &PerfTool,
+    iterations: u32,
+    perf_data_dir: &Path,
+) -> anyhow::Result<RuntimeResult> {
+    let mut result = RuntimeResult::new(processor_name.to_string());
+
+    for iteration in 0..iterations {
+        let mut output_sum = Stats::new();
+        for executable in executables {
+            eprintln!(
+                "running '{}-{}' Runtime iteration {}/{}",
+                processor_name,
+                executable.name,
+                iteration + 1,
+                iterations
+            );
+
+            let mut cmd = Command::new(&*FAKE_RUNTIME);
+            cmd.env("RUNTIME_ELF", &executable.path)
+                .env("CARGO_MANIFEST_DIR", &executable.cwd)
+                .env("RUSTC_BOOTSTRAP", "1")
+                .current_dir(&executable.cwd)
+                .args(args)
+                .arg("--wrap-rustc-with")
+                .arg(perf_tool.name());
+
+            match perf_tool.get_bencher() {
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile
+                | crate::toolchain::Bencher::Valgrind(_) => (),
+                crate::toolchain::Bencher::PerfRecord => {
+                    cmd.arg("--output").arg(
+                        perf_data_dir
+                            .join(format!("{}_{:02}_perf.data", executable.name, iteration)),
+                    );
+                }
+            }
+
+            if let HarnessResults::Criterion(criterion_dir) = harness_results {
+                cmd.env("CRITERION_HOME", criterion_dir);
+            }
+
+            let output = command_output(&mut cmd)?;
+
+            match perf_tool.get_bencher() {
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile => {
+                    if let HarnessResults::TestTimes = harness_results {
+                        for (test, stats) in
+                            parse_test_times(&String::from_utf8_lossy(&output.stdout))
+                        {
+                            result.append_sub(&format!("{}/{}", executable.name, test), stats);
+                        }
+                    }
+                    let output = process_benchmark_output(output)?;
+                    result.append_sub(&executable.name, output.clone());
+                    output_sum += output;
+                }
+                crate::toolchain::Bencher::PerfRecord | crate::toolchain::Bencher::Valgrind(_) => {}
+            }
+        }
+
+        result.append(output_sum);
+        if let HarnessResults::Criterion(criterion_dir) = harness_results {
+            append_criterion_results(&mut result, criterion_dir)?;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test_executables {
+    use std::{path::Path, process::Command};
+
+    use tempfile::TempDir;
+
+    use crate::{
+        runtime::libtest::LIBTEST_JSON_ARGS,
+        toolchain::{Bencher, PerfTool},
+    };
+
+    use super::{build_executables, measure_executables, Executable, HarnessResults};
+
+    fn build_adder(target_dir: &Path) -> Vec<Executable> {
+        let mut cmd = Command::new("cargo");
+        cmd.current_dir("test/executables/adder")
+            .env("CARGO_TARGET_DIR", target_dir)
+            .arg("test")
+            .arg("--release");
+        let mut executables = build_executables(&mut cmd).unwrap();
+        executables.sort_by(|a, b| a.name.cmp(&b.name));
+        executables
+    }
+
+    /// Test for build_executables
+    ///
+    /// Step1: Build the tests of a package with unit and integration tests.
+    ///
+    /// Step2: Verify an executable is collected for each test target.
+    #[test]
+    fn test_build_executables() {
+        let target_dir = TempDir::new().unwrap();
+        let executables = build_adder(target_dir.path());
+
+        assert_eq!(
+            vec!["lib-adder", "test-tree"],
+            executables
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        executables.iter().for_each(|e| {
+            assert!(e.path.starts_with(target_dir.path()));
+            assert!(e.path.exists());
+            assert!(e.cwd.ends_with("test/executables/adder"));
+        });
+    }
+
+    /// Test for measure_executables
+    ///
+    /// Step1: Measure the test executables of a package for 2 iterations
+    /// with rusage, reporting the time of each test.
+    ///
+    /// Step2: Verify the stats of the benchmark, of each executable and of
+    /// each test.
+    #[test]
+    fn test_measure_executables() {
+        let target_dir = TempDir::new().unwrap();
+        let executables = build_adder(target_dir.path());
+
+        let result = measure_executables(
+            "adder",
+            &executables,
+            &LIBTEST_JSON_ARGS,
+            HarnessResults::TestTimes,
+            &PerfTool::BenchTool(Bencher::Rusage),
+            2,
+            target_dir.path(),
+        )
+        .unwrap();
+
+        assert_eq!(2, result.stats.len());
+        assert!(result.stats[0].stats.contains_key("wall-time"));
+        for name in [
+            "lib-adder",
+            "lib-adder/tests::it_adds",
+            "test-tree",
+            "test-tree/tree_adds",
+        ] {
+            let sub = result.sub_results.iter().find(|s| s.name == name).unwrap();
+            assert_eq!(2, sub.stats.len());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+
+use crate::execute::Stats;
+
+/// Arguments of the libtest harness to print an event with the execution
+/// time of each test. They are unstable, so `RUSTC_BOOTSTRAP` must be set.
+pub const LIBTEST_JSON_ARGS: [&str; 5] = [
+    "-Z",
+    "unstable-options",
+    "--format",
+    "json",
+    "--report-time",
+];
+
+/// A line printed by libtest with `--format json`.
+#[derive(Deserialize)]
+struct TestEvent {
+    #[serde(rename = "type")]
+    ty: String,
+    name: Option<String>,
+    exec_time: Option<ExecTime>,
+}
+
+/// Older toolchains print the time as a string like `"0.001s"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExecTime {
+    Secs(f64),
+    Str(String),
+}
+
+impl ExecTime {
+    fn secs(&self) -> Option<f64> {
+        match self {
+            ExecTime::Secs(secs) => Some(*secs),
+            ExecTime::Str(s) => s.trim_end_matches('s').parse().ok(),
+        }
+    }
+}
+
+/// Collect the `exec-time` of each test from the libtest json events in
+/// `stdout`, other lines are ignored. Tests run several times, e.g. by
+/// several executables of `cargo test`, are summed.
+pub fn parse_test_times(stdout: &str) -> Vec<(String, Stats)> {
+    let mut times: Vec<(String, Stats)> = vec![];
+    for line in stdout.lines().filter(|l| l.starts_with('{')) {
+        let event: TestEvent = match serde_json::from_str(line) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+        let (name, secs) = match (event.ty == "test", event.name, event.exec_time) {
+            (true, Some(name), Some(time)) => match time.secs() {
+                Some(secs) => (name, secs),
+                None => continue,
+            },
+            _ => continue,
+        };
+        match times.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, stats)) => stats.add_or_insert(String::from("exec-time"), secs),
+            None => {
+                let mut stats = Stats::new();
+                stats.insert(String::from("exec-time"), secs);
+                times.push((name, stats));
+            }
+        }
+    }
+    times
+}
+
+#[cfg(test)]
+mod test_libtest {
+    use super::parse_test_times;
+
+    /// Test for parse_test_times
+    ///
+    /// Step1: Parse libtest json output mixed with perf stat output.
+    ///
+    /// Step2: Verify the execution time of each test.
+    #[test]
+    fn test_parse_test_times() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "tree::insert" }
+{ "type": "test", "name": "tree::insert", "event": "ok", "exec_time": 0.25 }
+{ "type": "test", "name": "tree::remove", "event": "failed", "exec_time": "0.5s", "stdout": "" }
+{ "type": "test", "event": "ignored", "name": "tree::slow" }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 1, "ignored": 1, "exec_time": 0.75 }
+{ "type": "test", "name": "tree::insert", "event": "ok", "exec_time": 0.5 }
+1000;;instructions:u;1000;100.00
+"#;
+        let times = parse_test_times(stdout);
+        assert_eq!(2, times.len());
+        assert_eq!("tree::insert", times[0].0);
+        assert_eq!(Some(&0.75), times[0].1.stats.get("exec-time"));
+        assert_eq!("tree::remove", times[1].0);
+        assert_eq!(Some(&0.5), times[1].1.stats.get("exec-time"));
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    io::BufReader,
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
@@ -8,6 +7,7 @@ use anyhow::Context;
 use cargo_metadata::Message;
 
 use crate::{
+    benchmark::{benchmark::BenchmarkConfig, feature_set::FeatureSet},
     execute::process_benchmark_output,
     toolchain::{Compiler, PerfTool},
     utils::command::{command_discard_output, command_output},
@@ -22,27 +22,55 @@ pub struct RuntimeProcess<'a> {
     elf: PathBuf,
     name: String,
     args: Vec<String>,
+    /// Arguments for cargo to build the binary with.
+    cargo_args: Vec<String>,
     manifest_path: String,
     iterations: u32,
+    /// Input file piped to the stdin of the binary.
+    stdin: Option<PathBuf>,
+    expected_output_sha256: Option<String>,
 }
 
 impl<'a> RuntimeProcess<'a> {
+    /// Build the binary of the benchmark `name` in `cwd` with `feature_set`.
+    /// It is run with the `runtime_args` of `config` followed by `extra_args`,
+    /// and the `inputs` of `config`.
     pub fn new(
         compiler: Compiler<'a>,
         cwd: &'a Path,
         name: String,
-        args: Vec<String>,
-        manifest_path: String,
+        config: &BenchmarkConfig,
+        feature_set: &FeatureSet,
+        extra_args: Vec<String>,
         iterations: u32,
     ) -> Self {
+        let (stdin, inputs): (Vec<_>, Vec<_>) = config.inputs.iter().partition(|i| i.stdin);
         let mut process = RuntimeProcess {
             compiler,
             cwd,
             elf: PathBuf::new(),
             name,
-            args,
-            manifest_path,
+            args: config
+                .runtime_args
+                .clone()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .chain(extra_args)
+                .chain(
+                    inputs
+                        .iter()
+                        .map(|i| cwd.join(&i.path).to_string_lossy().into_owned()),
+                )
+                .collect(),
+            cargo_args: feature_set.cargo_args(),
+            manifest_path: config
+                .runtime_cargo_toml
+                .clone()
+                .unwrap_or_else(|| String::from("Cargo.toml")),
             iterations,
+            stdin: stdin.first().map(|i| cwd.join(&i.path)),
+            expected_output_sha256: config.expected_output_sha256.clone(),
         };
 
         match process.build() {
@@ -61,6 +89,13 @@ impl<'a> RuntimeProcess<'a> {
         let mut cmd = Command::new(Path::new(&*FAKE_RUNTIME));
         cmd.env("RUNTIME_ELF", self.elf.clone())
             .args(self.args.clone());
+        // Read by runtime-fake, which fails if the output is not as expected.
+        if let Some(stdin) = &self.stdin {
+            cmd.env("RUNTIME_STDIN", stdin);
+        }
+        if let Some(sha256) = &self.expected_output_sha256 {
+            cmd.env("RUNTIME_EXPECTED_SHA256", sha256);
+        }
         cmd
     }
 
@@ -84,20 +119,15 @@ impl<'a> RuntimeProcess<'a> {
             .env("RUSTC", self.compiler.rustc)
             .arg("build")
             .arg("--release")
-            .arg("--message-format")
+            .args(&self.cargo_args)
             .arg("--manifest-path")
             .arg(&self.manifest_path)
-            .arg("json-diagnostic-rendered-ansi");
-
-        let mut child = cmd
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()
-            .map_err(|error| anyhow::anyhow!("Failed to start cargo: {:?}", error))?;
+            .arg("--message-format")
+            .arg("json-diagnostic-rendered-ansi")
+            .stdin(Stdio::null());
 
-        let stream = BufReader::new(child.stdout.take().unwrap());
-        for message in Message::parse_stream(stream) {
+        let output = command_output(&mut cmd)?;
+        for message in Message::parse_stream(&output.stdout[..]) {
             let message = message?;
             match message {
                 Message::CompilerArtifact(artifact) => {
@@ -145,7 +175,10 @@ impl<'a> Runtime for RuntimeProcess<'a> {
             cmd.arg(perf_tool_name);
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile
+                | crate::toolchain::Bencher::Valgrind(_) => (),
                 crate::toolchain::Bencher::PerfRecord => {
                     cmd.arg("--output")
                         .arg(self.cwd.join(format!("{}_perf.data", iteration)));
@@ -153,14 +186,16 @@ impl<'a> Runtime for RuntimeProcess<'a> {
             }
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => {
+                crate::toolchain::Bencher::PerfStat
+                | crate::toolchain::Bencher::Rusage
+                | crate::toolchain::Bencher::PerfStatSelfProfile => {
                     let output = command_output(&mut cmd)
                         .with_context(|| format!("fail to start benchmark process."))?;
                     let stats = process_benchmark_output(output)?;
                     log::info!("stats:{:?}", stats);
                     result.append(stats);
                 }
-                crate::toolchain::Bencher::PerfRecord => (),
+                crate::toolchain::Bencher::PerfRecord | crate::toolchain::Bencher::Valgrind(_) => {}
             }
         }
 
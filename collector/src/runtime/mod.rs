@@ -2,15 +2,27 @@ use std::{
     fs::{create_dir_all, read_dir},
     mem::ManuallyDrop,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use anyhow::{bail, Context};
+use anyhow::bail;
 
 use crate::{
-    benchmark::benchmark::Benchamrk,
-    benchmark::benchmark::BenchmarkSuit,
-    statistics::runtime_stat::RuntimeResultVec,
-    toolchain::{Compiler, LocalToolchain, PerfTool},
+    benchmark::{
+        adaptive::AdaptiveIterations,
+        benchmark::{Benchamrk, BenchmarkSuit},
+        failure::{BenchmarkFailure, FailureKind},
+        feature_set::FeatureSet,
+        filter::BenchmarkFilter,
+        interleave::Side,
+        run_options::RunOptions,
+        scaling::Scaling,
+    },
+    compile_time::{discover_benchmark_suit, write_failures},
+    execute::Stats,
+    statistics::{metadata::RunMetadata, runtime_stat::RuntimeResultVec},
+    toolchain::{Bencher, Compiler, LocalToolchain, PerfTool},
+    utils::{checkpoint::Checkpoint, command::TimeoutGuard},
 };
 
 use crate::statistics::runtime_stat::RuntimeResult;
@@ -19,6 +31,9 @@ pub mod cargo_bench_process;
 pub mod cargo_example_process;
 pub mod cargo_package_process;
 pub mod cargo_test_process;
+pub mod criterion;
+pub mod executables;
+pub mod libtest;
 pub mod measure;
 
 pub trait Runtime {
@@ -30,121 +45,491 @@ pub trait Runtime {
     fn draw_flame_graph(&self, out_path: &Path) -> anyhow::Result<()>;
 }
 
+/// runtime-fake samples the memory of the workload every 5 ms, see
+/// `WorkloadMemoryTracker` there.
+const WORKLOAD_MEMORY_NOTE: &str =
+    "note: workload-max-rss is sampled every 5 ms, processes living shorter may be missed";
+
 pub fn bench_runtime(
     ltc: &LocalToolchain,
     benchmark_dir: PathBuf,
-    iterations: u32,
-    perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
-    flame_graph_out_path: &Path,
-    flamegraph_flag: i32,
-    out_dir: &PathBuf,
+    filter: &BenchmarkFilter,
+    options: &RunOptions,
+    warmup_runs: Option<u32>,
+    resume: bool,
 ) -> anyhow::Result<RuntimeResultVec> {
+    let RunOptions {
+        perf_tool,
+        event_filter_file,
+        iterations,
+        adaptive,
+        flame_graph_path: flame_graph_out_path,
+        flamegraph_flag,
+        out_dir,
+    } = *options;
+    let started_at = SystemTime::now();
+    match perf_tool.get_bencher() {
+        Bencher::Valgrind(tool) => {
+            bail!("{:?} is only supported for compile-time benchmarks.", tool)
+        }
+        Bencher::PerfStatSelfProfile => {
+            bail!("PerfStatSelfProfile is only supported for compile-time benchmarks.")
+        }
+        _ => (),
+    }
+
+    let mut checkpoint = Checkpoint::new(out_dir, "runtime_checkpoint.jsonl", resume)?;
+
     let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
+        benchmarks: discover_benchmark_suit(&benchmark_dir, filter)?,
     };
     println!("{}", benchmark_suit.display_benchmarks());
+    println!("{}", WORKLOAD_MEMORY_NOTE);
+
+    let mut failures = vec![];
+
+    for benchmark in &benchmark_suit.benchmarks {
+        // In adaptive mode, further iterations are done one by one.
+        let iterations = adaptive.map_or(iterations.unwrap_or(benchmark.config.runs), |a| {
+            a.min_iterations
+        }) as u32;
+        for feature_set in benchmark.feature_sets() {
+            // Unlike compile-time ones, runtime results are checkpointed per
+            // feature set rather than per iteration, as all iterations are
+            // measured by a single `Runtime::measure` call.
+            if checkpoint.records().iter().any(|r: &RuntimeResult| {
+                r.name == benchmark.name && r.feature_set == feature_set.name
+            }) {
+                println!(
+                    "Skipping {} [{}]: already recorded",
+                    benchmark.name, feature_set.name
+                );
+                continue;
+            }
 
-    let mut results = RuntimeResultVec(vec![]);
+            let timing_dir = ManuallyDrop::new(benchmark.make_temp_dir(&benchmark.path)?);
+            let cwd = timing_dir.path();
 
-    for benchmark in benchmark_suit.benchmarks {
-        let timing_dir = ManuallyDrop::new(benchmark.make_temp_dir(&benchmark.path)?);
-        let cwd = timing_dir.path();
+            let _timeout = TimeoutGuard::new(benchmark.timeout());
+            let process = benchmark.make_runtime_process(
+                Compiler::from_toolchain(ltc),
+                cwd,
+                iterations,
+                &feature_set,
+            );
 
-        let process =
-            benchmark.make_runtime_process(Compiler::from_toolchain(ltc), cwd, iterations);
+            match &process {
+                Ok(process) => {
+                    let warmup_runs = warmup_runs.unwrap_or(benchmark.config.warmup_runs);
+                    let warmup = match warm_up(
+                        benchmark,
+                        Compiler::from_toolchain(ltc),
+                        cwd,
+                        warmup_runs,
+                        &feature_set,
+                        perf_tool,
+                        event_filter_file,
+                    ) {
+                        Ok(warmup) => warmup,
+                        Err(err) => {
+                            eprintln!(
+                                "Fail to warm up {} [{}]: {:?}",
+                                benchmark.name, feature_set.name, err
+                            );
+                            vec![]
+                        }
+                    };
 
-        match &process {
-            Ok(process) => {
-                let result = process.measure(perf_tool, event_filter_file);
+                    let result = process.measure(perf_tool, event_filter_file);
 
-                match result {
-                    Ok(result) => {
-                        match perf_tool.get_bencher() {
-                            crate::toolchain::Bencher::PerfStat => {
-                                if result.is_some() {
-                                    results.0.push(result.unwrap())
+                    match result {
+                        Ok(result) => {
+                            match perf_tool.get_bencher() {
+                                crate::toolchain::Bencher::PerfStat
+                                | crate::toolchain::Bencher::Rusage
+                                | crate::toolchain::Bencher::PerfStatSelfProfile => {
+                                    if let Some(mut result) = result {
+                                        result.feature_set = feature_set.name.clone();
+                                        result.warmup = warmup;
+                                        let context = BenchContext {
+                                            benchmark,
+                                            compiler: Compiler::from_toolchain(ltc),
+                                            cwd,
+                                            feature_set: &feature_set,
+                                        };
+                                        if let Some(adaptive) = adaptive {
+                                            if let Err(err) = measure_adaptively(
+                                                &context,
+                                                perf_tool,
+                                                event_filter_file,
+                                                adaptive,
+                                                &mut result,
+                                            ) {
+                                                eprintln!(
+                                                    "Fail to iterate {} [{}] further: {:?}",
+                                                    benchmark.name, feature_set.name, err
+                                                );
+                                            }
+                                        }
+                                        if let Some(scaling) = &benchmark.config.scaling {
+                                            if let Err(err) = measure_scaling(
+                                                &context,
+                                                iterations,
+                                                perf_tool,
+                                                event_filter_file,
+                                                scaling,
+                                                &mut result,
+                                            ) {
+                                                eprintln!(
+                                                    "Fail to measure scaling of {} [{}]: {:?}",
+                                                    benchmark.name, feature_set.name, err
+                                                );
+                                            }
+                                        }
+                                        checkpoint.record(result)?;
+                                    }
                                 }
-                            }
-                            crate::toolchain::Bencher::PerfRecord => {
-                                // 将PerfRecord产生的数据文件拷贝至目标文件夹
-                                let cwd = timing_dir.path();
-                                let dst_dir = out_dir.join(benchmark.name.clone());
-
-                                let _ = create_dir_all(dst_dir.clone());
-
-                                // Find file whoose name contains "perf.analyze" in directory cwd,
-                                // and copy it into directory dst_dir.
-                                for entry in read_dir(cwd)? {
-                                    let entry = entry?;
-                                    if entry.file_name().to_str().unwrap().contains("perf.data") {
-                                        let src_path = PathBuf::from(entry.file_name());
-                                        let dst_path =
-                                            dst_dir.join(src_path.as_os_str().to_str().unwrap());
-                                        if let Err(err) =
-                                            std::fs::copy(cwd.join(src_path), &dst_path)
+                                crate::toolchain::Bencher::PerfRecord => {
+                                    // 将PerfRecord产生的数据文件拷贝至目标文件夹
+                                    let cwd = timing_dir.path();
+                                    let dst_dir = out_dir
+                                        .join(benchmark.name.clone())
+                                        .join(feature_set.name.clone());
+
+                                    let _ = create_dir_all(dst_dir.clone());
+
+                                    // Find file whoose name contains "perf.analyze" in directory cwd,
+                                    // and copy it into directory dst_dir.
+                                    for entry in read_dir(cwd)? {
+                                        let entry = entry?;
+                                        if entry.file_name().to_str().unwrap().contains("perf.data")
                                         {
-                                            eprintln!("Failed to copy 'perf.data' file: {}", err);
+                                            let src_path = PathBuf::from(entry.file_name());
+                                            let dst_path = dst_dir
+                                                .join(src_path.as_os_str().to_str().unwrap());
+                                            if let Err(err) =
+                                                std::fs::copy(cwd.join(src_path), &dst_path)
+                                            {
+                                                eprintln!(
+                                                    "Failed to copy 'perf.data' file: {}",
+                                                    err
+                                                );
+                                            }
                                         }
                                     }
                                 }
+                                // Rejected before benchmarking.
+                                crate::toolchain::Bencher::Valgrind(_) => (),
                             }
                         }
+                        Err(err) => {
+                            eprintln!(
+                                "Fail to bench {} [{}]. Skip.",
+                                benchmark.name, feature_set.name
+                            );
+                            failures.push(BenchmarkFailure {
+                                feature_set: Some(feature_set.name.clone()),
+                                ..BenchmarkFailure::new(benchmark.name.clone(), &err)
+                            });
+                            continue;
+                        }
                     }
-                    Err(_) => {
-                        eprintln!("Fail to bench {}. Skip.", benchmark.name);
-                        continue;
+                    //draw flamegraph
+                    if flamegraph_flag > 0 {
+                        process.draw_flame_graph(flame_graph_out_path)?;
                     }
                 }
-                //draw flamegraph
-                if flamegraph_flag > 0 {
-                    process.draw_flame_graph(flame_graph_out_path)?;
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    eprintln!(
+                        "Benchmark {} skipped due to previous error.",
+                        benchmark.name
+                    );
+                    failures.push(BenchmarkFailure {
+                        feature_set: Some(feature_set.name.clone()),
+                        kind: FailureKind::ConfigError,
+                        ..BenchmarkFailure::new(
+                            benchmark.name.clone(),
+                            &anyhow::Error::msg(msg.clone()),
+                        )
+                    });
                 }
             }
-            Err(msg) => {
-                eprintln!("{}", msg);
-                eprintln!(
-                    "Benchmark {} skipped due to previous error.",
-                    benchmark.name
-                );
-            }
+
+            // process need to be dropped before we drop timing_dir
+            // but why?
+            drop(process);
+            drop(ManuallyDrop::into_inner(timing_dir));
         }
+    }
 
-        // process need to be dropped before we drop timing_dir
-        // but why?
-        drop(process);
-        drop(ManuallyDrop::into_inner(timing_dir));
+    let metadata = RunMetadata::collect(&ltc.rustc, &ltc.cargo, &benchmark_dir, started_at);
+    write_failures(out_dir, metadata, &failures)?;
+
+    // Results of this run and of the resumed one.
+    let mut results = RuntimeResultVec(checkpoint.into_records());
+    results
+        .0
+        .retain(|r| benchmark_suit.benchmarks.iter().any(|b| b.name == r.name));
+
+    Ok(results)
+}
+
+/// Measure every benchmark with the toolchains `ltcs` of both sides,
+/// alternating them iteration by iteration in the order of `schedule`. Each
+/// side is built and warmed up in a temp dir of its own, set up exactly like
+/// in `bench_runtime`, before the measured iterations start. The iterations,
+/// adaptive mode and flamegraphs of `options` are not used.
+pub fn bench_runtime_interleaved(
+    ltcs: [&LocalToolchain; 2],
+    benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
+    options: &RunOptions,
+    warmup_runs: Option<u32>,
+    schedule: &[[Side; 2]],
+) -> anyhow::Result<[RuntimeResultVec; 2]> {
+    let started_at = SystemTime::now();
+    let RunOptions {
+        perf_tool,
+        event_filter_file,
+        out_dir,
+        ..
+    } = *options;
+    match perf_tool.get_bencher() {
+        Bencher::PerfStat | Bencher::Rusage => (),
+        bencher => bail!("{:?} is not supported to compare toolchains.", bencher),
     }
 
+    let benchmark_suit = BenchmarkSuit {
+        benchmarks: discover_benchmark_suit(&benchmark_dir, filter)?,
+    };
+    println!("{}", benchmark_suit.display_benchmarks());
+    println!("{}", WORKLOAD_MEMORY_NOTE);
+
+    let mut results = [RuntimeResultVec(vec![]), RuntimeResultVec(vec![])];
+    let mut failures = [vec![], vec![]];
+    for benchmark in &benchmark_suit.benchmarks {
+        for feature_set in benchmark.feature_sets() {
+            let _timeout = TimeoutGuard::new(benchmark.timeout());
+            let timing_dirs = [
+                benchmark.make_temp_dir(&benchmark.path)?,
+                benchmark.make_temp_dir(&benchmark.path)?,
+            ];
+
+            for side in [Side::A, Side::B] {
+                let idx = side as usize;
+                let warmup_runs = warmup_runs.unwrap_or(benchmark.config.warmup_runs);
+                // Also builds the benchmark, so that the first measured
+                // iteration of neither side includes the build.
+                if let Err(err) = warm_up(
+                    benchmark,
+                    Compiler::from_toolchain(ltcs[idx]),
+                    timing_dirs[idx].path(),
+                    warmup_runs.max(1),
+                    &feature_set,
+                    perf_tool,
+                    event_filter_file,
+                ) {
+                    eprintln!(
+                        "Fail to warm up {} [{}] with toolchain {:?}: {:?}",
+                        benchmark.name, feature_set.name, side, err
+                    );
+                }
+            }
+
+            let mut side_results: [Option<RuntimeResult>; 2] = [None, None];
+            'iterations: for (i, order) in schedule.iter().enumerate() {
+                for side in order {
+                    let idx = *side as usize;
+                    eprintln!(
+                        "running '{}' [{}] with toolchain {:?}, iteration {}/{}",
+                        benchmark.name,
+                        feature_set.name,
+                        side,
+                        i + 1,
+                        schedule.len()
+                    );
+                    let result = benchmark
+                        .make_runtime_process(
+                            Compiler::from_toolchain(ltcs[idx]),
+                            timing_dirs[idx].path(),
+                            1,
+                            &feature_set,
+                        )
+                        .map_err(anyhow::Error::msg)
+                        .and_then(|process| process.measure(perf_tool, event_filter_file));
+                    match result {
+                        Ok(Some(result)) => match &mut side_results[idx] {
+                            Some(merged) => {
+                                merged.merge(result);
+                            }
+                            None => side_results[idx] = Some(result),
+                        },
+                        Ok(None) => (),
+                        Err(err) => {
+                            eprintln!(
+                                "Fail to bench {} [{}] with toolchain {:?}. Skip.",
+                                benchmark.name, feature_set.name, side
+                            );
+                            failures[idx].push(BenchmarkFailure {
+                                feature_set: Some(feature_set.name.clone()),
+                                iteration: Some(i + 1),
+                                ..BenchmarkFailure::new(benchmark.name.clone(), &err)
+                            });
+                            // Unpaired iterations are of no use.
+                            break 'iterations;
+                        }
+                    }
+                }
+            }
+
+            for (idx, result) in side_results.into_iter().enumerate() {
+                if let Some(mut result) = result {
+                    result.feature_set = feature_set.name.clone();
+                    results[idx].0.push(result);
+                }
+            }
+        }
+    }
+
+    for side in [Side::A, Side::B] {
+        let ltc = ltcs[side as usize];
+        let metadata = RunMetadata::collect(&ltc.rustc, &ltc.cargo, &benchmark_dir, started_at);
+        write_failures(
+            &out_dir.join(side.dir_name()),
+            metadata,
+            &failures[side as usize],
+        )?;
+    }
     Ok(results)
 }
 
-fn discover_benchmark_suit(dir: &PathBuf) -> anyhow::Result<Vec<Benchamrk>> {
-    let mut benchmarks = vec![];
-
-    for entry in read_dir(dir)
-        .with_context(|| format!("failed to list benchmark dir '{}'", dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        let name = match entry.file_name().into_string() {
-            core::result::Result::Ok(s) => s,
-            Err(e) => bail!("non-utf8 benchmark name: {:?}", e),
-        };
-        if !entry.file_type()?.is_dir() {
-            println!("benchmark '{}' ignored", name);
+/// Execute `warmup_runs` iterations of `benchmark` and return their stats.
+/// They are always measured with `perf stat`, so that no profile data of
+/// warmup runs ends up next to that of the measured ones.
+fn warm_up(
+    benchmark: &Benchamrk,
+    compiler: Compiler<'_>,
+    cwd: &Path,
+    warmup_runs: u32,
+    feature_set: &FeatureSet,
+    perf_tool: &PerfTool,
+    event_filter_file: &PathBuf,
+) -> anyhow::Result<Vec<Stats>> {
+    if warmup_runs == 0 {
+        return Ok(vec![]);
+    }
+    eprintln!(
+        "warming up '{}' [{}] with {} runs",
+        benchmark.name, feature_set.name, warmup_runs
+    );
+    let process = benchmark
+        .make_runtime_process(compiler, cwd, warmup_runs, feature_set)
+        .map_err(anyhow::Error::msg)?;
+    // Profile data of perf-record is not kept for warm-up runs, so they are
+    // measured with perf-stat, or rusage if that is the tool chosen.
+    let bencher = match perf_tool.get_bencher() {
+        Bencher::Rusage => Bencher::Rusage,
+        _ => Bencher::PerfStat,
+    };
+    let result = process.measure(&PerfTool::BenchTool(bencher), event_filter_file)?;
+    Ok(result.map(|r| r.stats).unwrap_or_default())
+}
+
+/// A benchmark built with a toolchain and one of its feature sets in the
+/// temp dir `cwd`, which is measured further after its regular iterations,
+/// either adaptively or at the sizes of its `scaling`.
+struct BenchContext<'a> {
+    benchmark: &'a Benchamrk,
+    compiler: Compiler<'a>,
+    cwd: &'a Path,
+    feature_set: &'a FeatureSet,
+}
+
+/// Measure the benchmark of `context` one iteration at a time until the
+/// stopping rule of `adaptive` is met, appending the stats to `result`.
+fn measure_adaptively(
+    context: &BenchContext,
+    perf_tool: &PerfTool,
+    event_filter_file: &PathBuf,
+    adaptive: &AdaptiveIterations,
+    result: &mut RuntimeResult,
+) -> anyhow::Result<()> {
+    while adaptive.needs_more(&result.stats.iter().collect::<Vec<_>>()) {
+        let process = context
+            .benchmark
+            .make_runtime_process(context.compiler, context.cwd, 1, context.feature_set)
+            .map_err(anyhow::Error::msg)?;
+        match process.measure(perf_tool, event_filter_file)? {
+            Some(more) if !more.stats.is_empty() => more.stats.into_iter().for_each(|stats| {
+                result.append(stats);
+            }),
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
+/// Measure the benchmark of `context` at each size of `scaling` and fit the
+/// stats to the complexity models. The largest size is that of the regular
+/// run, so its stats are taken from `result` rather than measured again.
+fn measure_scaling(
+    context: &BenchContext,
+    iterations: u32,
+    perf_tool: &PerfTool,
+    event_filter_file: &PathBuf,
+    scaling: &Scaling,
+    result: &mut RuntimeResult,
+) -> anyhow::Result<()> {
+    let max_size = scaling.max_size();
+    for n in &scaling.sizes {
+        let name = Scaling::sub_result_name(*n);
+        if Some(*n) == max_size {
+            for stats in result.stats.clone() {
+                result.append_sub(&name, stats);
+            }
             continue;
         }
-        benchmarks.push(Benchamrk::new(name, path)?);
+
+        eprintln!(
+            "measuring '{}' [{}] with n = {}",
+            context.benchmark.name, context.feature_set.name, n
+        );
+        let process = context
+            .benchmark
+            .make_scaled_runtime_process(
+                context.compiler,
+                context.cwd,
+                iterations,
+                context.feature_set,
+                *n,
+            )
+            .map_err(anyhow::Error::msg)?;
+        if let Some(sized) = process.measure(perf_tool, event_filter_file)? {
+            for stats in sized.stats {
+                result.append_sub(&name, stats);
+            }
+        }
     }
 
-    Ok(benchmarks)
+    result.fit_complexities(
+        &scaling
+            .sizes
+            .iter()
+            .map(|n| (*n, Scaling::sub_result_name(*n)))
+            .collect::<Vec<_>>(),
+    );
+    Ok(())
 }
 
 lazy_static::lazy_static! {
     static ref FAKE_RUNTIME: PathBuf = {
         let mut fake_runtime = std::env::current_exe().unwrap();
         fake_runtime.pop();
+        // Unit tests run from `target/<profile>/deps`.
+        if cfg!(test) && fake_runtime.ends_with("deps") {
+            fake_runtime.pop();
+        }
         fake_runtime.push("runtime-fake");
         fake_runtime
     };
@@ -187,3 +572,50 @@ fn test_runtime() {
         Err(_) => println!("{:?}", output),
     }
 }
+
+#[cfg(test)]
+mod test_warm_up {
+    use crate::{
+        execute::Stats,
+        statistics::runtime_stat::{RuntimeResult, RuntimeResultVec},
+    };
+
+    fn stats(wall_time: f64) -> Stats {
+        let mut stats = Stats::new();
+        stats.insert(String::from("wall-time"), wall_time);
+        stats
+    }
+
+    /// Test for keeping the stats of `warm_up` out of the measured ones
+    ///
+    /// Step1: Record warmup runs next to the measured runs and to those of an
+    /// adaptive run merged in, the way `bench_runtime` does.
+    ///
+    /// Step2: Check the warmup runs are neither in `stats` nor in the
+    /// statistics, also after a round trip through json.
+    #[test]
+    fn test_warmup_not_measured() {
+        let mut result = RuntimeResult::new(String::from("helloworld"));
+        result.append(stats(1.0));
+        result.append(stats(1.2));
+        result.warmup = vec![stats(10.0), stats(8.0)];
+
+        let mut adaptive = RuntimeResult::new(String::from("helloworld"));
+        adaptive.append(stats(1.1));
+        adaptive.warmup = vec![stats(9.0)];
+        result.merge(adaptive);
+
+        let result: RuntimeResult =
+            serde_json::from_str(&serde_json::to_string(&result).unwrap()).unwrap();
+        assert_eq!(result.stats.len(), 3);
+        assert_eq!(result.warmup.len(), 2);
+        assert!(result.stats.iter().all(|s| s.stats["wall-time"] < 2.0));
+
+        let statistics = RuntimeResultVec(vec![result]).calculate_statistics();
+        let (label, wall_time) = &statistics[0].statistic_vec[0];
+        assert_eq!(label, "wall-time");
+        assert_eq!(wall_time.max, 1.2);
+        assert_eq!(wall_time.min, 1.0);
+        assert!((wall_time.algebraic_mean - 1.1).abs() < 1e-9);
+    }
+}
@@ -5,7 +5,10 @@ use std::path::PathBuf;
 use crate::src_code_analyze::ops::analyze_ops;
 use crate::statistics::compile_time_stat::{CompileTimeBenchResult, CompileTimeResultSet};
 
-use crate::{compile_time::discover_benchmark_suit, src_code_analyze::analyzer::analyze_benchmark};
+use crate::{
+    benchmark::filter::BenchmarkFilter, compile_time::discover_benchmark_suit,
+    src_code_analyze::analyzer::analyze_benchmark,
+};
 
 pub fn src_code_analyze(
     bench_dir: PathBuf,
@@ -13,7 +16,7 @@ pub fn src_code_analyze(
     out_path: PathBuf,
 ) -> anyhow::Result<PathBuf> {
     assert!(dependency_dir.exists());
-    let benchmarks = discover_benchmark_suit(&bench_dir)?;
+    let benchmarks = discover_benchmark_suit(&bench_dir, &BenchmarkFilter::default())?;
 
     let ops = analyze_ops();
 
@@ -3,7 +3,11 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    benchmark::{profile::Profile, scenario::Scenario},
+    benchmark::{
+        feature_set::{default_feature_set_name, labeled_benchmark_name, DEFAULT_FEATURE_SET},
+        profile::Profile,
+        scenario::Scenario,
+    },
     execute::Stats,
     statistics::statistic::Statistics,
 };
@@ -13,7 +17,13 @@ pub struct CompileTimeResult {
     pub benchmark: String,
     pub iteration: usize,
     pub profile: Profile,
+    /// Name of the `FeatureSet` the benchmark was built with.
+    #[serde(default = "default_feature_set_name")]
+    pub feature_set: String,
     pub scenario: Scenario,
+    /// Name of the patch applied before this run, only set for `IncrPatched`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
     pub stats: Stats,
 }
 
@@ -29,10 +39,50 @@ impl CompileTimeResult {
             benchmark,
             iteration,
             profile,
+            feature_set: default_feature_set_name(),
             scenario,
+            patch: None,
             stats,
         }
     }
+
+    pub fn with_feature_set(mut self, feature_set: String) -> Self {
+        self.feature_set = feature_set;
+        self
+    }
+
+    pub fn with_patch(mut self, patch: String) -> Self {
+        self.patch = Some(patch);
+        self
+    }
+
+    pub fn label(&self) -> String {
+        run_label(
+            &self.benchmark,
+            &self.feature_set,
+            &self.profile,
+            &self.scenario,
+            self.patch.as_deref(),
+        )
+    }
+}
+
+/// Label of the runs of a benchmark with one feature set, profile, scenario
+/// and patch, e.g. `helloworld release incr-patched (0-println)`.
+pub fn run_label(
+    benchmark: &str,
+    feature_set: &str,
+    profile: &Profile,
+    scenario: &Scenario,
+    patch: Option<&str>,
+) -> String {
+    format!(
+        "{} {} {}{}",
+        labeled_benchmark_name(benchmark, feature_set),
+        profile,
+        scenario,
+        patch.map(|p| format!(" ({})", p)).unwrap_or_default()
+    )
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -63,11 +113,25 @@ impl CompileTimeBenchResult {
         self.iterations
     }
 
+    /// Get stats of the `Full` scenario measured with `profile` and the
+    /// default feature set.
     pub fn get_stats_ref_by_profile(&self, profile: &Profile) -> Vec<&Stats> {
+        self.get_stats_ref_by_profile_feature_set(profile, DEFAULT_FEATURE_SET)
+    }
+
+    /// Get stats of the `Full` scenario measured with `profile` and `feature_set`.
+    pub fn get_stats_ref_by_profile_feature_set(
+        &self,
+        profile: &Profile,
+        feature_set: &str,
+    ) -> Vec<&Stats> {
         self.result_vec
             .iter()
             .filter_map(|r| {
-                if r.profile == *profile {
+                if r.profile == *profile
+                    && r.feature_set == feature_set
+                    && r.scenario == Scenario::Full
+                {
                     Some(&r.stats)
                 } else {
                     None
@@ -76,14 +140,33 @@ impl CompileTimeBenchResult {
             .collect()
     }
 
-    pub fn get_stats_with_profile_scenario(&self) -> HashMap<(Profile, Scenario), Vec<Stats>> {
-        let mut map = HashMap::<(Profile, Scenario), Vec<Stats>>::new();
+    /// Names of the feature sets measured, in the order they were measured.
+    pub fn get_feature_sets(&self) -> Vec<String> {
+        let mut feature_sets = vec![];
         self.result_vec.iter().for_each(|r| {
-            if map.contains_key(&(r.profile, r.scenario)) {
-                let stats = map.get_mut(&(r.profile, r.scenario)).unwrap();
+            if !feature_sets.contains(&r.feature_set) {
+                feature_sets.push(r.feature_set.clone());
+            }
+        });
+        feature_sets
+    }
+
+    pub fn get_stats_with_profile_scenario(
+        &self,
+    ) -> HashMap<(Profile, String, Scenario, Option<String>), Vec<Stats>> {
+        let mut map = HashMap::<(Profile, String, Scenario, Option<String>), Vec<Stats>>::new();
+        self.result_vec.iter().for_each(|r| {
+            let key = (
+                r.profile.clone(),
+                r.feature_set.clone(),
+                r.scenario,
+                r.patch.clone(),
+            );
+            if map.contains_key(&key) {
+                let stats = map.get_mut(&key).unwrap();
                 stats.push(r.stats.clone());
             } else {
-                map.insert((r.profile, r.scenario), vec![r.stats.clone()]);
+                map.insert(key, vec![r.stats.clone()]);
             }
         });
         map
@@ -106,32 +189,37 @@ impl CompileTimeResultSet {
         self.results.iter().for_each(|result| {
             let stats_map = result.get_stats_with_profile_scenario();
 
-            stats_map.iter().for_each(|((profile, scenario), stats)| {
-                let mut statistic_vec = Vec::<(String, Statistics)>::new();
-                let mut data_map = HashMap::<String, Vec<f64>>::new();
-
-                stats.iter().for_each(|stat| {
-                    stat.stats.iter().for_each(|(label, value)| {
-                        if data_map.contains_key(label) {
-                            data_map.get_mut(label).unwrap().push(value.clone());
-                        } else {
-                            data_map.insert(label.clone(), vec![value.clone()]);
-                        }
+            stats_map
+                .iter()
+                .for_each(|((profile, feature_set, scenario, patch), stats)| {
+                    let mut statistic_vec = Vec::<(String, Statistics)>::new();
+                    let mut data_map = HashMap::<String, (Vec<f64>, Vec<f64>)>::new();
+
+                    stats.iter().for_each(|stat| {
+                        stat.stats.iter().for_each(|(label, value)| {
+                            let (vals, coverage) = data_map.entry(label.clone()).or_default();
+                            vals.push(value.clone());
+                            coverage.push(stat.coverage(label));
+                        });
                     });
-                });
 
-                data_map.iter().for_each(|(label, vals)| {
-                    statistic_vec.push((label.clone(), Statistics::from(vals.clone())));
-                });
+                    data_map.iter().for_each(|(label, (vals, coverage))| {
+                        statistic_vec.push((
+                            label.clone(),
+                            Statistics::from_with_coverage(vals.clone(), coverage),
+                        ));
+                    });
 
-                statistics.push(CompileTimeStatistic {
-                    name: result.benchmark.clone(),
-                    profile: profile.clone(),
-                    scenario: scenario.clone(),
-                    iterations: result.iterations as u32,
-                    statistic_vec,
+                    statistics.push(CompileTimeStatistic {
+                        name: result.benchmark.clone(),
+                        profile: profile.clone(),
+                        feature_set: feature_set.clone(),
+                        scenario: scenario.clone(),
+                        patch: patch.clone(),
+                        iterations: result.iterations as u32,
+                        statistic_vec,
+                    });
                 });
-            });
         });
         statistics
     }
@@ -145,9 +233,25 @@ impl CompileTimeResultSet {
 pub struct CompileTimeStatistic {
     pub name: String,
     pub profile: Profile,
+    #[serde(default = "default_feature_set_name")]
+    pub feature_set: String,
     pub scenario: Scenario,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
     pub iterations: u32,
     pub statistic_vec: Vec<(String, Statistics)>,
 }
 
+impl CompileTimeStatistic {
+    pub fn label(&self) -> String {
+        run_label(
+            &self.name,
+            &self.feature_set,
+            &self.profile,
+            &self.scenario,
+            self.patch.as_deref(),
+        )
+    }
+}
+
 pub type CompileTimeStatistics = Vec<CompileTimeStatistic>;
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// Models a metric is fitted to over the input size `n`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Complexity {
+    #[serde(rename = "O(n)")]
+    Linear,
+    #[serde(rename = "O(n log n)")]
+    Linearithmic,
+    #[serde(rename = "O(n^2)")]
+    Quadratic,
+}
+
+impl Complexity {
+    pub const ALL: [Complexity; 3] = [
+        Complexity::Linear,
+        Complexity::Linearithmic,
+        Complexity::Quadratic,
+    ];
+
+    fn apply(&self, n: f64) -> f64 {
+        match self {
+            Complexity::Linear => n,
+            Complexity::Linearithmic => n * n.max(1.).log2(),
+            Complexity::Quadratic => n * n,
+        }
+    }
+}
+
+/// Least squares fit of `metric = coefficient * model(n) + intercept`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelFit {
+    pub model: Complexity,
+    pub coefficient: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+}
+
+/// Fits of every model to the mean of a metric at each input size.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComplexityFit {
+    pub metric: String,
+    pub fits: Vec<ModelFit>,
+    /// The model with the highest `r_squared`.
+    pub best: Complexity,
+}
+
+/// Fit `points` of `(n, metric)` to every model. At least 3 different sizes
+/// are needed, as any model fits 2 points exactly.
+pub fn fit_complexity(metric: &str, points: &[(f64, f64)]) -> Option<ComplexityFit> {
+    let mut sizes = points.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+    sizes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sizes.dedup();
+    if sizes.len() < 3 {
+        return None;
+    }
+
+    let fits = Complexity::ALL
+        .iter()
+        .map(|model| {
+            let xs = points
+                .iter()
+                .map(|(n, _)| model.apply(*n))
+                .collect::<Vec<_>>();
+            let ys = points.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+            fit_line(*model, &xs, &ys)
+        })
+        .collect::<Vec<_>>();
+    let best = fits
+        .iter()
+        .fold(None, |best: Option<&ModelFit>, fit| match best {
+            Some(best) if best.r_squared >= fit.r_squared => Some(best),
+            _ => Some(fit),
+        })?
+        .model;
+
+    Some(ComplexityFit {
+        metric: metric.to_string(),
+        fits,
+        best,
+    })
+}
+
+fn fit_line(model: Complexity, xs: &[f64], ys: &[f64]) -> ModelFit {
+    let len = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / len;
+    let mean_y = ys.iter().sum::<f64>() / len;
+    let cov = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let var = xs.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>();
+
+    let coefficient = if var > 0. { cov / var } else { 0. };
+    let intercept = mean_y - coefficient * mean_x;
+
+    let ss_res = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (y - (coefficient * x + intercept)).powi(2))
+        .sum::<f64>();
+    let ss_tot = ys.iter().map(|y| (y - mean_y).powi(2)).sum::<f64>();
+    ModelFit {
+        model,
+        coefficient,
+        intercept,
+        r_squared: if ss_tot > 0. {
+            1. - ss_res / ss_tot
+        } else {
+            1.
+        },
+    }
+}
+
+#[cfg(test)]
+mod test_complexity {
+    use super::{fit_complexity, Complexity};
+
+    /// Test for fit_complexity
+    ///
+    /// Step1: Fit metrics growing linearly, quadratically and as n log n.
+    ///
+    /// Step2: Verify the best model and its coefficients.
+    #[test]
+    fn test_fit_complexity() {
+        let sizes = [1000., 2000., 4000., 8000., 16000.];
+
+        let linear = sizes.map(|n| (n, 3. * n + 50.));
+        let fit = fit_complexity("wall-time", &linear).unwrap();
+        assert_eq!(Complexity::Linear, fit.best);
+        assert!((fit.fits[0].coefficient - 3.).abs() < 1e-9);
+        assert!((fit.fits[0].intercept - 50.).abs() < 1e-6);
+
+        let quadratic = sizes.map(|n| (n, 0.5 * n * n));
+        assert_eq!(
+            Complexity::Quadratic,
+            fit_complexity("wall-time", &quadratic).unwrap().best
+        );
+
+        let linearithmic = sizes.map(|n| (n, 2. * n * n.log2()));
+        assert_eq!(
+            Complexity::Linearithmic,
+            fit_complexity("wall-time", &linearithmic).unwrap().best
+        );
+
+        assert!(fit_complexity("wall-time", &linear[..2]).is_none());
+    }
+}
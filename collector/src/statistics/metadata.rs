@@ -0,0 +1,327 @@
+use std::{
+    fs::{read_to_string, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use serde::{
+    de::{DeserializeOwned, Error},
+    Deserialize, Serialize,
+};
+use walkdir::WalkDir;
+
+use crate::utils::command::command_output;
+
+/// The machine a benchmark ran on.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HostInfo {
+    pub cpu_model: String,
+    pub cpu_cores: usize,
+    pub kernel: String,
+    /// cpufreq scaling governor of cpu0, if there is one.
+    pub governor: Option<String>,
+    pub memory_kb: u64,
+}
+
+impl HostInfo {
+    pub fn collect() -> Self {
+        let cpuinfo = read_to_string("/proc/cpuinfo").unwrap_or_default();
+        let meminfo = read_to_string("/proc/meminfo").unwrap_or_default();
+
+        HostInfo {
+            cpu_model: proc_field(&cpuinfo, "model name").unwrap_or_default(),
+            cpu_cores: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(0),
+            kernel: read_trimmed("/proc/sys/kernel/osrelease").unwrap_or_default(),
+            governor: read_trimmed("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor"),
+            memory_kb: proc_field(&meminfo, "MemTotal")
+                .and_then(|m| m.trim_end_matches("kB").trim().parse().ok())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Header of every results and statistics file, recording where and how
+/// they were produced.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunMetadata {
+    pub host: HostInfo,
+    /// Output of `rustc -vV`.
+    pub rustc_version: String,
+    /// Output of `cargo -V`.
+    pub cargo_version: String,
+    pub bench_dir: PathBuf,
+    /// Git revision of the benchmark dir, suffixed with `-dirty` if it has
+    /// local changes, or a hash of its content if it is not in a repo.
+    pub bench_dir_rev: Option<String>,
+    /// Seconds since the UNIX epoch.
+    pub started_at: u64,
+    /// Seconds since the UNIX epoch.
+    pub finished_at: u64,
+    /// Command line arguments of the collector.
+    pub args: Vec<String>,
+}
+
+impl RunMetadata {
+    pub fn collect(rustc: &Path, cargo: &Path, bench_dir: &Path, started_at: SystemTime) -> Self {
+        RunMetadata {
+            rustc_version: version_output(Command::new(rustc).arg("-vV")),
+            cargo_version: version_output(Command::new(cargo).arg("-V")),
+            ..RunMetadata::collect_without_toolchain(bench_dir, started_at)
+        }
+    }
+
+    /// Metadata of a run that uses no toolchain, with empty versions.
+    pub fn collect_without_toolchain(bench_dir: &Path, started_at: SystemTime) -> Self {
+        RunMetadata {
+            host: HostInfo::collect(),
+            rustc_version: String::new(),
+            cargo_version: String::new(),
+            bench_dir: bench_dir.to_path_buf(),
+            bench_dir_rev: bench_dir_rev(bench_dir),
+            started_at: unix_secs(started_at),
+            finished_at: unix_secs(SystemTime::now()),
+            args: std::env::args().collect(),
+        }
+    }
+
+    /// Why results measured on `self` and on `other` should not be
+    /// compared, or `None` if they can be.
+    pub fn incompatibility(&self, other: &RunMetadata) -> Option<String> {
+        let (a, b) = (&self.host, &other.host);
+        if a.cpu_model != b.cpu_model {
+            return Some(format!(
+                "different CPUs: `{}` and `{}`",
+                a.cpu_model, b.cpu_model
+            ));
+        }
+        if a.cpu_cores != b.cpu_cores {
+            return Some(format!(
+                "different numbers of cores: {} and {}",
+                a.cpu_cores, b.cpu_cores
+            ));
+        }
+        if a.governor != b.governor {
+            return Some(format!(
+                "different cpufreq governors: {:?} and {:?}",
+                a.governor, b.governor
+            ));
+        }
+        None
+    }
+}
+
+/// Check that results are from compatible hosts before comparing them.
+/// Files without metadata can not be checked and are accepted with a warning.
+pub fn check_compatible(
+    a: Option<&RunMetadata>,
+    b: Option<&RunMetadata>,
+    force: bool,
+) -> anyhow::Result<()> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            if let Some(reason) = a.incompatibility(b) {
+                if !force {
+                    bail!(
+                        "refuse to compare results of incompatible hosts ({}). Use `--force` to compare anyway.",
+                        reason
+                    );
+                }
+                eprintln!(
+                    "Warning: comparing results of incompatible hosts ({}).",
+                    reason
+                );
+            } else if a.host.kernel != b.host.kernel {
+                eprintln!(
+                    "Warning: results are from different kernels: {} and {}.",
+                    a.host.kernel, b.host.kernel
+                );
+            }
+        }
+        _ => eprintln!(
+            "Warning: host metadata is missing, can not check if the hosts are compatible."
+        ),
+    }
+    Ok(())
+}
+
+/// Content of a results or statistics file: the metadata header followed
+/// by the data. Files written before the header was introduced only contain
+/// the data, and are read with `metadata` set to `None`.
+#[derive(Serialize, Debug)]
+pub struct ResultFile<T> {
+    pub metadata: Option<RunMetadata>,
+    pub data: T,
+}
+
+#[derive(Deserialize)]
+struct WithMetadata<T> {
+    metadata: Option<RunMetadata>,
+    data: T,
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for ResultFile<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        // Decide on the shape first, so that errors in a file with the
+        // header are reported instead of an error about the legacy shape.
+        let has_header = value
+            .as_object()
+            .is_some_and(|o| o.contains_key("metadata") && o.contains_key("data"));
+        if has_header {
+            let WithMetadata { metadata, data } =
+                serde_json::from_value(value).map_err(D::Error::custom)?;
+            Ok(ResultFile { metadata, data })
+        } else {
+            let data = serde_json::from_value(value).map_err(D::Error::custom)?;
+            Ok(ResultFile {
+                metadata: None,
+                data,
+            })
+        }
+    }
+}
+
+impl<T> ResultFile<T> {
+    pub fn new(metadata: Option<RunMetadata>, data: T) -> Self {
+        ResultFile { metadata, data }
+    }
+}
+
+/// Read a results or statistics file, with or without a metadata header.
+pub fn read_result_file<T: DeserializeOwned>(path: &Path) -> anyhow::Result<ResultFile<T>> {
+    serde_json::from_reader(BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {:?}", path))?,
+    ))
+    .with_context(|| format!("failed to parse {:?}", path))
+}
+
+fn proc_field(content: &str, key: &str) -> Option<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim().to_string())
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn version_output(cmd: &mut Command) -> String {
+    match command_output(cmd) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Err(e) => {
+            eprintln!("Fail to get version from {:?}: {:?}", cmd, e);
+            String::new()
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn bench_dir_rev(bench_dir: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        command_output(Command::new("git").current_dir(bench_dir).args(args))
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    };
+    match git(&["rev-parse", "HEAD"]) {
+        Some(rev) if !rev.is_empty() => {
+            let dirty = git(&["status", "--porcelain", "--", "."]).is_some_and(|s| !s.is_empty());
+            Some(if dirty { rev + "-dirty" } else { rev })
+        }
+        _ => content_hash(bench_dir).map(|h| format!("content:{:016x}", h)),
+    }
+}
+
+/// FNV-1a hash of the relative paths and contents of all files in `dir`,
+/// skipping build outputs.
+fn content_hash(dir: &Path) -> Option<u64> {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET;
+    let mut update = |bytes: &[u8]| {
+        bytes.iter().for_each(|b| {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        })
+    };
+
+    for entry in WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target" && e.file_name() != ".git")
+    {
+        let entry = entry.ok()?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(dir).ok()?;
+            update(relative.to_string_lossy().as_bytes());
+            update(&std::fs::read(entry.path()).ok()?);
+        }
+    }
+    Some(hash)
+}
+
+#[cfg(test)]
+mod test_metadata {
+    use crate::statistics::runtime_stat::RuntimeStatistics;
+
+    use super::{check_compatible, HostInfo, ResultFile, RunMetadata};
+
+    fn metadata(cpu_model: &str, cpu_cores: usize) -> RunMetadata {
+        RunMetadata {
+            host: HostInfo {
+                cpu_model: cpu_model.to_string(),
+                cpu_cores,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Test for ResultFile and check_compatible
+    ///
+    /// Step1: Read files with and without the metadata header, and verify
+    /// the error of a malformed file with the header is about its data.
+    ///
+    /// Step2: Verify results of different hosts are refused unless forced.
+    #[test]
+    fn test_metadata() {
+        let legacy: ResultFile<RuntimeStatistics> =
+            serde_json::from_str(r#"[{"name": "a", "statistic_vec": []}]"#).unwrap();
+        assert!(legacy.metadata.is_none());
+        assert_eq!("a", legacy.data[0].name);
+
+        let a = metadata("Intel(R) Xeon(R) Gold 6248", 8);
+        let file = ResultFile::new(Some(a.clone()), legacy.data);
+        let file: ResultFile<RuntimeStatistics> =
+            serde_json::from_str(&serde_json::to_string(&file).unwrap()).unwrap();
+        assert_eq!(Some(&a), file.metadata.as_ref());
+        assert_eq!(1, file.data.len());
+
+        let err = serde_json::from_str::<ResultFile<RuntimeStatistics>>(
+            r#"{"metadata": null, "data": [{"name": "a"}]}"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("statistic_vec"), "{}", err);
+
+        let b = metadata("AMD EPYC 7742", 8);
+        let c = metadata("Intel(R) Xeon(R) Gold 6248", 16);
+        assert!(check_compatible(Some(&a), Some(&a), false).is_ok());
+        assert!(check_compatible(Some(&a), Some(&b), false).is_err());
+        assert!(check_compatible(Some(&a), Some(&c), false).is_err());
+        assert!(check_compatible(Some(&a), Some(&b), true).is_ok());
+        assert!(check_compatible(Some(&a), None, false).is_ok());
+    }
+}
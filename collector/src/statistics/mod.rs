@@ -1,3 +1,9 @@
 pub mod compile_time_stat;
+pub mod complexity;
+pub mod metadata;
+pub mod paired;
+pub mod pareto;
 pub mod runtime_stat;
+pub mod self_profile;
 pub mod statistic;
+pub mod welch;
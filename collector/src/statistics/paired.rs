@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    benchmark::{
+        interleave::{InterleaveOrder, Side},
+        profile::Profile,
+        scenario::Scenario,
+    },
+    execute::Stats,
+    statistics::{
+        compile_time_stat::CompileTimeBenchResult, runtime_stat::RuntimeResultVec,
+        statistic::t_critical_95,
+    },
+};
+
+/// Differences `b - a` of a metric between the two toolchains, paired by
+/// iteration. As both are measured right after each other, the pairs share
+/// the state of the machine, and the test is much more sensitive than
+/// comparing two separate runs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairedDiff {
+    pub metric: String,
+    pub pairs: usize,
+    pub mean_a: f64,
+    pub mean_b: f64,
+    pub mean_diff: f64,
+    /// Sample standard deviation of the differences.
+    pub std_dev_diff: f64,
+    /// `mean_diff` relative to `mean_a`.
+    pub relative_change: f64,
+    /// t statistic of the paired t-test.
+    pub t_value: f64,
+    /// Whether the difference is significant at the 95% level.
+    pub significant: bool,
+}
+
+/// Paired differences of every metric of a benchmark run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairedComparison {
+    pub name: String,
+    pub feature_set: String,
+    /// Only set for compile-time benchmarks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    pub diffs: Vec<PairedDiff>,
+}
+
+/// Content of `paired_comparison.json` written by `bench_compare`. The
+/// metadata header of the file records the versions of toolchain `a`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairedComparisonFile {
+    pub rustc_a: String,
+    pub rustc_b: String,
+    /// Output of `rustc -vV` of toolchain `b`.
+    pub rustc_b_version: String,
+    /// Output of `cargo -V` of toolchain `b`.
+    pub cargo_b_version: String,
+    pub order: InterleaveOrder,
+    pub seed: u64,
+    /// Order the toolchains were run in, per iteration.
+    pub schedule: Vec<[Side; 2]>,
+    pub comparisons: Vec<PairedComparison>,
+}
+
+/// Paired differences of every metric measured in all `pairs`.
+pub fn paired_diffs(pairs: &[(&Stats, &Stats)]) -> Vec<PairedDiff> {
+    let metrics = pairs
+        .iter()
+        .flat_map(|(a, b)| a.stats.keys().filter(|m| b.stats.contains_key(*m)))
+        .collect::<BTreeSet<_>>();
+
+    metrics
+        .into_iter()
+        .filter_map(|metric| {
+            let values = pairs
+                .iter()
+                .filter_map(|(a, b)| Some((*a.stats.get(metric)?, *b.stats.get(metric)?)))
+                .collect::<Vec<_>>();
+            paired_diff(metric, &values)
+        })
+        .collect()
+}
+
+fn paired_diff(metric: &str, values: &[(f64, f64)]) -> Option<PairedDiff> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean_a = values.iter().map(|(a, _)| a).sum::<f64>() / n;
+    let mean_b = values.iter().map(|(_, b)| b).sum::<f64>() / n;
+    let mean_diff = mean_b - mean_a;
+    let std_dev_diff = if values.len() > 1 {
+        (values
+            .iter()
+            .map(|(a, b)| (b - a - mean_diff).powi(2))
+            .sum::<f64>()
+            / (n - 1.))
+            .sqrt()
+    } else {
+        0.
+    };
+
+    let t_value = if std_dev_diff > 0. {
+        mean_diff / (std_dev_diff / n.sqrt())
+    } else if mean_diff == 0. {
+        0.
+    } else {
+        // Every pair differs by exactly the same amount.
+        mean_diff.signum() * f64::INFINITY
+    };
+
+    Some(PairedDiff {
+        metric: metric.to_string(),
+        pairs: values.len(),
+        mean_a,
+        mean_b,
+        mean_diff,
+        std_dev_diff,
+        relative_change: if mean_a != 0. { mean_diff / mean_a } else { 0. },
+        t_value,
+        significant: values.len() > 1 && t_value.abs() > t_critical_95(values.len() - 1),
+    })
+}
+
+/// Compare compile-time results of the two toolchains, pairing results of
+/// the same benchmark run by iteration.
+pub fn compare_compile_time(
+    a: &[CompileTimeBenchResult],
+    b: &[CompileTimeBenchResult],
+) -> Vec<PairedComparison> {
+    let (a, b) = (group_by_run(a), group_by_run(b));
+
+    a.iter()
+        .filter_map(|(key, a_iterations)| {
+            let (_, b_iterations) = b.iter().find(|(k, _)| k == key)?;
+            let pairs = a_iterations
+                .iter()
+                .filter_map(|(i, a)| Some((*a, *b_iterations.get(i)?)))
+                .collect::<Vec<_>>();
+            let (name, feature_set, profile, scenario, patch) = key.clone();
+            Some(PairedComparison {
+                name,
+                feature_set,
+                profile: Some(profile),
+                scenario: Some(scenario),
+                patch,
+                diffs: paired_diffs(&pairs),
+            })
+        })
+        .collect()
+}
+
+type RunKey = (String, String, Profile, Scenario, Option<String>);
+
+/// Stats of each iteration of every benchmark run, in the order the runs
+/// were measured.
+fn group_by_run(results: &[CompileTimeBenchResult]) -> Vec<(RunKey, BTreeMap<usize, &Stats>)> {
+    let mut groups = Vec::<(RunKey, BTreeMap<usize, &Stats>)>::new();
+    results
+        .iter()
+        .flat_map(|r| r.result_vec.iter())
+        .for_each(|r| {
+            let key = (
+                r.benchmark.clone(),
+                r.feature_set.clone(),
+                r.profile.clone(),
+                r.scenario,
+                r.patch.clone(),
+            );
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, iterations)) => {
+                    iterations.insert(r.iteration, &r.stats);
+                }
+                None => groups.push((key, BTreeMap::from([(r.iteration, &r.stats)]))),
+            }
+        });
+    groups
+}
+
+/// Compare runtime results of the two toolchains, pairing the stats of the
+/// same benchmark by iteration.
+pub fn compare_runtime(a: &RuntimeResultVec, b: &RuntimeResultVec) -> Vec<PairedComparison> {
+    a.0.iter()
+        .filter_map(|a| {
+            let b =
+                b.0.iter()
+                    .find(|b| b.name == a.name && b.feature_set == a.feature_set)?;
+            let pairs = a.stats.iter().zip(b.stats.iter()).collect::<Vec<_>>();
+            Some(PairedComparison {
+                name: a.name.clone(),
+                feature_set: a.feature_set.clone(),
+                profile: None,
+                scenario: None,
+                patch: None,
+                diffs: paired_diffs(&pairs),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_paired {
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+        statistics::compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
+    };
+
+    use super::{compare_compile_time, paired_diffs};
+
+    fn stats(instructions: f64, wall_time: f64) -> Stats {
+        let mut stats = Stats::new();
+        stats.insert(String::from("instructions:u"), instructions);
+        stats.insert(String::from("wall-time"), wall_time);
+        stats
+    }
+
+    /// Test for paired_diffs
+    ///
+    /// Step1: Pair stats where `b` is consistently 10% slower, with a lot of
+    /// noise shared by both toolchains, and instructions unchanged.
+    ///
+    /// Step2: Verify the mean difference and that only the slowdown is significant.
+    #[test]
+    fn test_paired_diffs() {
+        let a = [100., 200., 150., 120., 180.].map(|t| stats(1000., t));
+        let b = [110., 221., 165., 131., 198.].map(|t| stats(1000., t));
+        let pairs = a.iter().zip(b.iter()).collect::<Vec<_>>();
+
+        let diffs = paired_diffs(&pairs);
+        assert_eq!(2, diffs.len());
+        assert_eq!("instructions:u", diffs[0].metric);
+        assert_eq!(0., diffs[0].mean_diff);
+        assert!(!diffs[0].significant);
+
+        assert_eq!("wall-time", diffs[1].metric);
+        assert_eq!(5, diffs[1].pairs);
+        assert!((diffs[1].mean_diff - 15.).abs() < 1e-9);
+        assert!((diffs[1].relative_change - 0.1).abs() < 1e-9);
+        assert!(diffs[1].significant);
+    }
+
+    /// Test for compare_compile_time
+    ///
+    /// Step1: Build results of both toolchains, with an iteration missing in `b`.
+    ///
+    /// Step2: Verify only the iterations measured by both are paired.
+    #[test]
+    fn test_compare_compile_time() {
+        let result = |iteration, wall_time| {
+            CompileTimeResult::new(
+                String::from("helloworld"),
+                iteration,
+                Profile::Debug,
+                Scenario::Full,
+                stats(1000., wall_time),
+            )
+        };
+        let mut a = CompileTimeBenchResult::new(String::from("helloworld"), 3);
+        a.add_result(result(1, 1.));
+        a.add_result(result(2, 2.));
+        a.add_result(result(3, 3.));
+        let mut b = CompileTimeBenchResult::new(String::from("helloworld"), 3);
+        b.add_result(result(1, 2.));
+        b.add_result(result(3, 4.));
+
+        let comparisons = compare_compile_time(&[a], &[b]);
+        assert_eq!(1, comparisons.len());
+        assert_eq!(Some(Profile::Debug), comparisons[0].profile);
+        let wall_time = &comparisons[0].diffs[1];
+        assert_eq!(2, wall_time.pairs);
+        assert_eq!(2., wall_time.mean_a);
+        assert_eq!(3., wall_time.mean_b);
+    }
+}
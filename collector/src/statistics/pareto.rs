@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+/// Measurements of a benchmark at one sweep point, lower is better for all
+/// of them. Measurements the benchmark has no results of are not set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SweepRow {
+    pub benchmark: String,
+    pub point: String,
+    pub compile_time: Option<f64>,
+    pub runtime: Option<f64>,
+    pub binary_size: Option<f64>,
+    /// Whether no other point of the benchmark is at least as good in every
+    /// measurement and better in one.
+    pub pareto_optimal: bool,
+}
+
+impl SweepRow {
+    fn objectives(&self) -> [Option<f64>; 3] {
+        [self.compile_time, self.runtime, self.binary_size]
+    }
+}
+
+/// The points on the Pareto front of a benchmark.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParetoFront {
+    pub benchmark: String,
+    pub points: Vec<String>,
+}
+
+/// Mark the rows on the Pareto front of their benchmark. Only measurements
+/// every point of a benchmark has are compared, and points missing all of
+/// them are never on the front.
+pub fn mark_pareto_fronts(rows: &mut [SweepRow]) -> Vec<ParetoFront> {
+    let mut benchmarks = Vec::<String>::new();
+    rows.iter().for_each(|r| {
+        if !benchmarks.contains(&r.benchmark) {
+            benchmarks.push(r.benchmark.clone());
+        }
+    });
+
+    benchmarks
+        .into_iter()
+        .map(|benchmark| {
+            let indices = (0..rows.len())
+                .filter(|i| rows[*i].benchmark == benchmark)
+                .collect::<Vec<_>>();
+            let dimensions = (0..3)
+                .filter(|d| indices.iter().all(|i| rows[*i].objectives()[*d].is_some()))
+                .collect::<Vec<_>>();
+            let objectives = indices
+                .iter()
+                .map(|i| {
+                    let objectives = rows[*i].objectives();
+                    dimensions
+                        .iter()
+                        .filter_map(|d| objectives[*d])
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            let optimal = if dimensions.is_empty() {
+                vec![false; indices.len()]
+            } else {
+                pareto_front(&objectives)
+            };
+            let mut points = vec![];
+            indices.iter().zip(optimal).for_each(|(i, optimal)| {
+                rows[*i].pareto_optimal = optimal;
+                if optimal {
+                    points.push(rows[*i].point.clone());
+                }
+            });
+            ParetoFront { benchmark, points }
+        })
+        .collect()
+}
+
+/// Whether each of `objectives` is not dominated by any other, where lower
+/// values are better.
+pub fn pareto_front(objectives: &[Vec<f64>]) -> Vec<bool> {
+    objectives
+        .iter()
+        .map(|x| {
+            !objectives.iter().any(|y| {
+                y.iter().zip(x).all(|(y, x)| y <= x) && y.iter().zip(x).any(|(y, x)| y < x)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_pareto {
+    use super::{mark_pareto_fronts, pareto_front, SweepRow};
+
+    fn row(benchmark: &str, point: &str, objectives: [Option<f64>; 3]) -> SweepRow {
+        SweepRow {
+            benchmark: benchmark.to_string(),
+            point: point.to_string(),
+            compile_time: objectives[0],
+            runtime: objectives[1],
+            binary_size: objectives[2],
+            pareto_optimal: false,
+        }
+    }
+
+    /// Test for pareto_front
+    ///
+    /// Step1: Compute the front of points trading off 2 objectives, one of
+    /// them dominated and one duplicated.
+    ///
+    /// Step2: Verify only the dominated point is off the front.
+    #[test]
+    fn test_pareto_front() {
+        let front = pareto_front(&[
+            vec![1., 5.],
+            vec![2., 3.],
+            vec![3., 4.],
+            vec![4., 1.],
+            vec![2., 3.],
+        ]);
+        assert_eq!(vec![true, true, false, true, true], front);
+    }
+
+    /// Test for mark_pareto_fronts
+    ///
+    /// Step1: Mark rows of 2 benchmarks, one without runtime results and one
+    /// with a point missing its binary size.
+    ///
+    /// Step2: Verify each benchmark is compared on the measurements all of
+    /// its points have.
+    #[test]
+    fn test_mark_pareto_fronts() {
+        let mut rows = vec![
+            row("a", "opt-level=2", [Some(10.), None, Some(200.)]),
+            row("a", "opt-level=3", [Some(12.), None, Some(300.)]),
+            row("a", "opt-level=s", [Some(11.), None, Some(100.)]),
+            row("b", "opt-level=2", [Some(10.), Some(5.), Some(200.)]),
+            row("b", "opt-level=3", [Some(12.), Some(4.), None]),
+        ];
+        let fronts = mark_pareto_fronts(&mut rows);
+        assert_eq!(2, fronts.len());
+        assert_eq!(vec!["opt-level=2", "opt-level=s"], fronts[0].points);
+        assert_eq!(vec!["opt-level=2", "opt-level=3"], fronts[1].points);
+        assert!(!rows[1].pareto_optimal);
+        assert!(rows[4].pareto_optimal);
+    }
+}
@@ -2,7 +2,14 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{execute::Stats, statistics::statistic::Statistics};
+use crate::{
+    benchmark::feature_set::default_feature_set_name,
+    execute::Stats,
+    statistics::{
+        complexity::{fit_complexity, ComplexityFit},
+        statistic::Statistics,
+    },
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RuntimeResultVec(pub Vec<RuntimeResult>);
@@ -14,43 +21,92 @@ impl RuntimeResultVec {
             return statistics;
         }
         self.0.iter().for_each(|result| {
-            let mut stat_map = HashMap::<String, Vec<f64>>::new();
-
-            result.stats.iter().for_each(|stat| {
-                stat.stats.iter().for_each(|(label, val)| {
-                    if stat_map.contains_key(label) {
-                        stat_map.get_mut(label).unwrap().push(val.clone());
-                    } else {
-                        stat_map.insert(label.clone(), vec![val.clone()]);
-                    }
-                });
-            });
-
-            let mut statistic_vec = vec![];
-            stat_map.iter().for_each(|(label, vals)| {
-                statistic_vec.push((label.clone(), Statistics::from(vals.clone())));
-            });
             statistics.push(RuntimeStatistic {
                 name: result.name.clone(),
-                statistic_vec,
+                feature_set: result.feature_set.clone(),
+                statistic_vec: calculate_statistic_vec(&result.stats),
+                sub_statistics: result
+                    .sub_results
+                    .iter()
+                    .map(|sub| {
+                        let mut statistic_vec = calculate_statistic_vec(&sub.stats);
+                        if !sub.samples.is_empty() {
+                            statistic_vec.push((
+                                String::from("samples"),
+                                Statistics::from(sub.samples.clone()),
+                            ));
+                        }
+                        (sub.name.clone(), statistic_vec)
+                    })
+                    .collect(),
+                complexity_fits: result.complexity_fits.clone(),
             });
         });
         statistics
     }
 }
 
+fn calculate_statistic_vec(stats: &[Stats]) -> Vec<(String, Statistics)> {
+    let mut stat_map = HashMap::<String, (Vec<f64>, Vec<f64>)>::new();
+
+    stats.iter().for_each(|stat| {
+        stat.stats.iter().for_each(|(label, val)| {
+            let (vals, coverage) = stat_map.entry(label.clone()).or_default();
+            vals.push(val.clone());
+            coverage.push(stat.coverage(label));
+        });
+    });
+
+    let mut statistic_vec = vec![];
+    stat_map.iter().for_each(|(label, (vals, coverage))| {
+        statistic_vec.push((
+            label.clone(),
+            Statistics::from_with_coverage(vals.clone(), coverage),
+        ));
+    });
+    statistic_vec
+}
+
 /// Stats gathered by several iterations of a single benchmark.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RuntimeResult {
+    pub name: String,
+    /// Name of the `FeatureSet` the benchmark was built with.
+    #[serde(default = "default_feature_set_name")]
+    pub feature_set: String,
+    pub stats: Vec<Stats>,
+    /// Stats of the warmup runs, which are not part of `stats`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup: Vec<Stats>,
+    /// Stats of the parts the benchmark consists of, e.g. of each test
+    /// executable, while `stats` covers the whole benchmark.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_results: Vec<RuntimeSubResult>,
+    /// How each metric scales with the input size, fitted to the sub results
+    /// of a `scaling` benchmark.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub complexity_fits: Vec<ComplexityFit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuntimeSubResult {
     pub name: String,
     pub stats: Vec<Stats>,
+    /// Raw samples reported by the benchmark harness, e.g. the time per
+    /// iteration of each criterion sample in ns.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<f64>,
 }
 
 impl RuntimeResult {
     pub fn new(name: String) -> Self {
         RuntimeResult {
             name,
+            feature_set: default_feature_set_name(),
             stats: vec![],
+            warmup: vec![],
+            sub_results: vec![],
+            complexity_fits: vec![],
         }
     }
 
@@ -58,12 +114,92 @@ impl RuntimeResult {
         self.stats.push(stats);
         self
     }
+
+    /// Append `stats` to the sub result called `name`.
+    pub fn append_sub(&mut self, name: &str, stats: Stats) -> &Self {
+        self.sub_result_mut(name).stats.push(stats);
+        self
+    }
+
+    /// Append raw `samples` to the sub result called `name`.
+    pub fn extend_sub_samples(&mut self, name: &str, samples: Vec<f64>) -> &Self {
+        self.sub_result_mut(name).samples.extend(samples);
+        self
+    }
+
+    /// Append the stats and sub results of another run of the same benchmark.
+    pub fn merge(&mut self, other: RuntimeResult) -> &Self {
+        self.stats.extend(other.stats);
+        for sub in other.sub_results {
+            let merged = self.sub_result_mut(&sub.name);
+            merged.stats.extend(sub.stats);
+            merged.samples.extend(sub.samples);
+        }
+        self
+    }
+
+    /// Fit the mean of each metric in the sub results of `sizes`, given as
+    /// pairs of the input size and the name of its sub result.
+    pub fn fit_complexities(&mut self, sizes: &[(u64, String)]) {
+        let subs = sizes
+            .iter()
+            .filter_map(|(n, name)| {
+                self.sub_results
+                    .iter()
+                    .find(|sub| &sub.name == name && !sub.stats.is_empty())
+                    .map(|sub| (*n as f64, sub))
+            })
+            .collect::<Vec<_>>();
+        let mut metrics = match subs.first() {
+            Some((_, sub)) => sub.stats[0].stats.keys().cloned().collect::<Vec<_>>(),
+            None => return,
+        };
+        metrics.sort();
+
+        self.complexity_fits = metrics
+            .iter()
+            .filter_map(|metric| {
+                let points = subs
+                    .iter()
+                    .map(|(n, sub)| {
+                        let vals = sub
+                            .stats
+                            .iter()
+                            .map(|s| s.stats.get(metric).copied())
+                            .collect::<Option<Vec<_>>>()?;
+                        Some((*n, vals.iter().sum::<f64>() / vals.len() as f64))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                fit_complexity(metric, &points)
+            })
+            .collect();
+    }
+
+    fn sub_result_mut(&mut self, name: &str) -> &mut RuntimeSubResult {
+        match self.sub_results.iter().position(|sub| sub.name == name) {
+            Some(i) => &mut self.sub_results[i],
+            None => {
+                self.sub_results.push(RuntimeSubResult {
+                    name: name.to_string(),
+                    stats: vec![],
+                    samples: vec![],
+                });
+                self.sub_results.last_mut().unwrap()
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct RuntimeStatistic {
     pub name: String,
+    #[serde(default = "default_feature_set_name")]
+    pub feature_set: String,
     pub statistic_vec: Vec<(String, Statistics)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_statistics: Vec<(String, Vec<(String, Statistics)>)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub complexity_fits: Vec<ComplexityFit>,
 }
 
 pub type RuntimeStatistics = Vec<RuntimeStatistic>;
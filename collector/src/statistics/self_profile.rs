@@ -0,0 +1,271 @@
+use std::{collections::HashMap, ops::AddAssign};
+
+use serde::{Deserialize, Serialize};
+
+use super::compile_time_stat::CompileTimeResultSet;
+
+/// Time rustc spent in each query and activity (e.g. `typeck`,
+/// `mir_borrowck`, `LLVM_module_codegen`, `link_crate`) of a compilation,
+/// summarized from `-Zself-profile` data.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelfProfile {
+    pub queries: Vec<QueryData>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryData {
+    pub label: String,
+    /// Seconds spent in the query itself, excluding the queries it called.
+    pub self_time: f64,
+    /// Seconds spent loading the results from the incremental cache.
+    pub incremental_load_time: f64,
+    pub invocation_count: u64,
+    pub number_of_cache_hits: u64,
+}
+
+/// Output of `summarize summarize --json`.
+#[derive(Deserialize)]
+struct Summary {
+    query_data: Vec<SummaryQuery>,
+}
+
+#[derive(Deserialize)]
+struct SummaryQuery {
+    label: String,
+    self_time: SummaryDuration,
+    #[serde(default)]
+    incremental_load_time: SummaryDuration,
+    #[serde(default)]
+    invocation_count: u64,
+    #[serde(default)]
+    number_of_cache_hits: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct SummaryDuration {
+    secs: u64,
+    nanos: u32,
+}
+
+impl SummaryDuration {
+    fn as_secs_f64(&self) -> f64 {
+        self.secs as f64 + self.nanos as f64 / 1e9
+    }
+}
+
+impl SelfProfile {
+    /// Parse the json printed by rustc-fake as `!self-profile-output:`.
+    pub fn from_summarize_json(json: &str) -> serde_json::Result<Self> {
+        let summary: Summary = serde_json::from_str(json)?;
+        Ok(SelfProfile {
+            queries: summary
+                .query_data
+                .into_iter()
+                .map(|q| QueryData {
+                    label: q.label,
+                    self_time: q.self_time.as_secs_f64(),
+                    incremental_load_time: q.incremental_load_time.as_secs_f64(),
+                    invocation_count: q.invocation_count,
+                    number_of_cache_hits: q.number_of_cache_hits,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn get(&self, label: &str) -> Option<&QueryData> {
+        self.queries.iter().find(|q| q.label == label)
+    }
+}
+
+/// Sum the profiles of several crates, e.g. of a package benchmark.
+impl AddAssign for SelfProfile {
+    fn add_assign(&mut self, rhs: Self) {
+        for query in rhs.queries {
+            match self.queries.iter_mut().find(|q| q.label == query.label) {
+                Some(q) => {
+                    q.self_time += query.self_time;
+                    q.incremental_load_time += query.incremental_load_time;
+                    q.invocation_count += query.invocation_count;
+                    q.number_of_cache_hits += query.number_of_cache_hits;
+                }
+                None => self.queries.push(query),
+            }
+        }
+    }
+}
+
+/// Change of the self time of a query between 2 result sets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueryChange {
+    pub query: String,
+    /// Mean self time per run in the first result set, in seconds.
+    pub self_time_a: f64,
+    /// Mean self time per run in the second result set, in seconds.
+    pub self_time_b: f64,
+    /// Change rate of `self_time_a` on `self_time_b` in percent, `None` if
+    /// the query did not run in the second result set.
+    pub change_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelfProfileComparison {
+    /// Changes summed over all runs found in both result sets.
+    pub total: Vec<QueryChange>,
+    /// Changes of each run, labeled by benchmark, profile and scenario.
+    pub runs: Vec<(String, Vec<QueryChange>)>,
+}
+
+/// Compare the self profiles of the runs found in both `a` and `b`. Changes
+/// are sorted by the seconds regressed, the largest regression first.
+pub fn compare_self_profiles(
+    a: &CompileTimeResultSet,
+    b: &CompileTimeResultSet,
+) -> SelfProfileComparison {
+    let (runs_a, runs_b) = (mean_self_times(a), mean_self_times(b));
+
+    let mut labels = runs_a
+        .keys()
+        .filter(|l| runs_b.contains_key(*l))
+        .collect::<Vec<_>>();
+    labels.sort();
+
+    let mut total_a = HashMap::new();
+    let mut total_b = HashMap::new();
+    let runs = labels
+        .into_iter()
+        .map(|label| {
+            let (a, b) = (&runs_a[label], &runs_b[label]);
+            a.iter()
+                .for_each(|(q, t)| *total_a.entry(q.clone()).or_insert(0.) += t);
+            b.iter()
+                .for_each(|(q, t)| *total_b.entry(q.clone()).or_insert(0.) += t);
+            (label.clone(), query_changes(a, b))
+        })
+        .collect();
+
+    SelfProfileComparison {
+        total: query_changes(&total_a, &total_b),
+        runs,
+    }
+}
+
+/// Mean self time of each query over the iterations of each run.
+fn mean_self_times(set: &CompileTimeResultSet) -> HashMap<String, HashMap<String, f64>> {
+    let mut sums = HashMap::<String, (usize, HashMap<String, f64>)>::new();
+    for result in set.get_ref_results() {
+        for r in &result.result_vec {
+            let profile = match &r.stats.self_profile {
+                Some(profile) => profile,
+                None => continue,
+            };
+            let (n, times) = sums.entry(r.label()).or_default();
+            *n += 1;
+            profile
+                .queries
+                .iter()
+                .for_each(|q| *times.entry(q.label.clone()).or_insert(0.) += q.self_time);
+        }
+    }
+
+    sums.into_iter()
+        .map(|(label, (n, times))| {
+            (
+                label,
+                times.into_iter().map(|(q, t)| (q, t / n as f64)).collect(),
+            )
+        })
+        .collect()
+}
+
+fn query_changes(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> Vec<QueryChange> {
+    let mut queries = a.keys().chain(b.keys()).collect::<Vec<_>>();
+    queries.sort();
+    queries.dedup();
+
+    let mut changes = queries
+        .into_iter()
+        .map(|q| {
+            let self_time_a = a.get(q).copied().unwrap_or(0.);
+            let self_time_b = b.get(q).copied().unwrap_or(0.);
+            QueryChange {
+                query: q.clone(),
+                self_time_a,
+                self_time_b,
+                change_rate: if self_time_b > 0. {
+                    Some((self_time_a - self_time_b) / self_time_b * 100.)
+                } else {
+                    None
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+    changes.sort_by(|x, y| {
+        (y.self_time_a - y.self_time_b)
+            .partial_cmp(&(x.self_time_a - x.self_time_b))
+            .unwrap()
+    });
+    changes
+}
+
+#[cfg(test)]
+mod test_self_profile {
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+        statistics::compile_time_stat::{
+            CompileTimeBenchResult, CompileTimeResult, CompileTimeResultSet,
+        },
+    };
+
+    use super::{compare_self_profiles, SelfProfile};
+
+    fn result_set(typeck: u32, codegen: u32) -> CompileTimeResultSet {
+        let json = format!(
+            r#"{{"query_data": [
+                {{"label": "typeck", "time": {{"secs": 1, "nanos": 0}}, "self_time": {{"secs": 0, "nanos": {}}}, "invocation_count": 10}},
+                {{"label": "LLVM_module_codegen", "self_time": {{"secs": 1, "nanos": {}}}}}
+            ], "total_time": {{"secs": 2, "nanos": 0}}}}"#,
+            typeck, codegen
+        );
+        let mut stats = Stats::new();
+        stats.insert(String::from("wall-time"), 2.);
+        stats.self_profile = Some(SelfProfile::from_summarize_json(&json).unwrap());
+
+        let mut bench_result = CompileTimeBenchResult::new(String::from("regex"), 1);
+        bench_result.add_result(CompileTimeResult::new(
+            String::from("regex"),
+            1,
+            Profile::Debug,
+            Scenario::Full,
+            stats,
+        ));
+        CompileTimeResultSet::new(String::from("rustc"), vec![bench_result])
+    }
+
+    /// Test for SelfProfile
+    ///
+    /// Step1: Parse summarize output and sum profiles.
+    ///
+    /// Step2: Compare self profiles of 2 result sets, the regressed query first.
+    #[test]
+    fn test_self_profile() {
+        let profile = SelfProfile::from_summarize_json(
+            r#"{"query_data": [{"label": "typeck", "self_time": {"secs": 0, "nanos": 500000000}, "invocation_count": 3}]}"#,
+        )
+        .unwrap();
+        let mut sum = profile.clone();
+        sum += profile;
+        assert_eq!(1., sum.get("typeck").unwrap().self_time);
+        assert_eq!(6, sum.get("typeck").unwrap().invocation_count);
+
+        let cmp = compare_self_profiles(
+            &result_set(600_000_000, 0),
+            &result_set(300_000_000, 100_000_000),
+        );
+        assert_eq!(1, cmp.runs.len());
+        assert_eq!("regex debug full", cmp.runs[0].0);
+        assert_eq!("typeck", cmp.total[0].query);
+        assert!((cmp.total[0].change_rate.unwrap() - 100.).abs() < 1e-6);
+        assert_eq!("LLVM_module_codegen", cmp.total[1].query);
+    }
+}
@@ -12,6 +12,14 @@ pub struct Statistics {
     pub q1: f64,
     /// 第三四分位点
     pub q3: f64,
+    /// Lowest percentage of the time a multiplexed perf counter was active
+    /// in any sample. Not set if no sample was multiplexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_coverage: Option<f64>,
+    /// Mean of the samples weighted by their coverage, which down-weights
+    /// samples of multiplexed counters. Not set if no sample was multiplexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage_weighted_mean: Option<f64>,
 }
 
 impl Statistics {
@@ -25,6 +33,8 @@ impl Statistics {
             standard_deviation: 0.0,
             q1: 0.0,
             q3: 0.0,
+            min_coverage: None,
+            coverage_weighted_mean: None,
         }
     }
 
@@ -71,8 +81,54 @@ impl Statistics {
             standard_deviation,
             q1: quartile(&data, 0.25),
             q3: quartile(&data, 0.75),
+            min_coverage: None,
+            coverage_weighted_mean: None,
         }
     }
+
+    /// Statistics of `data`, where `coverage[i]` is the percentage of the
+    /// time `data[i]` was measured by a multiplexed counter.
+    pub fn from_with_coverage(data: Vec<f64>, coverage: &[f64]) -> Self {
+        let mut statistics = Self::from(data.clone());
+        let min_coverage = coverage.iter().copied().fold(100., f64::min);
+        if min_coverage < 100. {
+            let weights = coverage.iter().sum::<f64>();
+            statistics.min_coverage = Some(min_coverage);
+            statistics.coverage_weighted_mean =
+                Some(data.iter().zip(coverage).map(|(x, w)| x * w).sum::<f64>() / weights);
+        }
+        statistics
+    }
+
+    /// Half width of the 95% confidence interval of the mean, relative to the
+    /// mean. `n` is the number of values these statistics were computed from.
+    pub fn relative_ci95(&self, n: usize) -> f64 {
+        if n < 2 || self.algebraic_mean == 0.0 {
+            return f64::INFINITY;
+        }
+        // `variance` is the population variance, correct it to the sample one.
+        let sample_sd = (self.variance * n as f64 / (n - 1) as f64).sqrt();
+        t_critical_95(n - 1) * sample_sd / (n as f64).sqrt() / self.algebraic_mean.abs()
+    }
+}
+
+/// The 97.5% quantile of Student's t-distribution with `df` degrees
+/// of freedom.
+pub fn t_critical_95(df: usize) -> f64 {
+    const T_TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => T_TABLE[df - 1],
+        // Round down to the nearest tabulated df, which errs on the wide side.
+        31..=40 => 2.042,
+        41..=60 => 2.021,
+        61..=120 => 2.000,
+        _ => 1.980,
+    }
 }
 
 /// calculate quartile_1 and quartile_3
@@ -89,3 +145,29 @@ fn quartile(data: &Vec<f64>, percentile: f64) -> f64 {
         lower + (upper - lower) * (percentile * (n - 1) as f64 - index as f64)
     }
 }
+
+#[cfg(test)]
+mod test_statistic {
+    use super::Statistics;
+
+    /// Test for Statistics::from_with_coverage
+    ///
+    /// Step1: Compute statistics of samples of which one was multiplexed.
+    ///
+    /// Step2: Verify the lowest coverage and the coverage weighted mean.
+    ///
+    /// Step3: Verify neither is set if every sample was measured all the time.
+    #[test]
+    fn test_from_with_coverage() {
+        let statistics = Statistics::from_with_coverage(vec![10., 20., 40.], &[100., 50., 100.]);
+        assert_eq!(Some(50.), statistics.min_coverage);
+        // (10 * 100 + 20 * 50 + 40 * 100) / 250
+        assert_eq!(Some(24.), statistics.coverage_weighted_mean);
+        assert!((statistics.algebraic_mean - 70. / 3.).abs() < 1e-9);
+
+        let statistics = Statistics::from_with_coverage(vec![10., 20.], &[100., 100.]);
+        assert_eq!(None, statistics.min_coverage);
+        assert_eq!(None, statistics.coverage_weighted_mean);
+        assert_eq!(15., statistics.algebraic_mean);
+    }
+}
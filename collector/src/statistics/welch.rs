@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::statistics::statistic::t_critical_95;
+
+/// Welch's t-test of whether the means of two independent samples differ,
+/// without assuming they have the same variance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WelchTest {
+    pub mean_a: f64,
+    pub mean_b: f64,
+    /// t statistic of `mean_b - mean_a`.
+    pub t_value: f64,
+    /// Welch–Satterthwaite degrees of freedom.
+    pub df: f64,
+    /// Whether the difference is significant at the 95% level.
+    pub significant: bool,
+}
+
+impl WelchTest {
+    /// At least 2 values are needed in each sample.
+    pub fn new(a: &[f64], b: &[f64]) -> Option<Self> {
+        if a.len() < 2 || b.len() < 2 {
+            return None;
+        }
+        let (mean_a, var_a) = mean_variance(a);
+        let (mean_b, var_b) = mean_variance(b);
+        let (se_a, se_b) = (var_a / a.len() as f64, var_b / b.len() as f64);
+        let se = (se_a + se_b).sqrt();
+        let diff = mean_b - mean_a;
+
+        if se == 0. {
+            // Both samples are constant.
+            return Some(WelchTest {
+                mean_a,
+                mean_b,
+                t_value: if diff == 0. {
+                    0.
+                } else {
+                    diff.signum() * f64::INFINITY
+                },
+                df: (a.len() + b.len() - 2) as f64,
+                significant: diff != 0.,
+            });
+        }
+
+        let t_value = diff / se;
+        let df = (se_a + se_b).powi(2)
+            / (se_a.powi(2) / (a.len() - 1) as f64 + se_b.powi(2) / (b.len() - 1) as f64);
+        Some(WelchTest {
+            mean_a,
+            mean_b,
+            t_value,
+            df,
+            // Round down to the tabulated df, which errs on the insignificant side.
+            significant: t_value.abs() > t_critical_95((df.floor() as usize).max(1)),
+        })
+    }
+
+    /// `mean_b` relative to `mean_a`, e.g. `0.05` if `b` is 5% larger.
+    pub fn relative_change(&self) -> f64 {
+        if self.mean_a == 0. {
+            0.
+        } else {
+            (self.mean_b - self.mean_a) / self.mean_a
+        }
+    }
+}
+
+/// Mean and sample variance.
+fn mean_variance(data: &[f64]) -> (f64, f64) {
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    let variance = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.);
+    (mean, variance)
+}
+
+#[cfg(test)]
+mod test_welch {
+    use super::WelchTest;
+
+    /// Test for WelchTest
+    ///
+    /// Step1: Test samples with a clear shift, overlapping samples and
+    /// constant samples.
+    ///
+    /// Step2: Verify the t statistic, degrees of freedom and significance.
+    #[test]
+    fn test_welch_test() {
+        let a = [100., 102., 98., 101., 99.];
+        let b = [110., 112., 108., 111., 109.];
+        let test = WelchTest::new(&a, &b).unwrap();
+        assert!((test.t_value - 10. / 1.).abs() < 1e-9);
+        assert!((test.df - 8.).abs() < 1e-9);
+        assert!(test.significant);
+        assert!((test.relative_change() - 0.1).abs() < 1e-9);
+
+        let c = [99., 103., 97., 102., 100.];
+        assert!(!WelchTest::new(&a, &c).unwrap().significant);
+
+        let constant = WelchTest::new(&[5., 5.], &[6., 6.]).unwrap();
+        assert!(constant.significant);
+        assert!(WelchTest::new(&a, &[1.]).is_none());
+    }
+}
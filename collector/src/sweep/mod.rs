@@ -0,0 +1,283 @@
+use std::{
+    env,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+
+use crate::{
+    benchmark::{
+        feature_set::DEFAULT_FEATURE_SET,
+        filter::BenchmarkFilter,
+        profile::Profile,
+        run_options::RunOptions,
+        scenario::Scenario,
+        sweep::{SweepMatrix, SweepPoint},
+    },
+    compile_time::{
+        bench_compile_time,
+        binary_size::{bench_binary_size, BINARY_SIZE_LABEL},
+    },
+    runtime::bench_runtime,
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResultSet},
+        metadata::{ResultFile, RunMetadata},
+        pareto::{mark_pareto_fronts, ParetoFront, SweepRow},
+        runtime_stat::RuntimeResultVec,
+    },
+    toolchain::{Bencher, LocalToolchain, PerfTool, ResultWriter},
+};
+
+/// Content of `sweep_results.json`.
+#[derive(Serialize)]
+pub struct SweepResult {
+    pub points: Vec<SweepPoint>,
+    pub compile_time_metric: String,
+    pub runtime_metric: String,
+    /// One row per benchmark and point.
+    pub rows: Vec<SweepRow>,
+    pub pareto_fronts: Vec<ParetoFront>,
+}
+
+/// Where the benchmarks are and what is measured at each sweep point.
+pub struct SweepOptions<'a> {
+    pub perf_tool: &'a PerfTool,
+    pub event_filter_file: &'a PathBuf,
+    /// The profile compile-time and binary-size benchmarks are built with.
+    pub profile: &'a Profile,
+    pub compile_time_dir: &'a Path,
+    pub runtime_dir: &'a Path,
+    pub filter: &'a BenchmarkFilter,
+    pub iterations: usize,
+    pub compile_time_metric: &'a str,
+    pub runtime_metric: &'a str,
+}
+
+/// Measure compile time, runtime and binary size at every point of `matrix`.
+/// The results of each point are written to `point-<i>` in `out_dir`, and
+/// the table of all of them to `sweep_results.json` and `sweep_table.csv`.
+pub fn bench_sweep(
+    ltc: &LocalToolchain,
+    matrix: &SweepMatrix,
+    options: &SweepOptions,
+    out_dir: &Path,
+) -> anyhow::Result<SweepResult> {
+    match options.perf_tool.get_bencher() {
+        Bencher::PerfStat | Bencher::Rusage => (),
+        bencher => bail!("{:?} is not supported to sweep codegen settings.", bencher),
+    }
+    if matches!(options.profile, Profile::Check | Profile::Doc) {
+        bail!("{:?} produces no binary to sweep.", options.profile);
+    }
+    let started_at = SystemTime::now();
+
+    let points = matrix.points();
+    let mut rows = vec![];
+    for (i, point) in points.iter().enumerate() {
+        println!("sweep point {}/{}: {}", i + 1, points.len(), point.name);
+
+        // Inherited by every cargo the benchmarks are built with.
+        let cargo_env = point.cargo_env();
+        cargo_env
+            .iter()
+            .for_each(|(key, value)| env::set_var(key, value));
+        let point_rows = measure_point(
+            ltc,
+            point,
+            options,
+            &out_dir.join(format!("point-{:02}", i)),
+        );
+        cargo_env.iter().for_each(|(key, _)| env::remove_var(key));
+
+        match point_rows {
+            Ok(point_rows) => rows.extend(point_rows),
+            Err(err) => eprintln!("Fail to measure sweep point {}: {:?}", point.name, err),
+        }
+    }
+
+    // Rows of a benchmark next to each other.
+    let mut benchmarks = Vec::<String>::new();
+    rows.iter().for_each(|r: &SweepRow| {
+        if !benchmarks.contains(&r.benchmark) {
+            benchmarks.push(r.benchmark.clone());
+        }
+    });
+    rows.sort_by_key(|r| benchmarks.iter().position(|b| *b == r.benchmark));
+    let pareto_fronts = mark_pareto_fronts(&mut rows);
+
+    let result = SweepResult {
+        points,
+        compile_time_metric: options.compile_time_metric.to_string(),
+        runtime_metric: options.runtime_metric.to_string(),
+        rows,
+        pareto_fronts,
+    };
+    let metadata =
+        RunMetadata::collect(&ltc.rustc, &ltc.cargo, options.compile_time_dir, started_at);
+    ResultWriter::new(out_dir.to_path_buf(), PathBuf::from("sweep_results.json"))?.write(
+        serde_json::to_string(&ResultFile::new(Some(metadata), &result))?,
+    )?;
+    write_table(&out_dir.join("sweep_table.csv"), &result.rows)?;
+    Ok(result)
+}
+
+/// Measure every benchmark with the settings of `point` applied.
+fn measure_point(
+    ltc: &LocalToolchain,
+    point: &SweepPoint,
+    options: &SweepOptions,
+    point_dir: &Path,
+) -> anyhow::Result<Vec<SweepRow>> {
+    let started_at = SystemTime::now();
+    let compile_time_dir = point_dir.join("compile_time");
+    let run_options = RunOptions {
+        perf_tool: options.perf_tool,
+        event_filter_file: options.event_filter_file,
+        iterations: Some(options.iterations),
+        adaptive: None,
+        flame_graph_path: point_dir,
+        flamegraph_flag: 0,
+        out_dir: &compile_time_dir,
+    };
+    let compile_time = bench_compile_time(
+        ltc,
+        &run_options,
+        std::slice::from_ref(options.profile),
+        &[Scenario::Full],
+        options.compile_time_dir.to_path_buf(),
+        options.filter,
+        false,
+    )?;
+    let binary_size = bench_binary_size(
+        ltc,
+        std::slice::from_ref(options.profile),
+        options.compile_time_dir.to_path_buf(),
+        options.filter,
+    )?;
+    let runtime_dir = point_dir.join("runtime");
+    let runtime = bench_runtime(
+        ltc,
+        options.runtime_dir.to_path_buf(),
+        options.filter,
+        &RunOptions {
+            out_dir: &runtime_dir,
+            ..run_options
+        },
+        None,
+        false,
+    )?;
+
+    let metadata =
+        RunMetadata::collect(&ltc.rustc, &ltc.cargo, options.compile_time_dir, started_at);
+    ResultWriter::new(compile_time_dir, PathBuf::from("compile_time_results.json"))?.write(
+        serde_json::to_string(&ResultFile::new(
+            Some(metadata.clone()),
+            &CompileTimeResultSet::new(point.name.clone(), compile_time.clone()),
+        ))?,
+    )?;
+    ResultWriter::new(
+        point_dir.to_path_buf(),
+        PathBuf::from("compiled_binary_size.json"),
+    )?
+    .write(serde_json::to_string(&ResultFile::new(
+        Some(metadata.clone()),
+        &binary_size,
+    ))?)?;
+    ResultWriter::new(runtime_dir, PathBuf::from("runtime_results.json"))?.write(
+        serde_json::to_string(&ResultFile::new(
+            Some(RunMetadata {
+                bench_dir: options.runtime_dir.to_path_buf(),
+                ..metadata
+            }),
+            &runtime,
+        ))?,
+    )?;
+
+    let mut benchmarks = compile_time
+        .iter()
+        .map(|r| r.benchmark.clone())
+        .collect::<Vec<_>>();
+    runtime.0.iter().for_each(|r| {
+        if !benchmarks.contains(&r.name) {
+            benchmarks.push(r.name.clone());
+        }
+    });
+
+    Ok(benchmarks
+        .into_iter()
+        .map(|benchmark| SweepRow {
+            compile_time: compile_time_mean(
+                &compile_time,
+                &benchmark,
+                options.profile,
+                options.compile_time_metric,
+            ),
+            runtime: runtime_mean(&runtime, &benchmark, options.runtime_metric),
+            binary_size: compile_time_mean(
+                &binary_size,
+                &benchmark,
+                options.profile,
+                BINARY_SIZE_LABEL,
+            ),
+            benchmark,
+            point: point.name.clone(),
+            pareto_optimal: false,
+        })
+        .collect())
+}
+
+/// Mean of `metric` of a full build of `benchmark` with the default features.
+fn compile_time_mean(
+    results: &[CompileTimeBenchResult],
+    benchmark: &str,
+    profile: &Profile,
+    metric: &str,
+) -> Option<f64> {
+    mean(
+        results
+            .iter()
+            .filter(|r| r.benchmark == benchmark)
+            .flat_map(|r| r.result_vec.iter())
+            .filter(|r| {
+                r.profile == *profile
+                    && r.scenario == Scenario::Full
+                    && r.feature_set == DEFAULT_FEATURE_SET
+            })
+            .filter_map(|r| r.stats.stats.get(metric).copied()),
+    )
+}
+
+/// Mean of `metric` of `benchmark` with the default features.
+fn runtime_mean(results: &RuntimeResultVec, benchmark: &str, metric: &str) -> Option<f64> {
+    mean(
+        results
+            .0
+            .iter()
+            .filter(|r| r.name == benchmark && r.feature_set == DEFAULT_FEATURE_SET)
+            .flat_map(|r| r.stats.iter())
+            .filter_map(|s| s.stats.get(metric).copied()),
+    )
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let values = values.collect::<Vec<_>>();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn write_table(path: &Path, rows: &[SweepRow]) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("Fail to create {:?}", path))?;
+    let mut csv_writer = csv::Writer::from_writer(BufWriter::new(file));
+    for row in rows {
+        csv_writer.serialize(row)?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
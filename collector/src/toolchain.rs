@@ -5,16 +5,25 @@ use std::{
     path::{Path, PathBuf},
     process::Command,
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{Context, Ok};
 use log::debug;
 
-use crate::benchmark::profile::{Profile, Profiles};
+use crate::benchmark::{
+    adaptive::AdaptiveIterations,
+    filter::BenchmarkFilter,
+    interleave::InterleaveOrder,
+    profile::{Profile, Profiles},
+    scenario::Scenarios,
+};
+use crate::execute::DEFAULT_PERF_EVENTS;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Compiler<'a> {
     pub rustc: &'a Path,
+    pub rustdoc: Option<&'a Path>,
     pub cargo: &'a Path,
     pub is_nightly: bool,
 }
@@ -23,6 +32,7 @@ impl<'a> Compiler<'a> {
     pub fn from_toolchain(tc: &'a LocalToolchain) -> Compiler<'a> {
         Compiler {
             rustc: &tc.rustc,
+            rustdoc: tc.rustdoc.as_deref(),
             cargo: &tc.cargo,
             is_nightly: true,
         }
@@ -32,6 +42,7 @@ impl<'a> Compiler<'a> {
 #[derive(Debug)]
 pub struct LocalToolchain {
     pub rustc: PathBuf,
+    pub rustdoc: Option<PathBuf>,
     pub cargo: PathBuf,
     pub flame_graph: PathBuf,
     pub id: String,
@@ -42,6 +53,9 @@ impl Display for LocalToolchain {
         let rustc_s = String::from("rustc: ") + self.rustc.to_str().unwrap();
         let cargo_s = String::from("cargo: ") + self.cargo.to_str().unwrap();
         f.write_str((rustc_s + "\n" + &cargo_s).as_str())?;
+        if let Some(rustdoc) = &self.rustdoc {
+            f.write_str(format!("\nrustdoc: {}", rustdoc.display()).as_str())?;
+        }
         std::fmt::Result::Ok(())
     }
 }
@@ -53,7 +67,9 @@ impl Display for LocalToolchain {
 /// - `cargo`: if one is given, check if it is acceptable. Otherwise, look
 ///   for the nightly Cargo via `rustup`.
 pub fn get_local_toolchain(
+    profiles: &[Profile],
     rustc: &str,
+    rustdoc: Option<&Path>,
     cargo: Option<&Path>,
     id: Option<&str>,
     id_suffix: &str,
@@ -134,6 +150,24 @@ pub fn get_local_toolchain(
         (rustc, id)
     };
 
+    let rustdoc =
+        if let Some(rustdoc) = &rustdoc {
+            Some(rustdoc.canonicalize().with_context(|| {
+                format!("failed to canonicalize rustdoc executable {:?}", rustdoc)
+            })?)
+        } else if profiles.contains(&Profile::Doc) {
+            // We need a `rustdoc`. Look for one next to `rustc`.
+            if let Result::Ok(rustdoc) = rustc.with_file_name("rustdoc").canonicalize() {
+                debug!("found rustdoc: {:?}", &rustdoc);
+                Some(rustdoc)
+            } else {
+                anyhow::bail!("'Doc' is in the profiles list, but we could not find a rustdoc");
+            }
+        } else {
+            // No `rustdoc` provided, but none needed.
+            None
+        };
+
     let cargo = if let Some(cargo) = &cargo {
         cargo
             .canonicalize()
@@ -172,6 +206,7 @@ pub fn get_local_toolchain(
 
     Ok(LocalToolchain {
         rustc,
+        rustdoc,
         cargo,
         flame_graph,
         id,
@@ -192,6 +227,10 @@ pub struct LocalOptions {
     // toolchain name, and `PathBuf` doesn't work well for the latter.
     pub rustc: String,
 
+    /// The path to the local rustdoc to measure
+    #[clap(long, parse(from_os_str))]
+    pub rustdoc: Option<PathBuf>,
+
     /// Identifier to associate benchmark results with
     #[clap(long)]
     pub id: Option<String>,
@@ -199,13 +238,144 @@ pub struct LocalOptions {
     /// The path to the local Cargo to use
     #[clap(long, parse(from_os_str))]
     pub cargo: Option<PathBuf>,
-    // /// Exclude all benchmarks matching a prefix in this comma-separated list
-    // #[clap(long)]
-    // pub exclude: Option<String>,
 
-    // /// Include only benchmarks matching a prefix in this comma-separated list
-    // #[clap(long)]
-    // pub include: Option<String>,
+    #[clap(flatten)]
+    pub filter: FilterOptions,
+}
+
+impl LocalOptions {
+    pub fn benchmark_filter(&self) -> BenchmarkFilter {
+        self.filter.benchmark_filter()
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FilterOptions {
+    /// Exclude all benchmarks matching a prefix or glob in this comma-separated list
+    #[clap(long)]
+    pub exclude: Option<String>,
+
+    /// Include only benchmarks matching a prefix or glob in this comma-separated list
+    #[clap(long)]
+    pub include: Option<String>,
+
+    /// Include only benchmarks with any of the tags in this comma-separated list
+    #[clap(long)]
+    pub tags: Option<String>,
+
+    /// Exclude all benchmarks with any of the tags in this comma-separated list
+    #[clap(long = "exclude-tags")]
+    pub exclude_tags: Option<String>,
+}
+
+impl FilterOptions {
+    pub fn benchmark_filter(&self) -> BenchmarkFilter {
+        BenchmarkFilter::new(
+            self.include.as_deref(),
+            self.exclude.as_deref(),
+            self.tags.as_deref(),
+            self.exclude_tags.as_deref(),
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AdaptiveOptions {
+    /// Iterate each benchmark until the relative 95% confidence interval of
+    /// this metric, e.g. `instructions:u` or `wall-time`, is below
+    /// `--target-ci`. `--iterations` is ignored if set.
+    #[clap(long = "adaptive-metric")]
+    pub adaptive_metric: Option<String>,
+
+    /// Target half width of the confidence interval relative to the mean,
+    /// e.g. 0.01 for ±1%
+    #[clap(long = "target-ci", default_value = "0.01")]
+    pub target_ci: f64,
+
+    /// The least iterations to do for each benchmark in adaptive mode
+    #[clap(long = "min-iterations", default_value = "3")]
+    pub min_iterations: usize,
+
+    /// The most iterations to do for each benchmark in adaptive mode
+    #[clap(long = "max-iterations", default_value = "30")]
+    pub max_iterations: usize,
+
+    /// Time in seconds the whole suite may take in adaptive mode. Only
+    /// `--min-iterations` are done once it is spent.
+    #[clap(long = "time-budget")]
+    pub time_budget: Option<u64>,
+}
+
+impl AdaptiveOptions {
+    pub fn adaptive_iterations(&self) -> Option<AdaptiveIterations> {
+        self.adaptive_metric.as_ref().map(|metric| {
+            AdaptiveIterations::new(
+                metric.clone(),
+                self.target_ci,
+                self.min_iterations,
+                self.max_iterations,
+                self.time_budget.map(Duration::from_secs),
+            )
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct EventOptions {
+    /// Named set of events counted by `perf-stat`: `basic`, `cache`,
+    /// `branch` or `frontend`
+    #[clap(long = "event-profile", default_value = "basic")]
+    pub event_profile: EventProfile,
+
+    /// Events counted by `perf-stat`, separated by ','. Overrides
+    /// `--event-profile`.
+    #[clap(long)]
+    pub events: Option<String>,
+}
+
+impl EventOptions {
+    /// The events to count, in the format of `perf stat -e`.
+    pub fn perf_events(&self) -> String {
+        match &self.events {
+            Some(events) => events.clone(),
+            None => self.event_profile.events().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventProfile {
+    Basic,
+    Cache,
+    Branch,
+    Frontend,
+}
+
+impl EventProfile {
+    pub fn events(&self) -> &'static str {
+        match self {
+            EventProfile::Basic => DEFAULT_PERF_EVENTS,
+            EventProfile::Cache => "instructions:u,cycles:u,task-clock,cache-references,cache-misses,L1-dcache-loads,L1-dcache-load-misses,LLC-loads,LLC-load-misses",
+            EventProfile::Branch => "instructions:u,cycles:u,task-clock,branches,branch-misses",
+            EventProfile::Frontend => "instructions:u,cycles:u,task-clock,stalled-cycles-frontend,L1-icache-load-misses,iTLB-loads,iTLB-load-misses",
+        }
+    }
+}
+
+impl FromStr for EventProfile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => std::result::Result::Ok(EventProfile::Basic),
+            "cache" => std::result::Result::Ok(EventProfile::Cache),
+            "branch" => std::result::Result::Ok(EventProfile::Branch),
+            "frontend" => std::result::Result::Ok(EventProfile::Frontend),
+            _ => Err(format!(
+                "Unrecognized event profile {}. Supported profiles are `basic`, `cache`, `branch` and `frontend`",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -220,15 +390,28 @@ pub enum Commands {
         #[clap(long, default_value = "1")]
         iterations: usize,
 
+        #[clap(flatten)]
+        adaptive: AdaptiveOptions,
+
         #[clap(long = "perf-tool", default_value = "perf-stat")]
         perf_tool: PerfTool,
 
+        #[clap(flatten)]
+        events: EventOptions,
+
         #[clap(long = "event-filter-file", default_value = "")]
         event_filter_file: PathBuf,
 
+        /// Profiles to measure, separated by ','. Besides `check`, `debug`,
+        /// `doc` and `release`, names of custom profiles are accepted.
         #[clap(long = "profile", default_value = "debug,release")]
         profiles: Profiles,
 
+        /// Scenarios to measure, separated by ','. Supported scenarios are
+        /// `full`, `incr-full`, `incr-unchanged` and `incr-patched`.
+        #[clap(long = "scenarios", default_value = "full")]
+        scenarios: Scenarios,
+
         /// The path of benchmark dir
         #[clap(long = "bench-dir", default_value = "../benchmarks/compile-time")]
         bench_dir: PathBuf,
@@ -240,6 +423,11 @@ pub enum Commands {
         /// Flag of Flamegraph. Make $flamegraph$ > 0 if flamegraph is needed.
         #[clap(long, default_value = "1")]
         flamegraph: i32,
+
+        /// Resume an interrupted run from the checkpoint in `out-dir`,
+        /// skipping results already recorded there.
+        #[clap(long)]
+        resume: bool,
     },
     /// Benchmarks the performance of programs generated by a local rustc.
     BenchRuntimeLocal {
@@ -250,9 +438,20 @@ pub enum Commands {
         #[clap(long, default_value = "5")]
         iterations: u32,
 
+        /// How many iterations to execute before the measured ones, overriding
+        /// `warmup_runs` in perf-config.
+        #[clap(long = "warmup-runs")]
+        warmup_runs: Option<u32>,
+
+        #[clap(flatten)]
+        adaptive: AdaptiveOptions,
+
         #[clap(long = "perf-tool", default_value = "perf-stat")]
         perf_tool: PerfTool,
 
+        #[clap(flatten)]
+        events: EventOptions,
+
         #[clap(long = "event-filter-file", default_value = "")]
         event_filter_file: PathBuf,
 
@@ -267,6 +466,146 @@ pub enum Commands {
         /// Flag of Flamegraph. Make $flamegraph$ > 0 if flamegraph is needed.
         #[clap(long, default_value = "1")]
         flamegraph: i32,
+
+        /// Resume an interrupted run from the checkpoint in `out-dir`,
+        /// skipping results already recorded there. Runtime benchmarks are
+        /// recorded once all iterations of a feature set are done, so an
+        /// interrupted one is measured again from its first iteration.
+        #[clap(long)]
+        resume: bool,
+    },
+
+    /// Compares two local rustcs, alternating them iteration by iteration,
+    /// and pairs the results of each iteration.
+    BenchCompare {
+        /// The path to the rustc measured as `a`, or a `+`-prefixed toolchain name
+        #[clap(long = "rustc-a")]
+        rustc_a: String,
+
+        /// The path to the rustc measured as `b`, or a `+`-prefixed toolchain name
+        #[clap(long = "rustc-b")]
+        rustc_b: String,
+
+        /// The path to the local Cargo to use with `rustc-a`
+        #[clap(long = "cargo-a", parse(from_os_str))]
+        cargo_a: Option<PathBuf>,
+
+        /// The path to the local Cargo to use with `rustc-b`
+        #[clap(long = "cargo-b", parse(from_os_str))]
+        cargo_b: Option<PathBuf>,
+
+        /// The path to the local rustdoc to use with `rustc-a`
+        #[clap(long = "rustdoc-a", parse(from_os_str))]
+        rustdoc_a: Option<PathBuf>,
+
+        /// The path to the local rustdoc to use with `rustc-b`
+        #[clap(long = "rustdoc-b", parse(from_os_str))]
+        rustdoc_b: Option<PathBuf>,
+
+        /// Kind of benchmarks to compare: `compile-time` or `runtime`
+        #[clap(long, default_value = "compile-time")]
+        kind: BenchKind,
+
+        /// How many iterations each toolchain runs for each benchmark.
+        #[clap(long, default_value = "5")]
+        iterations: usize,
+
+        /// Order of the toolchains within each iteration: `abab` runs `a`
+        /// first every time, `random` picks the first one at random.
+        #[clap(long, default_value = "random")]
+        order: InterleaveOrder,
+
+        /// Seed of `--order random`, to repeat the order of a previous run.
+        #[clap(long)]
+        seed: Option<u64>,
+
+        /// How many iterations to execute before the measured ones, overriding
+        /// `warmup_runs` in perf-config. Only for runtime benchmarks.
+        #[clap(long = "warmup-runs")]
+        warmup_runs: Option<u32>,
+
+        #[clap(long = "perf-tool", default_value = "perf-stat")]
+        perf_tool: PerfTool,
+
+        #[clap(flatten)]
+        events: EventOptions,
+
+        #[clap(long = "event-filter-file", default_value = "")]
+        event_filter_file: PathBuf,
+
+        /// Profiles to measure, separated by ','. Only for compile-time benchmarks.
+        #[clap(long = "profile", default_value = "debug,release")]
+        profiles: Profiles,
+
+        /// Scenarios to measure, separated by ','. Only for compile-time benchmarks.
+        #[clap(long = "scenarios", default_value = "full")]
+        scenarios: Scenarios,
+
+        /// The path of benchmark dir, `../benchmarks/compile-time` or
+        /// `../benchmarks/runtime/` by default
+        #[clap(long = "bench-dir")]
+        bench_dir: Option<PathBuf>,
+
+        /// The path of output dir. Results of each toolchain are written to
+        /// its sub dir `a` or `b`.
+        #[clap(long = "out-dir", default_value = "results")]
+        out_dir: PathBuf,
+
+        #[clap(flatten)]
+        filter: FilterOptions,
+    },
+
+    /// Measures compile time, runtime and binary size over a matrix of codegen
+    /// settings, and the Pareto front of each benchmark.
+    Sweep {
+        #[clap(flatten)]
+        local: LocalOptions,
+
+        /// Json file mapping each codegen setting to sweep, e.g. `opt-level`,
+        /// `lto` or `rustflags`, to the values to try.
+        #[clap(long = "sweep-config")]
+        sweep_config: PathBuf,
+
+        /// How many iterations of each benchmark to do at each point.
+        #[clap(long, default_value = "3")]
+        iterations: usize,
+
+        #[clap(long = "perf-tool", default_value = "perf-stat")]
+        perf_tool: PerfTool,
+
+        #[clap(flatten)]
+        events: EventOptions,
+
+        #[clap(long = "event-filter-file", default_value = "")]
+        event_filter_file: PathBuf,
+
+        /// The profile compile-time and binary-size benchmarks are built with.
+        /// Its settings are overridden by each point.
+        #[clap(long = "profile", default_value = "release")]
+        profile: Profile,
+
+        /// The metric of compile-time benchmarks in the table
+        #[clap(long = "compile-time-metric", default_value = "wall-time")]
+        compile_time_metric: String,
+
+        /// The metric of runtime benchmarks in the table
+        #[clap(long = "runtime-metric", default_value = "wall-time")]
+        runtime_metric: String,
+
+        /// The path of compile-time benchmark dir, also used for binary size
+        #[clap(
+            long = "compile-time-bench-dir",
+            default_value = "../benchmarks/compile-time"
+        )]
+        compile_time_bench_dir: PathBuf,
+
+        /// The path of runtime benchmark dir
+        #[clap(long = "runtime-bench-dir", default_value = "../benchmarks/runtime/")]
+        runtime_bench_dir: PathBuf,
+
+        /// The path of output dir
+        #[clap(long = "out-dir", default_value = "results")]
+        out_dir: PathBuf,
     },
 
     /// Generate MIR with a local rustc.
@@ -283,6 +622,41 @@ pub enum Commands {
         out_dir: PathBuf,
     },
 
+    /// Check perf-config.json and build of every benchmark without measuring anything.
+    ValidateSuite {
+        /// The path to the local rustc to build the benchmarks with, not
+        /// needed with `--no-build`
+        #[clap(required_unless_present = "no_build")]
+        rustc: Option<String>,
+
+        /// The path to the local rustdoc to build the benchmarks with
+        #[clap(long, parse(from_os_str))]
+        rustdoc: Option<PathBuf>,
+
+        /// Identifier to associate the results with
+        #[clap(long)]
+        id: Option<String>,
+
+        /// The path to the local Cargo to use
+        #[clap(long, parse(from_os_str))]
+        cargo: Option<PathBuf>,
+
+        #[clap(flatten)]
+        filter: FilterOptions,
+
+        /// The path of benchmark dir
+        #[clap(long = "bench-dir", default_value = "../benchmarks/compile-time")]
+        bench_dir: PathBuf,
+
+        /// The path of output file
+        #[clap(long = "out-dir", default_value = "results")]
+        out_dir: PathBuf,
+
+        /// Only check perf-config.json, skip building benchmarks.
+        #[clap(long = "no-build")]
+        no_build: bool,
+    },
+
     /// Benchmarks the binary size of compiled benchmarks for a local rustc.
     BinaryLocal {
         #[clap(flatten)]
@@ -405,14 +779,53 @@ pub enum PerfTool {
 pub enum Bencher {
     PerfStat,
     PerfRecord,
-    // PerfStatSelfProfile,
+    /// Measures with `wait4` instead of perf, for hosts without perf access.
+    Rusage,
+    /// Runs rustc under a valgrind tool. Only for compile-time benchmarks.
+    Valgrind(ValgrindTool),
+    /// PerfStat, plus the time rustc spent in each query from
+    /// `-Zself-profile`. Only for compile-time benchmarks.
+    PerfStatSelfProfile,
     // XperfStat,
     // XperfStatSelfProfile,
 }
 
+impl Bencher {
+    /// Name of the file the tool leaves in the benchmark dir, which is
+    /// copied into `out-dir` after each iteration.
+    pub fn output_file(&self) -> Option<&'static str> {
+        match self {
+            Bencher::PerfStat | Bencher::Rusage | Bencher::PerfStatSelfProfile => None,
+            Bencher::PerfRecord => Some("perf.data"),
+            Bencher::Valgrind(tool) => Some(tool.output_file()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValgrindTool {
+    Cachegrind,
+    Callgrind,
+    Dhat,
+    Massif,
+}
+
+impl ValgrindTool {
+    pub fn output_file(&self) -> &'static str {
+        match self {
+            ValgrindTool::Cachegrind => "cgout",
+            ValgrindTool::Callgrind => "clgout",
+            ValgrindTool::Dhat => "dhout",
+            ValgrindTool::Massif => "msout",
+        }
+    }
+}
+
 impl PerfTool {
     pub fn name(&self) -> String {
         match self {
+            // These names are the wrapper branches of rustc-fake.
+            PerfTool::BenchTool(Bencher::Valgrind(tool)) => format!("{:?}", tool),
             PerfTool::BenchTool(b) => format!("{:?}", b),
         }
     }
@@ -422,6 +835,13 @@ impl PerfTool {
             PerfTool::BenchTool(bencher) => bencher.clone(),
         }
     }
+
+    pub fn needs_perf(&self) -> bool {
+        matches!(
+            self.get_bencher(),
+            Bencher::PerfStat | Bencher::PerfRecord | Bencher::PerfStatSelfProfile
+        )
+    }
 }
 
 impl FromStr for PerfTool {
@@ -430,6 +850,22 @@ impl FromStr for PerfTool {
         match s {
             "perf-stat" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfStat)),
             "perf-record" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfRecord)),
+            "perf-stat-self-profile" => {
+                std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfStatSelfProfile))
+            }
+            "rusage" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Rusage)),
+            "cachegrind" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Valgrind(
+                ValgrindTool::Cachegrind,
+            ))),
+            "callgrind" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Valgrind(
+                ValgrindTool::Callgrind,
+            ))),
+            "dhat" => {
+                std::result::Result::Ok(PerfTool::BenchTool(Bencher::Valgrind(ValgrindTool::Dhat)))
+            }
+            "massif" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Valgrind(
+                ValgrindTool::Massif,
+            ))),
             _ => Err(format!("Unrecognized PerfTool {}", s)),
         }
     }
@@ -452,6 +888,35 @@ impl FromStr for BinaryPlotMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BenchKind {
+    CompileTime,
+    Runtime,
+}
+
+impl BenchKind {
+    pub fn default_bench_dir(&self) -> PathBuf {
+        match self {
+            BenchKind::CompileTime => PathBuf::from("../benchmarks/compile-time"),
+            BenchKind::Runtime => PathBuf::from("../benchmarks/runtime/"),
+        }
+    }
+}
+
+impl FromStr for BenchKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compile-time" => std::result::Result::Ok(Self::CompileTime),
+            "runtime" => std::result::Result::Ok(Self::Runtime),
+            _ => Err(format!(
+                "Unrecognized kind {}. Kind should be either `compile-time` or `runtime`",
+                s
+            )),
+        }
+    }
+}
+
 pub struct ResultWriter {
     fptr: File,
 }
@@ -473,3 +938,32 @@ impl ResultWriter {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod test_cli {
+    use clap::Parser;
+
+    use super::{Cli, Commands};
+
+    /// Test for the arguments of validate_suite
+    ///
+    /// Step1: Parse it with `--no-build` and without a rustc.
+    ///
+    /// Step2: Verify a rustc is required without `--no-build`.
+    #[test]
+    fn test_validate_suite_args() {
+        let cli = Cli::try_parse_from(["collector", "validate_suite", "--no-build"]).unwrap();
+        match cli.command {
+            Commands::ValidateSuite {
+                rustc, no_build, ..
+            } => {
+                assert!(no_build);
+                assert_eq!(None, rustc);
+            }
+            _ => panic!("not parsed as validate_suite"),
+        }
+
+        assert!(Cli::try_parse_from(["collector", "validate_suite"]).is_err());
+        assert!(Cli::try_parse_from(["collector", "validate_suite", "rustc"]).is_ok());
+    }
+}
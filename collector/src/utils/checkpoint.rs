@@ -0,0 +1,153 @@
+use std::{
+    fs::{create_dir_all, read_to_string, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use tempfile::NamedTempFile;
+
+/// Results streamed to a file in `out_dir` as soon as they are measured,
+/// one json object per line, so that an interrupted run can be resumed.
+pub struct Checkpoint<T> {
+    path: PathBuf,
+    fptr: File,
+    records: Vec<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Checkpoint<T> {
+    /// Open the checkpoint file `file_name` in `dir`. Records of a previous
+    /// run are loaded if `resume` is set, otherwise the file is truncated.
+    pub fn new(dir: &Path, file_name: &str, resume: bool) -> anyhow::Result<Self> {
+        create_dir_all(dir).with_context(|| format!("fail to create dir for {:?}", dir))?;
+        let path = dir.join(file_name);
+
+        let mut records = vec![];
+        if resume && path.exists() {
+            let content = read_to_string(&path)
+                .with_context(|| format!("fail to read checkpoint {:?}", path))?;
+            for (i, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // The last line may be cut off if the previous run was killed.
+                match serde_json::from_str(line) {
+                    Ok(record) => records.push(record),
+                    Err(e) => eprintln!(
+                        "Ignore broken record at line {} of checkpoint {:?}: {}",
+                        i + 1,
+                        path,
+                        e
+                    ),
+                }
+            }
+        }
+
+        let fptr = if records.is_empty() {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&path)
+                .with_context(|| format!("Fail to create checkpoint file {:?}.", path))?
+        } else {
+            // Rewrite the loaded records so that broken lines are dropped. The
+            // checkpoint is only replaced once all of them are written, so it
+            // survives the collector being killed meanwhile.
+            let mut tmp = NamedTempFile::new_in(dir)
+                .with_context(|| format!("Fail to create temp file in {:?}.", dir))?;
+            for record in &records {
+                let mut line = serde_json::to_string(record)?;
+                line.push('\n');
+                tmp.write_all(line.as_bytes())?;
+            }
+            tmp.flush()?;
+            tmp.persist(&path)
+                .with_context(|| format!("fail to write checkpoint {:?}.", path))?
+        };
+        Ok(Checkpoint {
+            path,
+            fptr,
+            records,
+        })
+    }
+
+    /// Records loaded from a previous run or recorded in this run.
+    pub fn records(&self) -> &[T] {
+        &self.records
+    }
+
+    pub fn into_records(self) -> Vec<T> {
+        self.records
+    }
+
+    /// Append a record to the checkpoint file and flush it immediately.
+    pub fn record(&mut self, record: T) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        self.fptr
+            .write_all(line.as_bytes())
+            .and_then(|_| self.fptr.flush())
+            .with_context(|| format!("fail to write checkpoint {:?}.", self.path))?;
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_checkpoint {
+    use std::{
+        fs::{read_dir, OpenOptions},
+        io::Write,
+    };
+
+    use tempfile::TempDir;
+
+    use super::Checkpoint;
+
+    /// Test for Checkpoint
+    ///
+    /// Step1: Record some values, then append a broken line as if the run was killed.
+    ///
+    /// Step2: Resume from the checkpoint, verify the loaded records, and that
+    /// new ones are appended to them.
+    ///
+    /// Step3: Open it again without resuming and verify it is empty.
+    #[test]
+    fn test_checkpoint_resume() {
+        let dir = TempDir::new().unwrap();
+
+        let mut checkpoint =
+            Checkpoint::<(String, usize)>::new(dir.path(), "ckpt.jsonl", false).unwrap();
+        checkpoint.record((String::from("a"), 1)).unwrap();
+        checkpoint.record((String::from("b"), 2)).unwrap();
+        drop(checkpoint);
+
+        OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("ckpt.jsonl"))
+            .unwrap()
+            .write_all(b"[\"c\",")
+            .unwrap();
+
+        let mut checkpoint =
+            Checkpoint::<(String, usize)>::new(dir.path(), "ckpt.jsonl", true).unwrap();
+        assert_eq!(
+            &[(String::from("a"), 1), (String::from("b"), 2)],
+            checkpoint.records()
+        );
+        checkpoint.record((String::from("c"), 3)).unwrap();
+        drop(checkpoint);
+        assert_eq!(1, read_dir(dir.path()).unwrap().count());
+
+        let checkpoint =
+            Checkpoint::<(String, usize)>::new(dir.path(), "ckpt.jsonl", true).unwrap();
+        assert_eq!(3, checkpoint.records().len());
+        drop(checkpoint);
+
+        let checkpoint =
+            Checkpoint::<(String, usize)>::new(dir.path(), "ckpt.jsonl", false).unwrap();
+        assert!(checkpoint.records().is_empty());
+    }
+}
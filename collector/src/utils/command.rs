@@ -1,13 +1,124 @@
-use std::process::{self, Command, Stdio};
+use std::{
+    cell::Cell,
+    process::{self, Child, Command, ExitStatus, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
-pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
-    use crate::utils::read2;
+#[derive(thiserror::Error, Debug)]
+pub enum CommandError {
+    #[error("expected success, got {}\n\nstderr={}\n\n stdout={}\n", .status, .stderr, .stdout)]
+    Failed {
+        status: ExitStatus,
+        stderr: String,
+        stdout: String,
+    },
+    #[error("process killed after timeout of {:?}", .0)]
+    Timeout(Duration),
+}
+
+thread_local! {
+    static TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+/// Commands run by this thread while the guard is alive are killed together
+/// with all their children once they exceed the timeout.
+///
+/// Such commands are spawned in a process group of their own, so they do not
+/// receive a Ctrl-C sent to the collector.
+pub struct TimeoutGuard {
+    prev: Option<Duration>,
+}
+
+impl TimeoutGuard {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        TimeoutGuard {
+            prev: TIMEOUT.with(|t| t.replace(timeout)),
+        }
+    }
+}
+
+impl Drop for TimeoutGuard {
+    fn drop(&mut self) {
+        TIMEOUT.with(|t| t.set(self.prev));
+    }
+}
+
+/// Kills the process group of a child once the timeout is reached.
+struct Watchdog {
+    timeout: Duration,
+    stop: mpsc::Sender<()>,
+    handle: JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn start(pid: u32, timeout: Duration) -> Self {
+        let (stop, rx) = mpsc::channel();
+        let handle = thread::spawn(move || match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                log::warn!("killing process group {} after {:?}", pid, timeout);
+                kill_process_group(pid);
+                true
+            }
+            _ => false,
+        });
+        Watchdog {
+            timeout,
+            stop,
+            handle,
+        }
+    }
+
+    /// Stop the watchdog, failing if the child has been killed.
+    fn stop(self) -> anyhow::Result<()> {
+        let _ = self.stop.send(());
+        if self.handle.join().unwrap_or(false) {
+            return Err(CommandError::Timeout(self.timeout).into());
+        }
+        Ok(())
+    }
+}
+
+/// Spawn `cmd`, watched by a watchdog if a `TimeoutGuard` is alive.
+fn spawn_with_timeout(cmd: &mut Command) -> anyhow::Result<(Child, Option<Watchdog>)> {
     use anyhow::Context;
-    let mut child = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+
+    let timeout = TIMEOUT.with(|t| t.get());
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let child = cmd
         .spawn()
         .with_context(|| format!("failed to spawn process for cmd: {:?}", cmd))?;
+    let watchdog = timeout.map(|timeout| Watchdog::start(child.id(), timeout));
+    Ok((child, watchdog))
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(pid: u32) {
+    log::warn!(
+        "killing process {} on timeout is only supported on unix",
+        pid
+    );
+}
+
+pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
+    use crate::utils::read2;
+    use anyhow::Context;
+
+    let (mut child, watchdog) =
+        spawn_with_timeout(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))?;
 
     let mut stdout = Vec::new();
     let mut stderr = Vec::new();
@@ -38,6 +149,10 @@ pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Out
         .wait()
         .with_context(|| "failed to wait on child process")?;
 
+    if let Some(watchdog) = watchdog {
+        watchdog.stop()?;
+    }
+
     Ok(process::Output {
         status,
         stdout,
@@ -49,22 +164,71 @@ pub fn command_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
     let output = run_command_with_output(cmd)?;
 
     if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "expected success, got {}\n\nstderr={}\n\n stdout={}\n",
-            output.status,
-            String::from_utf8_lossy(&output.stderr),
-            String::from_utf8_lossy(&output.stdout)
-        ));
+        return Err(CommandError::Failed {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        }
+        .into());
     }
 
     Ok(output)
 }
 
 pub fn command_discard_output(cmd: &mut Command) -> anyhow::Result<()> {
-    cmd.stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?
-        .wait()?;
+    let (mut child, watchdog) =
+        spawn_with_timeout(cmd.stdout(Stdio::null()).stderr(Stdio::null()))?;
+    child.wait()?;
+    if let Some(watchdog) = watchdog {
+        watchdog.stop()?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test_command {
+    use std::{
+        process::Command,
+        time::{Duration, Instant},
+    };
+
+    use super::{command_discard_output, command_output, CommandError, TimeoutGuard};
+
+    /// Test for TimeoutGuard
+    ///
+    /// Step1: Run a shell that spawns a child sleeping in background, with a short timeout.
+    ///
+    /// Step2: Verify both are killed and a `Timeout` error is returned, also
+    /// when the output is discarded.
+    #[test]
+    fn test_command_timeout() {
+        let start = Instant::now();
+        let err = {
+            let _timeout = TimeoutGuard::new(Some(Duration::from_millis(200)));
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg("sleep 10 & sleep 10");
+            command_output(&mut cmd).unwrap_err()
+        };
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::Timeout(_))
+        ));
+
+        let err = {
+            let _timeout = TimeoutGuard::new(Some(Duration::from_millis(200)));
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg("sleep 10");
+            command_discard_output(&mut cmd).unwrap_err()
+        };
+        assert!(matches!(
+            err.downcast_ref::<CommandError>(),
+            Some(CommandError::Timeout(_))
+        ));
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("exit 0");
+        assert!(command_output(&mut cmd).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod checkpoint;
 pub mod command;
 pub mod fs;
 pub mod read2;
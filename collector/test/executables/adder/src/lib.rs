@@ -0,0 +1,11 @@
+pub fn add(a: u64, b: u64) -> u64 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_adds() {
+        assert_eq!(4, super::add(2, 2));
+    }
+}
@@ -0,0 +1,4 @@
+#[test]
+fn tree_adds() {
+    assert_eq!(5, adder::add(2, 3));
+}
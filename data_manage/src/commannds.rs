@@ -64,6 +64,34 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// Compare even if the results were measured on incompatible hosts.
+        #[clap(long = "force")]
+        force: bool,
+    },
+
+    /// Compare the rustc self profiles of 2 compile-time datas measured with
+    /// `perf-stat-self-profile` and list the queries that regressed most.
+    SelfProfileCompare {
+        /// The first data input file.
+        #[clap(long = "data-1")]
+        data_a: PathBuf,
+
+        /// The second data input file.
+        #[clap(long = "data-2")]
+        data_b: PathBuf,
+
+        /// The path of output file
+        #[clap(long = "out-path", default_value = "self_profile_compare.json")]
+        out_path: PathBuf,
+
+        /// The number of queries to print.
+        #[clap(long = "top", default_value = "20")]
+        top: usize,
+
+        /// Compare even if the results were measured on incompatible hosts.
+        #[clap(long = "force")]
+        force: bool,
     },
 
     /// Compare 2 different stats on one metric and plot their change rate on geometric mean.
@@ -83,6 +111,10 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// Compare even if the results were measured on incompatible hosts.
+        #[clap(long = "force")]
+        force: bool,
     },
 
     /// Compare 2 different stats on 2 metrics and plot their change rate on geometric mean.
@@ -106,6 +138,10 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// Compare even if the results were measured on incompatible hosts.
+        #[clap(long = "force")]
+        force: bool,
     },
 
     /// Merge several metrics into a new metric of a table data fmt file.
@@ -1,18 +1,22 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
+use collector::statistics::metadata::check_compatible;
+
 use super::data::{calculate_change_rate, read_data, ChangeRate};
 
 pub fn compare_data(
-    data_a: &PathBuf,
-    data_b: &PathBuf,
+    data_a: &Path,
+    data_b: &Path,
     metric: &String,
     out_path: PathBuf,
+    force: bool,
 ) -> anyhow::Result<PathBuf> {
-    let data_a = read_data(data_a, metric)?;
-    let data_b = read_data(data_b, metric)?;
+    let (data_a, metadata_a) = read_data(data_a, metric)?;
+    let (data_b, metadata_b) = read_data(data_b, metric)?;
+    check_compatible(metadata_a.as_ref(), metadata_b.as_ref(), force)?;
 
     // Calculate change rate of stats_a on stats_b
     let change_rate = calculate_change_rate(&data_a, &data_b);
@@ -75,7 +79,7 @@ mod test_compare_stat {
 
         assert_eq!(
             out_path.clone(),
-            compare_data(&stat_1, &stat_2, &metric, out_path.clone()).unwrap()
+            compare_data(&stat_1, &stat_2, &metric, out_path.clone(), false).unwrap()
         );
 
         fs::metadata(&out_path).unwrap();
@@ -0,0 +1,60 @@
+use std::{fs::File, io::Write, path::PathBuf};
+
+use collector::statistics::{
+    compile_time_stat::CompileTimeResultSet,
+    metadata::{check_compatible, read_result_file},
+    self_profile::{compare_self_profiles, QueryChange},
+};
+
+/// Compare the self profiles of 2 compile-time results measured with
+/// `perf-stat-self-profile`, write the comparison of every run to `out_path`
+/// and print the `top` queries that regressed most.
+pub fn compare_self_profile(
+    data_a: &PathBuf,
+    data_b: &PathBuf,
+    out_path: PathBuf,
+    top: usize,
+    force: bool,
+) -> anyhow::Result<PathBuf> {
+    let result_a = read_result_file::<CompileTimeResultSet>(data_a)?;
+    let result_b = read_result_file::<CompileTimeResultSet>(data_b)?;
+    check_compatible(
+        result_a.metadata.as_ref(),
+        result_b.metadata.as_ref(),
+        force,
+    )?;
+
+    let comparison = compare_self_profiles(&result_a.data, &result_b.data);
+    if comparison.runs.is_empty() {
+        anyhow::bail!(
+            "no self profile found in both {:?} and {:?}",
+            data_a,
+            data_b
+        );
+    }
+
+    print_query_changes(&comparison.total, top);
+
+    let mut file = File::create(&out_path)?;
+    file.write_all(serde_json::to_string(&comparison)?.as_bytes())?;
+    Ok(out_path)
+}
+
+fn print_query_changes(changes: &[QueryChange], top: usize) {
+    println!(
+        "{:<40} {:>12} {:>12} {:>10}",
+        "query", "data-1 (s)", "data-2 (s)", "change"
+    );
+    for change in changes.iter().take(top) {
+        println!(
+            "{:<40} {:>12.6} {:>12.6} {:>10}",
+            change.query,
+            change.self_time_a,
+            change.self_time_b,
+            change
+                .change_rate
+                .map(|r| format!("{:+.2}%", r))
+                .unwrap_or(String::from("new"))
+        );
+    }
+}
@@ -1,19 +1,23 @@
 use std::{
     collections::HashMap,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
+use collector::statistics::metadata::check_compatible;
+
 use super::stats::{calculate_change_rate, read_stats};
 
 pub fn compare_stat(
-    stats_a: &PathBuf,
-    stats_b: &PathBuf,
+    stats_a: &Path,
+    stats_b: &Path,
     metric: &String,
     out_path: PathBuf,
+    force: bool,
 ) -> anyhow::Result<PathBuf> {
-    let stats_a = read_stats(stats_a, metric)?;
-    let stats_b = read_stats(stats_b, metric)?;
+    let (stats_a, metadata_a) = read_stats(stats_a, metric)?;
+    let (stats_b, metadata_b) = read_stats(stats_b, metric)?;
+    check_compatible(metadata_a.as_ref(), metadata_b.as_ref(), force)?;
 
     // Calculate change rate of stats_a on stats_b
     let change_rate = calculate_change_rate(&stats_a, &stats_b);
@@ -67,7 +71,7 @@ mod test_compare_stat {
 
         assert_eq!(
             out_path.clone(),
-            compare_stat(&stat_1, &stat_2, &metric, out_path.clone()).unwrap()
+            compare_stat(&stat_1, &stat_2, &metric, out_path.clone(), false).unwrap()
         );
 
         fs::metadata(&out_path).unwrap();
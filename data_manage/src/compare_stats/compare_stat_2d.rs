@@ -1,25 +1,29 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
+use collector::statistics::metadata::check_compatible;
+
 use super::stats::{calculate_change_rate, read_stats, ChangeRate};
 
 pub fn compare_stat_2d(
-    stats_a: &PathBuf,
-    stats_b: &PathBuf,
+    stats_a: &Path,
+    stats_b: &Path,
     metric_1: &String,
     metric_2: &String,
     out_path: PathBuf,
+    force: bool,
 ) -> anyhow::Result<PathBuf> {
+    let (stats_a_1, metadata_a) = read_stats(stats_a, metric_1)?;
+    let (stats_b_1, metadata_b) = read_stats(stats_b, metric_1)?;
+    check_compatible(metadata_a.as_ref(), metadata_b.as_ref(), force)?;
+
     // Calculate change rate of stats_a on stats_b
-    let change_rate_1 = calculate_change_rate(
-        &read_stats(stats_a, metric_1)?,
-        &read_stats(stats_b, metric_1)?,
-    );
+    let change_rate_1 = calculate_change_rate(&stats_a_1, &stats_b_1);
     let change_rate_2 = calculate_change_rate(
-        &read_stats(stats_a, metric_2)?,
-        &read_stats(stats_b, metric_2)?,
+        &read_stats(stats_a, metric_2)?.0,
+        &read_stats(stats_b, metric_2)?.0,
     );
 
     plot_compare(&change_rate_1, &change_rate_2, out_path, metric_1, metric_2)
@@ -83,7 +87,15 @@ mod test_compare_stat_2d {
 
         assert_eq!(
             out_path.clone(),
-            compare_stat_2d(&stat_1, &stat_2, &metric_1, &metric_2, out_path.clone()).unwrap()
+            compare_stat_2d(
+                &stat_1,
+                &stat_2,
+                &metric_1,
+                &metric_2,
+                out_path.clone(),
+                false
+            )
+            .unwrap()
         );
 
         fs::metadata(&out_path).unwrap();
@@ -1,24 +1,33 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{collections::HashMap, path::Path};
 
 use anyhow::bail;
-use collector::statistics::{
-    compile_time_stat::CompileTimeResultSet, runtime_stat::RuntimeResultVec,
+use collector::{
+    benchmark::feature_set::labeled_benchmark_name,
+    statistics::{
+        compile_time_stat::CompileTimeResultSet,
+        metadata::{read_result_file, RunMetadata},
+        runtime_stat::RuntimeResultVec,
+    },
 };
 
 pub type LabeledData = HashMap<String, Vec<f64>>;
 pub type ChangeRate = HashMap<String, Vec<f64>>;
 
-pub fn read_data(data_file: &PathBuf, metric: &String) -> anyhow::Result<LabeledData> {
+/// Read data of `metric` from a results file, together with the metadata of
+/// the run that produced it.
+pub fn read_data(
+    data_file: &Path,
+    metric: &String,
+) -> anyhow::Result<(LabeledData, Option<RunMetadata>)> {
     // Filter out non-relevant metrics and reshape stats into Hashmap
 
-    match serde_json::from_reader::<_, CompileTimeResultSet>(BufReader::new(File::open(data_file)?))
-    {
-        Ok(s) => return Ok(reshape_data(s, metric)),
+    match read_result_file::<CompileTimeResultSet>(data_file) {
+        Ok(s) => return Ok((reshape_data(s.data, metric), s.metadata)),
         Err(_) => (),
     }
 
-    match serde_json::from_reader::<_, RuntimeResultVec>(BufReader::new(File::open(data_file)?)) {
-        Ok(s) => return Ok(reshape_runtime_data(s, metric)),
+    match read_result_file::<RuntimeResultVec>(data_file) {
+        Ok(s) => return Ok((reshape_runtime_data(s.data, metric), s.metadata)),
         Err(e) => bail!(e),
     }
 }
@@ -48,25 +57,15 @@ pub fn calculate_change_rate(data_a: &LabeledData, data_b: &LabeledData) -> Chan
 }
 
 fn reshape_data(data: CompileTimeResultSet, metric: &String) -> LabeledData {
+    let mut labeled_data = LabeledData::new();
     data.results
         .into_iter()
-        .map(|s| {
-            (
-                s.benchmark,
-                s.result_vec
-                    .into_iter()
-                    .map(|d| {
-                        d.stats
-                            .stats
-                            .into_iter()
-                            .find(|(m, _)| m == metric)
-                            .unwrap()
-                    })
-                    .map(|x| x.1)
-                    .collect(),
-            )
-        })
-        .collect()
+        .flat_map(|s| s.result_vec)
+        .for_each(|d| {
+            let value = d.stats.stats.iter().find(|(m, _)| *m == metric).unwrap().1;
+            labeled_data.entry(d.label()).or_default().push(*value);
+        });
+    labeled_data
 }
 
 fn reshape_runtime_data(data: RuntimeResultVec, metric: &String) -> LabeledData {
@@ -74,7 +73,7 @@ fn reshape_runtime_data(data: RuntimeResultVec, metric: &String) -> LabeledData
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                labeled_benchmark_name(&s.name, &s.feature_set),
                 s.stats
                     .into_iter()
                     .map(|stats| stats.stats.into_iter().find(|(m, _)| m == metric).unwrap())
@@ -84,3 +83,51 @@ fn reshape_runtime_data(data: RuntimeResultVec, metric: &String) -> LabeledData
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test_data {
+    use collector::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+        statistics::compile_time_stat::{
+            CompileTimeBenchResult, CompileTimeResult, CompileTimeResultSet,
+        },
+    };
+
+    use super::reshape_data;
+
+    /// Test for reshape_data
+    ///
+    /// Step1: Reshape results of the same benchmark in several scenarios.
+    ///
+    /// Step2: Verify the data of each scenario and patch are kept apart.
+    #[test]
+    fn test_reshape_data() {
+        let result = |scenario: Scenario, value: f64| {
+            let mut stats = Stats::new();
+            stats.insert(String::from("wall-time"), value);
+            CompileTimeResult::new(
+                String::from("helloworld"),
+                1,
+                Profile::Debug,
+                scenario,
+                stats,
+            )
+        };
+        let mut bench_result = CompileTimeBenchResult::new(String::from("helloworld"), 1);
+        bench_result.add_result(result(Scenario::Full, 1.));
+        bench_result.add_result(result(Scenario::IncrUnchanged, 2.));
+        bench_result.add_result(result(Scenario::IncrPatched, 3.).with_patch(String::from("0-a")));
+        bench_result.add_result(result(Scenario::IncrPatched, 4.).with_patch(String::from("1-b")));
+
+        let data = reshape_data(
+            CompileTimeResultSet::new(String::new(), vec![bench_result]),
+            &String::from("wall-time"),
+        );
+        assert_eq!(4, data.len());
+        assert_eq!(vec![1.], data["helloworld debug full"]);
+        assert_eq!(vec![2.], data["helloworld debug incr-unchanged"]);
+        assert_eq!(vec![3.], data["helloworld debug incr-patched (0-a)"]);
+        assert_eq!(vec![4.], data["helloworld debug incr-patched (1-b)"]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod compare_data;
+pub mod compare_self_profile;
 pub mod compare_stat;
 pub mod compare_stat_2d;
 mod data;
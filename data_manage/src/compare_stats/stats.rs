@@ -1,26 +1,34 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{collections::HashMap, path::Path};
 
 use anyhow::bail;
-use collector::statistics::{
-    compile_time_stat::CompileTimeStatistics, runtime_stat::RuntimeStatistics,
-    statistic::Statistics,
+use collector::{
+    benchmark::feature_set::labeled_benchmark_name,
+    statistics::{
+        compile_time_stat::CompileTimeStatistics,
+        metadata::{read_result_file, RunMetadata},
+        runtime_stat::RuntimeStatistics,
+        statistic::Statistics,
+    },
 };
 
 pub type LabeledStats = HashMap<String, Statistics>;
 pub type ChangeRate = HashMap<String, f64>;
 
-pub fn read_stats(stats_file: &PathBuf, metric: &String) -> anyhow::Result<LabeledStats> {
+/// Read stats of `metric` from a stats file, together with the metadata of
+/// the run that produced it.
+pub fn read_stats(
+    stats_file: &Path,
+    metric: &String,
+) -> anyhow::Result<(LabeledStats, Option<RunMetadata>)> {
     // Filter out non-relevant metrics and reshape stats into Hashmap
 
-    match serde_json::from_reader::<_, CompileTimeStatistics>(BufReader::new(File::open(
-        stats_file,
-    )?)) {
-        Ok(s) => return Ok(reshape_stat(s, metric)),
+    match read_result_file::<CompileTimeStatistics>(stats_file) {
+        Ok(s) => return Ok((reshape_stat(s.data, metric), s.metadata)),
         Err(_) => (),
     }
 
-    match serde_json::from_reader::<_, RuntimeStatistics>(BufReader::new(File::open(stats_file)?)) {
-        Ok(s) => return Ok(reshape_runtime_stat(s, metric)),
+    match read_result_file::<RuntimeStatistics>(stats_file) {
+        Ok(s) => return Ok((reshape_runtime_stat(s.data, metric), s.metadata)),
         Err(e) => bail!(e),
     }
 }
@@ -42,7 +50,7 @@ fn reshape_stat(stats: CompileTimeStatistics, metric: &String) -> HashMap<String
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                s.label(),
                 s.statistic_vec
                     .into_iter()
                     .find(|(m, _)| m == metric)
@@ -58,7 +66,7 @@ fn reshape_runtime_stat(stats: RuntimeStatistics, metric: &String) -> HashMap<St
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                labeled_benchmark_name(&s.name, &s.feature_set),
                 s.statistic_vec
                     .into_iter()
                     .find(|(m, _)| m == metric)
@@ -1,7 +1,8 @@
 use clap::Parser;
 use commannds::Cli;
 use compare_stats::{
-    compare_data::compare_data, compare_stat::compare_stat, compare_stat_2d::compare_stat_2d,
+    compare_data::compare_data, compare_self_profile::compare_self_profile,
+    compare_stat::compare_stat, compare_stat_2d::compare_stat_2d,
 };
 use merge_stats::{merge_runtime_stat::merge_runtime_stats, merge_stat::merge_compile_time_stats};
 use normalize_stats::normalize_data::normalize_stat;
@@ -39,9 +40,10 @@ fn main() {
             stats_b,
             out_path,
             metric,
-        } => match compare_stat(&stats_a, &stats_b, &metric, out_path) {
+            force,
+        } => match compare_stat(&stats_a, &stats_b, &metric, out_path, force) {
             Ok(p) => println!("Plot change rate of stats to {}", p.to_str().unwrap()),
-            Err(e) => eprintln!("{}", e),
+            Err(e) => exit_with_error(e),
         },
         commannds::Commands::StatsCompare2d {
             stats_a,
@@ -49,9 +51,20 @@ fn main() {
             metric_a,
             metric_b,
             out_path,
-        } => match compare_stat_2d(&stats_a, &stats_b, &metric_a, &metric_b, out_path) {
+            force,
+        } => match compare_stat_2d(&stats_a, &stats_b, &metric_a, &metric_b, out_path, force) {
             Ok(p) => println!("Plot change rate of stats to {}", p.to_str().unwrap()),
-            Err(e) => eprintln!("{}", e),
+            Err(e) => exit_with_error(e),
+        },
+        commannds::Commands::SelfProfileCompare {
+            data_a,
+            data_b,
+            out_path,
+            top,
+            force,
+        } => match compare_self_profile(&data_a, &data_b, out_path, top, force) {
+            Ok(p) => println!("Write self profile comparison to {}", p.to_str().unwrap()),
+            Err(e) => exit_with_error(e),
         },
         commannds::Commands::MergeRuntimeStats {
             root_dir,
@@ -123,9 +136,10 @@ fn main() {
             data_b,
             metric,
             out_path,
-        } => match compare_data(&data_a, &data_b, &metric, out_path) {
+            force,
+        } => match compare_data(&data_a, &data_b, &metric, out_path, force) {
             Ok(p) => println!("Plot change rate of data to {}", p.to_str().unwrap()),
-            Err(e) => eprintln!("{}", e),
+            Err(e) => exit_with_error(e),
         },
         commannds::Commands::CalculateTableStats {
             table_data_path,
@@ -136,3 +150,10 @@ fn main() {
         },
     }
 }
+
+/// Report the error of a comparison and exit with a non-zero code, so that
+/// scripts can tell, e.g., when results of incompatible runs are refused.
+fn exit_with_error(e: anyhow::Error) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1)
+}
@@ -1,10 +1,15 @@
 use std::{
     fs::{create_dir_all, read_dir, File},
-    io::{BufReader, BufWriter},
+    io::BufWriter,
     path::PathBuf,
 };
 
-use collector::statistics::runtime_stat::{RuntimeResultVec, RuntimeStatistics};
+use collector::statistics::{
+    metadata::{read_result_file, ResultFile},
+    runtime_stat::{RuntimeResultVec, RuntimeStatistics},
+};
+
+use super::merge_metadata;
 
 pub fn merge_runtime_stats(
     root_dir: &PathBuf,
@@ -13,6 +18,7 @@ pub fn merge_runtime_stats(
 ) -> anyhow::Result<PathBuf> {
     let mut merged_stats = RuntimeStatistics::new();
     let mut merged_data = RuntimeResultVec { 0: vec![] };
+    let mut merged_metadata = None;
 
     // Iterate each benchmark group under root dir.
     for bench_group in read_dir(root_dir)? {
@@ -29,8 +35,9 @@ pub fn merge_runtime_stats(
                     for f in read_dir(rustc_dir.path())? {
                         let f = f?;
                         if f.file_name().to_str().unwrap().contains("results.json") {
-                            let mut data: RuntimeResultVec =
-                                serde_json::from_reader(BufReader::new(File::open(f.path())?))?;
+                            let ResultFile { metadata, mut data } =
+                                read_result_file::<RuntimeResultVec>(&f.path())?;
+                            merge_metadata(&mut merged_metadata, metadata, &f.path());
 
                             merged_stats.append(&mut data.calculate_statistics());
                             merged_data.0.append(&mut data.0);
@@ -46,11 +53,11 @@ pub fn merge_runtime_stats(
     create_dir_all(&out_dir)?;
     serde_json::to_writer(
         BufWriter::new(File::create(&out_dir.join("merged-stats.json"))?),
-        &merged_stats,
+        &ResultFile::new(merged_metadata.clone(), &merged_stats),
     )?;
     serde_json::to_writer(
         BufWriter::new(File::create(&out_dir.join("merged-data.json"))?),
-        &merged_data,
+        &ResultFile::new(merged_metadata, &merged_data),
     )?;
 
     Ok(out_dir)
@@ -58,13 +65,9 @@ pub fn merge_runtime_stats(
 
 #[cfg(test)]
 mod test_merge_runtime_stat {
-    use std::{
-        fs::{remove_file, File},
-        io::BufReader,
-        path::PathBuf,
-    };
+    use std::{fs::remove_file, path::PathBuf};
 
-    use collector::statistics::runtime_stat::RuntimeStatistics;
+    use collector::statistics::{metadata::read_result_file, runtime_stat::RuntimeStatistics};
 
     use super::merge_runtime_stats;
 
@@ -88,8 +91,7 @@ mod test_merge_runtime_stat {
             out_dir,
         );
 
-        let stats: RuntimeStatistics =
-            serde_json::from_reader(BufReader::new(File::open(&out_stats).unwrap())).unwrap();
+        let stats: RuntimeStatistics = read_result_file(&out_stats).unwrap().data;
 
         assert_eq!(12, stats.len());
 
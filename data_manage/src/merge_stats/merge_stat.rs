@@ -1,14 +1,19 @@
 use std::{
     fs::{create_dir_all, read_dir, File},
-    io::{BufReader, BufWriter},
+    io::BufWriter,
     path::PathBuf,
 };
 
 use collector::{
     benchmark::profile::Profile,
-    statistics::compile_time_stat::{CompileTimeResultSet, CompileTimeStatistics},
+    statistics::{
+        compile_time_stat::{CompileTimeResultSet, CompileTimeStatistics},
+        metadata::{read_result_file, ResultFile},
+    },
 };
 
+use super::merge_metadata;
+
 pub fn merge_compile_time_stats(
     root_dir: &PathBuf,
     profile: Profile,
@@ -17,6 +22,7 @@ pub fn merge_compile_time_stats(
 ) -> anyhow::Result<PathBuf> {
     let mut merged_stats = CompileTimeStatistics::new();
     let mut merged_data = CompileTimeResultSet::new(0.to_string(), vec![]);
+    let mut merged_metadata = None;
 
     // Iterate each benchmark group under root dir.
     for bench_group in read_dir(root_dir)? {
@@ -33,8 +39,9 @@ pub fn merge_compile_time_stats(
                     for f in read_dir(rustc_dir.path())? {
                         let f = f?;
                         if f.file_name().to_str().unwrap().contains("results.json") {
-                            let mut data: CompileTimeResultSet =
-                                serde_json::from_reader(BufReader::new(File::open(f.path())?))?;
+                            let ResultFile { metadata, mut data } =
+                                read_result_file::<CompileTimeResultSet>(&f.path())?;
+                            merge_metadata(&mut merged_metadata, metadata, &f.path());
 
                             merged_stats.append(
                                 &mut data
@@ -56,11 +63,11 @@ pub fn merge_compile_time_stats(
     create_dir_all(&out_dir)?;
     serde_json::to_writer(
         BufWriter::new(File::create(&out_dir.join("merged-stats.json"))?),
-        &merged_stats,
+        &ResultFile::new(merged_metadata.clone(), &merged_stats),
     )?;
     serde_json::to_writer(
         BufWriter::new(File::create(&out_dir.join("merged-data.json"))?),
-        &merged_data,
+        &ResultFile::new(merged_metadata, &merged_data),
     )?;
 
     Ok(out_dir)
@@ -68,14 +75,11 @@ pub fn merge_compile_time_stats(
 
 #[cfg(test)]
 mod test_merge_stat {
-    use std::{
-        fs::{remove_file, File},
-        io::BufReader,
-        path::PathBuf,
-    };
+    use std::{fs::remove_file, path::PathBuf};
 
     use collector::{
-        benchmark::profile::Profile, statistics::compile_time_stat::CompileTimeStatistics,
+        benchmark::profile::Profile,
+        statistics::{compile_time_stat::CompileTimeStatistics, metadata::read_result_file},
     };
 
     use super::merge_compile_time_stats;
@@ -101,8 +105,7 @@ mod test_merge_stat {
             out_dir,
         );
 
-        let stats: CompileTimeStatistics =
-            serde_json::from_reader(BufReader::new(File::open(&out_stats).unwrap())).unwrap();
+        let stats: CompileTimeStatistics = read_result_file(&out_stats).unwrap().data;
 
         assert_eq!(12, stats.len());
 
@@ -1,2 +1,23 @@
 pub mod merge_runtime_stat;
 pub mod merge_stat;
+
+use std::path::Path;
+
+use collector::statistics::metadata::RunMetadata;
+
+/// Keep the metadata of the first merged file, warning about later files
+/// measured on an incompatible host.
+fn merge_metadata(merged: &mut Option<RunMetadata>, metadata: Option<RunMetadata>, path: &Path) {
+    match (merged.as_ref(), metadata) {
+        (None, metadata) => *merged = metadata,
+        (Some(m), Some(metadata)) => {
+            if let Some(reason) = m.incompatibility(&metadata) {
+                eprintln!(
+                    "Warning: {:?} was measured on an incompatible host ({}).",
+                    path, reason
+                );
+            }
+        }
+        (Some(_), None) => (),
+    }
+}
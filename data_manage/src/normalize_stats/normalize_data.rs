@@ -1,20 +1,19 @@
 use std::{
     collections::HashMap,
     fs::File,
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    io::BufWriter,
+    path::{Path, PathBuf},
 };
 
 use collector::statistics::{
     compile_time_stat::{CompileTimeResultSet, CompileTimeStatistics},
+    metadata::read_result_file,
     statistic::Statistics,
 };
 
-pub fn normalize_stat(stats: &PathBuf, out_path: PathBuf) -> anyhow::Result<PathBuf> {
-    let normalized_stat = normalize_compile_time_stat(
-        &serde_json::from_reader(BufReader::new(File::open(&stats)?))?,
-        &"wall-time".to_string(),
-    );
+pub fn normalize_stat(stats: &Path, out_path: PathBuf) -> anyhow::Result<PathBuf> {
+    let normalized_stat =
+        normalize_compile_time_stat(&read_result_file(stats)?.data, &"wall-time".to_string());
     serde_json::to_writer(BufWriter::new(File::create(&out_path)?), &normalized_stat)?;
 
     Ok(out_path)
@@ -7,7 +7,10 @@ use std::{
 
 use collector::{
     mir_analyze::data::table_data::TableDatas,
-    statistics::{compile_time_stat::CompileTimeStatistics, runtime_stat::RuntimeStatistics},
+    statistics::{
+        compile_time_stat::CompileTimeStatistics, metadata::read_result_file,
+        runtime_stat::RuntimeStatistics,
+    },
 };
 
 pub fn merge_metrics_on_table_data(
@@ -38,7 +41,7 @@ pub fn merge_metrics_from_compile_time_stats_to_table_data(
         BufWriter::new(File::create(out_path)?),
         &merge_metrics_from_compile_time_stats(
             serde_json::from_reader(BufReader::new(File::open(table_data_path)?))?,
-            serde_json::from_reader(BufReader::new(File::open(stats_path)?))?,
+            read_result_file(stats_path)?.data,
             new_metrics,
         ),
     )?;
@@ -56,7 +59,7 @@ pub fn merge_runtime_metrics_to_table_data(
         BufWriter::new(File::create(out_path)?),
         &merge_metrics_from_runtime_stats(
             serde_json::from_reader(BufReader::new(File::open(table_data_path)?))?,
-            serde_json::from_reader(BufReader::new(File::open(stats_path)?))?,
+            read_result_file(stats_path)?.data,
             new_metrics,
         ),
     )?;